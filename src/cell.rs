@@ -2,19 +2,21 @@ use std::num::ParseFloatError;
 
 use xmlwriter::XmlWriter;
 
-use crate::{traits::XMLString, xml_templates::style::FontStyle};
+use crate::{
+    escape::{check_string_length, escape_xml, write_string},
+    hyperlink::Hyperlink,
+    rich_text::{self, Run},
+    traits::XMLString,
+    xml_templates::style::FontStyle,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone)]
 pub struct CellStyle {
     pub font_style: FontStyle,
-}
-
-impl Default for CellStyle {
-    fn default() -> Self {
-        CellStyle {
-            font_style: FontStyle::default(),
-        }
-    }
+    // format code, eg. `0.00%` or `yyyy-mm-dd`, None for General.
+    pub number_format: Option<String>,
+    // the cell xf of a read style sheet, its fill, border and alignment are kept.
+    pub(crate) base_xf: Option<usize>,
 }
 
 #[derive(Debug)]
@@ -29,16 +31,35 @@ pub enum CellValue {
     CFomula(String),
     CBool(bool),
     CInString(String),
+    // moved to the shared strings when the sheet is added to the work book.
+    CRichString(Vec<Run>),
     CDate(String),
+    // an error value, eg. #DIV/0!, of a read cell
+    CError(String),
     Empty,
 }
 
+/// how a formula is shared with other cells
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum FormulaKind {
+    #[default]
+    Normal,
+    /// one formula for the cells of `range` with the same index, only the first of them
+    /// has the text; the others have an empty formula.
+    Shared { index: u32, range: Option<String> },
+    /// an array formula whose result fills `range`, eg. `C1:C3`
+    Array { range: String },
+}
+
 #[derive(Debug)]
 pub struct Cell {
     pub value: CellValue,
     formula: Option<String>,
+    formula_kind: FormulaKind,
     attributes: CellAttributes,
     cell_style: Option<CellStyle>,
+    // moved to the sheet when the sheet is added to the work book.
+    hyperlink: Option<Hyperlink>,
 }
 
 impl Cell {
@@ -51,27 +72,69 @@ impl Cell {
         Cell {
             value: cell_value,
             formula: None,
+            formula_kind: FormulaKind::Normal,
+            attributes: CellAttributes {
+                reference: Some(reference),
+                style_index: None,
+            },
+            cell_style: None,
+            hyperlink: None,
+        }
+    }
+
+    /// a cell of any value, eg. a bool or a date read from a file
+    pub fn new(value: CellValue, reference: String) -> Cell {
+        Cell {
+            value,
+            formula: None,
+            formula_kind: FormulaKind::Normal,
             attributes: CellAttributes {
                 reference: Some(reference),
                 style_index: None,
             },
             cell_style: None,
+            hyperlink: None,
         }
     }
 
+    /// a string with differently formatted parts
+    pub fn from_rich_string(runs: Vec<Run>, reference: String) -> Cell {
+        Cell::new(CellValue::CRichString(runs), reference)
+    }
+
     pub fn from_number(value: String, reference: String) -> Result<Cell, ParseFloatError> {
         let _ = value.parse::<f64>()?;
         Ok(Cell {
             value: CellValue::CNumber(value),
             formula: None,
+            formula_kind: FormulaKind::Normal,
             attributes: CellAttributes {
                 reference: Some(reference),
                 style_index: None,
             },
             cell_style: None,
+            hyperlink: None,
         })
     }
 
+    /// the formula of the cell, without the leading `=`; the value stays as the last result.
+    pub fn set_formula(&mut self, formula: &str) {
+        self.formula = Some(formula.trim_start_matches('=').to_string());
+    }
+
+    pub fn get_formula(&self) -> Option<&str> {
+        self.formula.as_deref()
+    }
+
+    /// share the formula with other cells or make it an array formula
+    pub fn set_formula_kind(&mut self, kind: FormulaKind) {
+        self.formula_kind = kind;
+    }
+
+    pub fn get_formula_kind(&self) -> &FormulaKind {
+        &self.formula_kind
+    }
+
     pub fn get_attributes(&self) -> &CellAttributes {
         &self.attributes
     }
@@ -80,9 +143,48 @@ impl Cell {
         &self.cell_style
     }
 
+    pub fn set_style(&mut self, style: CellStyle) {
+        self.cell_style = Some(style);
+    }
+
+    /// the format code of the cell, None for General
+    pub fn number_format(&self) -> Option<&str> {
+        self.cell_style
+            .as_ref()
+            .and_then(|s| s.number_format.as_deref())
+    }
+
     pub fn set_font_style(&mut self, style: FontStyle) {
-        self.cell_style = Some(CellStyle { font_style: style });
+        self.cell_style
+            .get_or_insert_with(CellStyle::default)
+            .font_style = style;
+    }
+
+    /// how a number shows, eg. `0.00%` or `yyyy-mm-dd`
+    pub fn set_number_format(&mut self, format_code: &str) {
+        self.cell_style
+            .get_or_insert_with(CellStyle::default)
+            .number_format = Some(format_code.to_string());
+    }
+    /// link this cell to a url, email, file or a location like `Sheet2!A1`
+    pub fn set_hyperlink(&mut self, target: &str, tooltip: Option<&str>) {
+        let reference = self.attributes.reference.clone().unwrap_or_default();
+        self.hyperlink = Some(Hyperlink::new(&reference, target, tooltip));
     }
+
+    pub fn take_hyperlink(&mut self) -> Option<Hyperlink> {
+        self.hyperlink.take()
+    }
+
+    /// fails when the text of the cell is longer than excel allows
+    pub fn check_length(&self) -> Result<(), &'static str> {
+        match &self.value {
+            CellValue::CString(text) | CellValue::CInString(text) => check_string_length(text),
+            CellValue::CRichString(runs) => check_string_length(&rich_text::plain_text(runs)),
+            _ => Ok(()),
+        }
+    }
+
     pub fn set_style_index(&mut self, index: usize) {
         self.attributes.style_index = Some(index.to_string());
     }
@@ -92,55 +194,69 @@ impl XMLString for Cell {
     fn to_xml(self, writer: &mut XmlWriter) {
         writer.start_element("c");
         writer.write_attribute("r", &self.attributes.reference.unwrap());
-        if let Some(formula) = self.formula {
-            writer.start_element("formula");
-            writer.write_text(&formula);
-            writer.end_element();
-        }
         if let Some(style) = self.attributes.style_index {
             writer.write_attribute("s", &style);
         }
-        match self.value {
-            CellValue::CString(v) => {
-                writer.write_attribute("t", "s");
-                writer.start_element("v");
-                writer.write_text(&v);
-                writer.end_element();
+        let cell_type = match &self.value {
+            CellValue::CString(_) => Some("s"),
+            CellValue::CNumber(_) => Some("n"),
+            CellValue::CFomula(_) => Some("str"),
+            CellValue::CBool(_) => Some("b"),
+            CellValue::CInString(_) | CellValue::CRichString(_) => Some("inlineStr"),
+            CellValue::CDate(_) => Some("d"),
+            CellValue::CError(_) => Some("e"),
+            CellValue::Empty => None,
+        };
+        if let Some(cell_type) = cell_type {
+            writer.write_attribute("t", cell_type);
+        }
+        // the formula comes before its last value
+        if let Some(formula) = self.formula {
+            writer.start_element("f");
+            match self.formula_kind {
+                FormulaKind::Normal => {}
+                FormulaKind::Shared { index, range } => {
+                    writer.write_attribute("t", "shared");
+                    if let Some(range) = range {
+                        writer.write_attribute("ref", &range);
+                    }
+                    writer.write_attribute("si", &index);
+                }
+                FormulaKind::Array { range } => {
+                    writer.write_attribute("t", "array");
+                    writer.write_attribute("ref", &range);
+                }
             }
-            CellValue::CNumber(v) => {
-                writer.write_attribute("t", "n");
-                writer.start_element("v");
-                writer.write_text(&v);
-                writer.end_element();
+            if !formula.is_empty() {
+                writer.write_text(&escape_xml(&formula));
             }
-            CellValue::CFomula(v) => {
-                writer.write_attribute("t", "str");
+            writer.end_element();
+        }
+        match self.value {
+            CellValue::CString(v)
+            | CellValue::CNumber(v)
+            | CellValue::CFomula(v)
+            | CellValue::CDate(v)
+            | CellValue::CError(v) => {
                 writer.start_element("v");
-                writer.write_text(&v);
+                writer.write_text(&escape_xml(&v));
                 writer.end_element();
             }
             CellValue::CBool(v) => {
-                writer.write_attribute("t", "b");
                 writer.start_element("v");
-                writer.write_text(&v.to_string());
+                writer.write_text(if v { "1" } else { "0" });
                 writer.end_element();
             }
-
             CellValue::CInString(v) => {
-                writer.write_attribute("t", "inlineStr");
                 writer.start_element("is");
-                writer.start_element("t");
-                writer.write_text(&v);
-                writer.end_element();
+                write_string(writer, &v);
                 writer.end_element();
             }
-            CellValue::CDate(v) => {
-                writer.write_attribute("t", "d");
-                writer.start_element("v");
-                writer.write_text(&v);
+            CellValue::CRichString(runs) => {
+                writer.start_element("is");
+                rich_text::write_runs(writer, &runs);
                 writer.end_element();
             }
-
             CellValue::Empty => {}
         }
 
@@ -0,0 +1,744 @@
+// charts (xl/charts/chartN.xml), series refer to ranges of the sheets.
+
+use xmlwriter::{Indent, Options, XmlWriter};
+
+use crate::{escape::escape_xml, image::Anchor};
+
+static C_XMLNS: &str = "http://schemas.openxmlformats.org/drawingml/2006/chart";
+static A_XMLNS: &str = "http://schemas.openxmlformats.org/drawingml/2006/main";
+static R_XMLNS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+// ids of the category (x) and value (y) axes, and of the secondary axes of a combined chart.
+static CAT_AX_ID: &str = "50010001";
+static VAL_AX_ID: &str = "50010002";
+static SECONDARY_CAT_AX_ID: &str = "50010003";
+static SECONDARY_VAL_AX_ID: &str = "50010004";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartType {
+    Column,
+    Bar,
+    Line,
+    Pie,
+    Scatter,
+    Area,
+}
+
+/// how the series of a column, bar, line or area chart are put together.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Grouping {
+    // side by side, or over each other for lines and areas
+    #[default]
+    Standard,
+    Stacked,
+    PercentStacked,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum LegendPosition {
+    Right,
+    Left,
+    Top,
+    Bottom,
+    TopRight,
+}
+
+impl LegendPosition {
+    fn val(&self) -> &'static str {
+        match self {
+            LegendPosition::Right => "r",
+            LegendPosition::Left => "l",
+            LegendPosition::Top => "t",
+            LegendPosition::Bottom => "b",
+            LegendPosition::TopRight => "tr",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Trendline {
+    Linear,
+    Exponential,
+    // period, 2 - 255 points
+    MovingAverage(u32),
+}
+
+/// size of the error bars.
+#[derive(Debug, Clone, Copy)]
+pub enum ErrorBarValue {
+    Fixed(f64),
+    Percentage(f64),
+    // number of standard deviations
+    StandardDeviation(f64),
+    StandardError,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ErrorBarDirection {
+    #[default]
+    Both,
+    Plus,
+    Minus,
+}
+
+#[derive(Debug, Clone)]
+pub struct ErrorBars {
+    value: ErrorBarValue,
+    direction: ErrorBarDirection,
+    end_cap: bool,
+}
+
+impl ErrorBars {
+    pub fn new(value: ErrorBarValue) -> Self {
+        ErrorBars {
+            value,
+            direction: ErrorBarDirection::Both,
+            end_cap: true,
+        }
+    }
+
+    pub fn direction(mut self, direction: ErrorBarDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    pub fn end_cap(mut self, end_cap: bool) -> Self {
+        self.end_cap = end_cap;
+        self
+    }
+
+    fn to_xml(&self, writer: &mut XmlWriter, is_scatter: bool) {
+        writer.start_element("c:errBars");
+        // scatter charts can have x error bars too, these are on y.
+        if is_scatter {
+            write_val(writer, "c:errDir", "y");
+        }
+        let direction = match self.direction {
+            ErrorBarDirection::Both => "both",
+            ErrorBarDirection::Plus => "plus",
+            ErrorBarDirection::Minus => "minus",
+        };
+        write_val(writer, "c:errBarType", direction);
+        let (kind, value) = match self.value {
+            ErrorBarValue::Fixed(value) => ("fixedVal", Some(value)),
+            ErrorBarValue::Percentage(value) => ("percentage", Some(value)),
+            ErrorBarValue::StandardDeviation(value) => ("stdDev", Some(value)),
+            ErrorBarValue::StandardError => ("stdErr", None),
+        };
+        write_val(writer, "c:errValType", kind);
+        write_val(writer, "c:noEndCap", if self.end_cap { "0" } else { "1" });
+        if let Some(value) = value {
+            write_val(writer, "c:val", &value.to_string());
+        }
+        writer.end_element();
+    }
+}
+
+/// A series of a chart. Ranges are formulas like `Sheet1!$B$2:$B$5`,
+/// a name starting with `=` is a reference to a cell, otherwise it is the name itself.
+#[derive(Debug, Clone)]
+pub struct ChartSeries {
+    values: String,
+    categories: Option<String>,
+    name: Option<String>,
+    // RGB hex, eg. 4472C4
+    color: Option<String>,
+    trendline: Option<Trendline>,
+    error_bars: Option<ErrorBars>,
+}
+
+impl ChartSeries {
+    pub fn new(values: &str) -> Self {
+        ChartSeries {
+            values: values.trim_start_matches('=').to_string(),
+            categories: None,
+            name: None,
+            color: None,
+            trendline: None,
+            error_bars: None,
+        }
+    }
+
+    /// category labels, or the x values of a scatter chart.
+    pub fn categories(mut self, range: &str) -> Self {
+        self.categories = Some(range.trim_start_matches('=').to_string());
+        self
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// series color as RGB hex, eg. `4472C4`
+    pub fn color(mut self, rgb: &str) -> Self {
+        self.color = Some(rgb.trim_start_matches('#').to_ascii_uppercase());
+        self
+    }
+
+    pub fn trendline(mut self, trendline: Trendline) -> Self {
+        self.trendline = Some(trendline);
+        self
+    }
+
+    pub fn error_bars(mut self, error_bars: ErrorBars) -> Self {
+        self.error_bars = Some(error_bars);
+        self
+    }
+}
+
+/// A chart, build it up and insert it in a sheet.
+///
+/// ```ignore
+/// // columns with a line on a secondary axis
+/// let chart = Chart::new(ChartType::Column)
+///     .add_series(ChartSeries::new("Sheet1!$B$2:$B$13"))
+///     .combine(
+///         Chart::new(ChartType::Line)
+///             .add_series(ChartSeries::new("Sheet1!$C$2:$C$13"))
+///             .secondary_axis(true),
+///     );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Chart {
+    chart_type: ChartType,
+    series: Vec<ChartSeries>,
+    grouping: Grouping,
+    title: Option<String>,
+    x_axis_title: Option<String>,
+    y_axis_title: Option<String>,
+    x_axis_number_format: Option<String>,
+    y_axis_number_format: Option<String>,
+    date_axis: bool,
+    legend: Option<LegendPosition>,
+    data_labels: bool,
+    style: Option<u8>,
+    // a second chart drawn in the same plot area
+    combined: Option<Box<Chart>>,
+    secondary_axis: bool,
+}
+
+impl Chart {
+    pub fn new(chart_type: ChartType) -> Self {
+        Chart {
+            chart_type,
+            series: vec![],
+            grouping: Grouping::Standard,
+            title: None,
+            x_axis_title: None,
+            y_axis_title: None,
+            x_axis_number_format: None,
+            y_axis_number_format: None,
+            date_axis: false,
+            legend: Some(LegendPosition::Right),
+            data_labels: false,
+            style: None,
+            combined: None,
+            secondary_axis: false,
+        }
+    }
+
+    pub fn add_series(mut self, series: ChartSeries) -> Self {
+        self.series.push(series);
+        self
+    }
+
+    /// stack the series of a column, bar, line or area chart.
+    pub fn grouping(mut self, grouping: Grouping) -> Self {
+        self.grouping = grouping;
+        self
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    pub fn x_axis_title(mut self, title: &str) -> Self {
+        self.x_axis_title = Some(title.to_string());
+        self
+    }
+
+    pub fn y_axis_title(mut self, title: &str) -> Self {
+        self.y_axis_title = Some(title.to_string());
+        self
+    }
+
+    /// number format of the x axis labels, eg. `mmm yy`
+    pub fn x_axis_number_format(mut self, format: &str) -> Self {
+        self.x_axis_number_format = Some(format.to_string());
+        self
+    }
+
+    /// number format of the y axis labels, eg. `#,##0.00`
+    pub fn y_axis_number_format(mut self, format: &str) -> Self {
+        self.y_axis_number_format = Some(format.to_string());
+        self
+    }
+
+    /// the categories are dates, spaced by time instead of evenly.
+    pub fn date_axis(mut self, date_axis: bool) -> Self {
+        self.date_axis = date_axis;
+        self
+    }
+
+    /// legend position, `None` hides the legend.
+    pub fn legend(mut self, position: Option<LegendPosition>) -> Self {
+        self.legend = position;
+        self
+    }
+
+    /// show the value of every point.
+    pub fn data_labels(mut self, show: bool) -> Self {
+        self.data_labels = show;
+        self
+    }
+
+    /// one of excel's built in chart styles, 1 - 48.
+    pub fn style(mut self, style: u8) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    /// draw the series of another chart over this one, eg. a line over columns.
+    /// title, legend and style of the other chart are not used.
+    pub fn combine(mut self, chart: Chart) -> Self {
+        self.combined = Some(Box::new(chart));
+        self
+    }
+
+    /// plot against a second value axis on the right, for a combined chart.
+    pub fn secondary_axis(mut self, secondary_axis: bool) -> Self {
+        self.secondary_axis = secondary_axis;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.series.is_empty() {
+            return Err("Chart has no series");
+        }
+        if let Some(style) = self.style {
+            if !(1..=48).contains(&style) {
+                return Err("Chart style must be between 1 and 48");
+            }
+        }
+        if self.grouping != Grouping::Standard
+            && matches!(self.chart_type, ChartType::Pie | ChartType::Scatter)
+        {
+            return Err("Pie and scatter charts can not be stacked");
+        }
+        for series in self.series.iter() {
+            if self.chart_type == ChartType::Pie
+                && (series.trendline.is_some() || series.error_bars.is_some())
+            {
+                return Err("Pie charts have no trendlines or error bars");
+            }
+            if let Some(Trendline::MovingAverage(period)) = series.trendline {
+                if !(2..=255).contains(&period) {
+                    return Err("Moving average period must be between 2 and 255");
+                }
+            }
+        }
+        if let Some(combined) = &self.combined {
+            let can_combine =
+                |chart_type| !matches!(chart_type, ChartType::Pie | ChartType::Scatter);
+            if !can_combine(self.chart_type) || !can_combine(combined.chart_type) {
+                return Err("Pie and scatter charts can not be combined");
+            }
+            if combined.combined.is_some() {
+                return Err("Only two charts can be combined");
+            }
+            combined.validate()?;
+        }
+        Ok(())
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut writer = XmlWriter::new(Options {
+            indent: Indent::None,
+            ..Default::default()
+        });
+        writer.write_declaration();
+        writer.start_element("c:chartSpace");
+        writer.write_attribute("xmlns:c", C_XMLNS);
+        writer.write_attribute("xmlns:a", A_XMLNS);
+        writer.write_attribute("xmlns:r", R_XMLNS);
+
+        if let Some(style) = self.style {
+            write_val(&mut writer, "c:style", &style.to_string());
+        }
+
+        writer.start_element("c:chart");
+        if let Some(title) = &self.title {
+            write_title(&mut writer, title);
+            write_val(&mut writer, "c:autoTitleDeleted", "0");
+        } else {
+            write_val(&mut writer, "c:autoTitleDeleted", "1");
+        }
+
+        writer.start_element("c:plotArea");
+        writer.start_element("c:layout");
+        writer.end_element();
+        self.write_plot(&mut writer, 0, (CAT_AX_ID, VAL_AX_ID));
+        let secondary = self.combined.as_deref().filter(|c| c.secondary_axis);
+        if let Some(combined) = &self.combined {
+            // series are numbered across both charts
+            let axes = if combined.secondary_axis {
+                (SECONDARY_CAT_AX_ID, SECONDARY_VAL_AX_ID)
+            } else {
+                (CAT_AX_ID, VAL_AX_ID)
+            };
+            combined.write_plot(&mut writer, self.series.len(), axes);
+        }
+        if self.chart_type != ChartType::Pie {
+            self.write_axes(&mut writer, (CAT_AX_ID, VAL_AX_ID), false);
+        }
+        if let Some(secondary) = secondary {
+            secondary.write_axes(
+                &mut writer,
+                (SECONDARY_CAT_AX_ID, SECONDARY_VAL_AX_ID),
+                true,
+            );
+        }
+        writer.end_element();
+
+        if let Some(legend) = self.legend {
+            writer.start_element("c:legend");
+            write_val(&mut writer, "c:legendPos", legend.val());
+            write_val(&mut writer, "c:overlay", "0");
+            writer.end_element();
+        }
+        write_val(&mut writer, "c:plotVisOnly", "1");
+        writer.end_element();
+
+        writer.end_document()
+    }
+
+    fn grouping_val(&self) -> &'static str {
+        match (self.grouping, self.chart_type) {
+            (Grouping::Standard, ChartType::Column | ChartType::Bar) => "clustered",
+            (Grouping::Standard, _) => "standard",
+            (Grouping::Stacked, _) => "stacked",
+            (Grouping::PercentStacked, _) => "percentStacked",
+        }
+    }
+
+    // `first_index` is the number of series before this chart, `axes` the (category, value) axis ids.
+    fn write_plot(&self, writer: &mut XmlWriter, first_index: usize, axes: (&str, &str)) {
+        match self.chart_type {
+            ChartType::Column | ChartType::Bar => {
+                writer.start_element("c:barChart");
+                let bar_dir = if self.chart_type == ChartType::Bar {
+                    "bar"
+                } else {
+                    "col"
+                };
+                write_val(writer, "c:barDir", bar_dir);
+                write_val(writer, "c:grouping", self.grouping_val());
+            }
+            ChartType::Line => {
+                writer.start_element("c:lineChart");
+                write_val(writer, "c:grouping", self.grouping_val());
+            }
+            ChartType::Pie => {
+                writer.start_element("c:pieChart");
+            }
+            ChartType::Scatter => {
+                writer.start_element("c:scatterChart");
+                write_val(writer, "c:scatterStyle", "lineMarker");
+            }
+            ChartType::Area => {
+                writer.start_element("c:areaChart");
+                write_val(writer, "c:grouping", self.grouping_val());
+            }
+        }
+        let vary_colors = if self.chart_type == ChartType::Pie {
+            "1"
+        } else {
+            "0"
+        };
+        write_val(writer, "c:varyColors", vary_colors);
+
+        for (i, series) in self.series.iter().enumerate() {
+            self.write_series(writer, first_index + i, series);
+        }
+
+        match self.chart_type {
+            ChartType::Column | ChartType::Bar => {
+                write_val(writer, "c:gapWidth", "150");
+                // stacked bars sit on top of each other
+                if self.grouping != Grouping::Standard {
+                    write_val(writer, "c:overlap", "100");
+                }
+            }
+            ChartType::Line => write_val(writer, "c:marker", "1"),
+            ChartType::Pie => write_val(writer, "c:firstSliceAng", "0"),
+            _ => {}
+        }
+        if self.chart_type != ChartType::Pie {
+            write_val(writer, "c:axId", axes.0);
+            write_val(writer, "c:axId", axes.1);
+        }
+        writer.end_element();
+    }
+
+    fn write_series(&self, writer: &mut XmlWriter, index: usize, series: &ChartSeries) {
+        writer.start_element("c:ser");
+        write_val(writer, "c:idx", &index.to_string());
+        write_val(writer, "c:order", &index.to_string());
+
+        if let Some(name) = &series.name {
+            writer.start_element("c:tx");
+            if let Some(reference) = name.strip_prefix('=') {
+                write_reference(writer, "c:strRef", reference);
+            } else {
+                writer.start_element("c:v");
+                writer.write_text(&escape_xml(name));
+                writer.end_element();
+            }
+            writer.end_element();
+        }
+
+        if let Some(color) = &series.color {
+            writer.start_element("c:spPr");
+            let is_line = matches!(self.chart_type, ChartType::Line | ChartType::Scatter);
+            if is_line {
+                writer.start_element("a:ln");
+            }
+            writer.start_element("a:solidFill");
+            writer.start_element("a:srgbClr");
+            writer.write_attribute("val", color);
+            writer.end_element();
+            writer.end_element();
+            if is_line {
+                writer.end_element();
+            }
+            writer.end_element();
+        }
+
+        if matches!(self.chart_type, ChartType::Column | ChartType::Bar) {
+            write_val(writer, "c:invertIfNegative", "0");
+        }
+
+        if self.data_labels {
+            write_data_labels(writer);
+        }
+
+        if let Some(trendline) = series.trendline {
+            writer.start_element("c:trendline");
+            match trendline {
+                Trendline::Linear => write_val(writer, "c:trendlineType", "linear"),
+                Trendline::Exponential => write_val(writer, "c:trendlineType", "exp"),
+                Trendline::MovingAverage(period) => {
+                    write_val(writer, "c:trendlineType", "movingAvg");
+                    write_val(writer, "c:period", &period.to_string());
+                }
+            }
+            writer.end_element();
+        }
+
+        if let Some(error_bars) = &series.error_bars {
+            error_bars.to_xml(writer, self.chart_type == ChartType::Scatter);
+        }
+
+        if self.chart_type == ChartType::Scatter {
+            if let Some(categories) = &series.categories {
+                writer.start_element("c:xVal");
+                write_reference(writer, "c:numRef", categories);
+                writer.end_element();
+            }
+            writer.start_element("c:yVal");
+            write_reference(writer, "c:numRef", &series.values);
+            writer.end_element();
+        } else {
+            if let Some(categories) = &series.categories {
+                writer.start_element("c:cat");
+                // dates are numbers
+                let kind = if self.date_axis {
+                    "c:numRef"
+                } else {
+                    "c:strRef"
+                };
+                write_reference(writer, kind, categories);
+                writer.end_element();
+            }
+            writer.start_element("c:val");
+            write_reference(writer, "c:numRef", &series.values);
+            writer.end_element();
+        }
+
+        if matches!(self.chart_type, ChartType::Line | ChartType::Scatter) {
+            write_val(writer, "c:smooth", "0");
+        }
+        writer.end_element();
+    }
+
+    // the secondary category axis is hidden and its value axis is on the other side.
+    fn write_axes(&self, writer: &mut XmlWriter, axes: (&str, &str), secondary: bool) {
+        let (cat_ax_id, val_ax_id) = axes;
+        // bar charts lie on their side, the categories go up the left.
+        let (cat_pos, val_pos) = match (self.chart_type == ChartType::Bar, secondary) {
+            (false, false) => ("b", "l"),
+            (false, true) => ("b", "r"),
+            (true, false) => ("l", "b"),
+            (true, true) => ("l", "t"),
+        };
+
+        // scatter charts have values on both axes.
+        let cat_element = if self.chart_type == ChartType::Scatter {
+            "c:valAx"
+        } else if self.date_axis {
+            "c:dateAx"
+        } else {
+            "c:catAx"
+        };
+        writer.start_element(cat_element);
+        write_val(writer, "c:axId", cat_ax_id);
+        write_scaling(writer);
+        write_val(writer, "c:delete", if secondary { "1" } else { "0" });
+        write_val(writer, "c:axPos", cat_pos);
+        if let Some(title) = self.x_axis_title.as_ref().filter(|_| !secondary) {
+            write_title(writer, title);
+        }
+        if let Some(format) = &self.x_axis_number_format {
+            write_number_format(writer, format, false);
+        } else if self.date_axis {
+            write_number_format(writer, "dd/mm/yyyy", true);
+        }
+        write_val(writer, "c:tickLblPos", "nextTo");
+        write_val(writer, "c:crossAx", val_ax_id);
+        write_val(writer, "c:crosses", "autoZero");
+        match cat_element {
+            "c:valAx" => write_val(writer, "c:crossBetween", "midCat"),
+            "c:dateAx" => {
+                write_val(writer, "c:auto", "1");
+                write_val(writer, "c:lblOffset", "100");
+                write_val(writer, "c:baseTimeUnit", "days");
+            }
+            _ => {
+                write_val(writer, "c:auto", "1");
+                write_val(writer, "c:lblAlgn", "ctr");
+                write_val(writer, "c:lblOffset", "100");
+            }
+        }
+        writer.end_element();
+
+        writer.start_element("c:valAx");
+        write_val(writer, "c:axId", val_ax_id);
+        write_scaling(writer);
+        write_val(writer, "c:delete", "0");
+        write_val(writer, "c:axPos", val_pos);
+        if !secondary {
+            writer.start_element("c:majorGridlines");
+            writer.end_element();
+        }
+        if let Some(title) = &self.y_axis_title {
+            write_title(writer, title);
+        }
+        if let Some(format) = &self.y_axis_number_format {
+            write_number_format(writer, format, false);
+        } else if self.grouping == Grouping::PercentStacked {
+            write_number_format(writer, "0%", true);
+        } else {
+            write_number_format(writer, "General", true);
+        }
+        write_val(writer, "c:tickLblPos", "nextTo");
+        write_val(writer, "c:crossAx", cat_ax_id);
+        // the secondary value axis crosses at the far end of the categories
+        write_val(
+            writer,
+            "c:crosses",
+            if secondary { "max" } else { "autoZero" },
+        );
+        let cross_between = if self.chart_type == ChartType::Scatter {
+            "midCat"
+        } else {
+            "between"
+        };
+        write_val(writer, "c:crossBetween", cross_between);
+        writer.end_element();
+    }
+}
+
+/// where a chart sits on the sheet, in pixels.
+#[derive(Debug, Clone)]
+pub struct ChartOptions {
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub width: u32,
+    pub height: u32,
+    pub anchor: Anchor,
+}
+
+impl Default for ChartOptions {
+    // excel's default chart size
+    fn default() -> Self {
+        Self {
+            x_offset: 0,
+            y_offset: 0,
+            width: 480,
+            height: 288,
+            anchor: Anchor::TwoCell,
+        }
+    }
+}
+
+// <name val="value"/>
+fn write_val(writer: &mut XmlWriter, name: &str, value: &str) {
+    writer.start_element(name);
+    writer.write_attribute("val", value);
+    writer.end_element();
+}
+
+fn write_reference(writer: &mut XmlWriter, kind: &str, formula: &str) {
+    writer.start_element(kind);
+    writer.start_element("c:f");
+    writer.write_text(&escape_xml(formula));
+    writer.end_element();
+    writer.end_element();
+}
+
+fn write_number_format(writer: &mut XmlWriter, format: &str, source_linked: bool) {
+    writer.start_element("c:numFmt");
+    writer.write_attribute("formatCode", &escape_xml(format));
+    writer.write_attribute("sourceLinked", if source_linked { "1" } else { "0" });
+    writer.end_element();
+}
+
+fn write_scaling(writer: &mut XmlWriter) {
+    writer.start_element("c:scaling");
+    write_val(writer, "c:orientation", "minMax");
+    writer.end_element();
+}
+
+fn write_data_labels(writer: &mut XmlWriter) {
+    writer.start_element("c:dLbls");
+    write_val(writer, "c:showLegendKey", "0");
+    write_val(writer, "c:showVal", "1");
+    write_val(writer, "c:showCatName", "0");
+    write_val(writer, "c:showSerName", "0");
+    write_val(writer, "c:showPercent", "0");
+    write_val(writer, "c:showBubbleSize", "0");
+    writer.end_element();
+}
+
+fn write_title(writer: &mut XmlWriter, title: &str) {
+    writer.start_element("c:title");
+    writer.start_element("c:tx");
+    writer.start_element("c:rich");
+    writer.start_element("a:bodyPr");
+    writer.end_element();
+    writer.start_element("a:lstStyle");
+    writer.end_element();
+    writer.start_element("a:p");
+    writer.start_element("a:r");
+    writer.start_element("a:t");
+    writer.write_text(&escape_xml(title));
+    writer.end_element();
+    writer.end_element();
+    writer.end_element();
+    writer.end_element();
+    writer.end_element();
+    write_val(writer, "c:overlay", "0");
+    writer.end_element();
+}
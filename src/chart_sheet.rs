@@ -0,0 +1,114 @@
+// a sheet holding only a chart (xl/chartsheets/sheetN.xml), the chart sits in its own drawing.
+
+use xmlwriter::{Indent, Options, XmlWriter};
+
+use crate::{
+    chart::{Chart, ChartOptions},
+    drawing::{Drawing, DrawingNumbers},
+    image::Anchor,
+    page_setup::PageMargins,
+    xml_templates::relation_ship::{RelationShip, RS_DRAWING},
+};
+
+// the size excel gives the chart of a new chart sheet, in pixels.
+static CHART_WIDTH_PX: u32 = 910;
+static CHART_HEIGHT_PX: u32 = 661;
+
+pub struct ChartSheet {
+    pub name: String,
+    drawing: Drawing,
+    relation_ship: RelationShip,
+    // set once the sheet is added to a work book.
+    drawing_r_id: Option<String>,
+}
+
+impl ChartSheet {
+    pub fn new(name: &str, chart: Chart) -> Result<Self, &'static str> {
+        chart.validate()?;
+        let mut drawing = Drawing::new();
+        let options = ChartOptions {
+            width: CHART_WIDTH_PX,
+            height: CHART_HEIGHT_PX,
+            anchor: Anchor::Absolute,
+            ..Default::default()
+        };
+        drawing.add_chart(0, 0, chart, options);
+        Ok(ChartSheet {
+            name: name.to_string(),
+            drawing,
+            relation_ship: RelationShip::new(),
+            drawing_r_id: None,
+        })
+    }
+
+    pub fn get_drawing(&self) -> &Drawing {
+        &self.drawing
+    }
+
+    /// register the drawing of this sheet in its relationships,
+    /// called when the sheet is added to a work book.
+    pub(crate) fn link_parts(&mut self, drawing_numbers: &mut DrawingNumbers) {
+        self.drawing.link(drawing_numbers);
+        let r_id = self.relation_ship.add_relation(
+            RS_DRAWING,
+            &format!("../drawings/drawing{}.xml", self.drawing.number().unwrap()),
+            false,
+        );
+        self.drawing_r_id = Some(r_id);
+    }
+
+    /// the relationships, drawing and chart of this sheet, as (path in the package, content).
+    pub fn parts(&self, sheet_number: usize) -> Vec<(String, Vec<u8>)> {
+        let mut parts = vec![(
+            format!("xl/chartsheets/_rels/sheet{}.xml.rels", sheet_number),
+            self.relation_ship.to_xml().into_bytes(),
+        )];
+        parts.extend(self.drawing.parts());
+        parts
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut writer = XmlWriter::new(Options {
+            indent: Indent::None,
+            ..Default::default()
+        });
+        writer.write_declaration();
+        writer.start_element("chartsheet");
+        writer.write_attribute(
+            "xmlns",
+            "http://schemas.openxmlformats.org/spreadsheetml/2006/main",
+        );
+        writer.write_attribute(
+            "xmlns:r",
+            "http://schemas.openxmlformats.org/officeDocument/2006/relationships",
+        );
+
+        writer.start_element("sheetPr");
+        writer.end_element();
+        // the chart fills the window
+        writer.start_element("sheetViews");
+        writer.start_element("sheetView");
+        writer.write_attribute("zoomToFit", "1");
+        writer.write_attribute("workbookViewId", "0");
+        writer.end_element();
+        writer.end_element();
+
+        let margins = PageMargins::default();
+        writer.start_element("pageMargins");
+        writer.write_attribute("left", &margins.left.to_string());
+        writer.write_attribute("right", &margins.right.to_string());
+        writer.write_attribute("top", &margins.top.to_string());
+        writer.write_attribute("bottom", &margins.bottom.to_string());
+        writer.write_attribute("header", &margins.header.to_string());
+        writer.write_attribute("footer", &margins.footer.to_string());
+        writer.end_element();
+
+        if let Some(r_id) = &self.drawing_r_id {
+            writer.start_element("drawing");
+            writer.write_attribute("r:id", r_id);
+            writer.end_element();
+        }
+
+        writer.end_document()
+    }
+}
@@ -0,0 +1,586 @@
+// notes (legacy comments): xl/commentsN.xml and the vml shapes excel use to show them.
+
+use xmlwriter::{Indent, Options, XmlWriter};
+
+use crate::{
+    drawing::DrawingNumbers,
+    escape::escape_xml,
+    rich_text::{self, Run},
+    row::parse_cell_ref,
+    xml_templates::{doc_props::now_w3cdtf, style::FontStyle},
+};
+
+static COMMENTS_XMLNS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+
+// default size of a note box in pixels
+static DEFAULT_NOTE_WIDTH: u32 = 128;
+static DEFAULT_NOTE_HEIGHT: u32 = 74;
+
+// default column width and row height in pixels
+static COLUMN_WIDTH_PX: u32 = 64;
+static ROW_HEIGHT_PX: u32 = 20;
+
+#[derive(Debug, Clone)]
+pub struct Note {
+    reference: String,
+    // 0 based
+    row: usize,
+    column: usize,
+    author: String,
+    runs: Vec<Run>,
+    visible: bool,
+    width: u32,
+    height: u32,
+}
+
+impl Note {
+    pub fn new(cell_ref: &str, author: &str, text: &str) -> Result<Self, &'static str> {
+        let (row, column) = parse_cell_ref(cell_ref)?;
+        Ok(Note {
+            reference: cell_ref.replace('$', "").to_ascii_uppercase(),
+            row: row - 1,
+            column: column - 1,
+            author: author.to_string(),
+            runs: vec![Run::new(text)],
+            visible: false,
+            width: DEFAULT_NOTE_WIDTH,
+            height: DEFAULT_NOTE_HEIGHT,
+        })
+    }
+
+    /// show the note all the time instead of on hover.
+    pub fn set_visible(&mut self, visible: bool) -> &mut Self {
+        self.visible = visible;
+        self
+    }
+
+    /// size of the note box in pixels
+    pub fn set_size(&mut self, width: u32, height: u32) -> &mut Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// append formatted text to the note
+    pub fn add_run(&mut self, text: &str, font: FontStyle) -> &mut Self {
+        self.runs.push(Run::new(text).font(font));
+        self
+    }
+
+    pub fn get_reference(&self) -> &str {
+        &self.reference
+    }
+
+    pub fn get_author(&self) -> &str {
+        &self.author
+    }
+
+    pub fn get_text(&self) -> String {
+        rich_text::plain_text(&self.runs)
+    }
+}
+
+/// notes of one sheet
+#[derive(Debug, Default)]
+pub struct Notes {
+    notes: Vec<Note>,
+    // the first block of vml shape ids, set when the sheet is added to a work book
+    first_shape_block: usize,
+}
+
+// vml shape ids of a block, `block * 1024 + 1` to `block * 1024 + 1023`
+static SHAPES_PER_BLOCK: usize = 1023;
+
+fn writer_options() -> Options {
+    Options {
+        indent: Indent::None,
+        ..Default::default()
+    }
+}
+
+impl Notes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add a note, a note already on the same cell is replaced.
+    pub fn add(&mut self, note: Note) -> &mut Note {
+        self.notes.retain(|n| n.reference != note.reference);
+        self.notes.push(note);
+        self.notes.last_mut().unwrap()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Note> {
+        self.notes.iter()
+    }
+
+    /// take as many blocks of vml shape ids as the notes need.
+    pub(crate) fn link(&mut self, numbers: &mut DrawingNumbers) {
+        self.first_shape_block = numbers.next_shape_block;
+        numbers.next_shape_block += self.notes.len().div_ceil(SHAPES_PER_BLOCK).max(1);
+    }
+
+    fn authors(&self) -> Vec<&str> {
+        let mut authors: Vec<&str> = vec![];
+        for note in self.notes.iter() {
+            if !authors.contains(&note.author.as_str()) {
+                authors.push(&note.author);
+            }
+        }
+        authors
+    }
+
+    /// xl/commentsN.xml
+    pub fn to_comments_xml(&self) -> String {
+        let mut writer = XmlWriter::new(writer_options());
+        writer.write_declaration();
+        writer.start_element("comments");
+        writer.write_attribute("xmlns", COMMENTS_XMLNS);
+
+        let authors = self.authors();
+        writer.start_element("authors");
+        for author in authors.iter() {
+            writer.start_element("author");
+            writer.write_text(&escape_xml(author));
+            writer.end_element();
+        }
+        writer.end_element();
+
+        writer.start_element("commentList");
+        for note in self.notes.iter() {
+            let author_id = authors.iter().position(|a| *a == note.author).unwrap();
+            writer.start_element("comment");
+            writer.write_attribute("ref", &note.reference);
+            writer.write_attribute("authorId", &author_id.to_string());
+            write_runs(&mut writer, &note.runs);
+            writer.end_element();
+        }
+        writer.end_element();
+
+        writer.end_document()
+    }
+
+    /// xl/drawings/vmlDrawingN.vml, one text box shape per note.
+    pub fn to_vml_xml(&self) -> String {
+        let mut writer = XmlWriter::new(writer_options());
+        writer.start_element("xml");
+        writer.write_attribute("xmlns:v", "urn:schemas-microsoft-com:vml");
+        writer.write_attribute("xmlns:o", "urn:schemas-microsoft-com:office:office");
+        writer.write_attribute("xmlns:x", "urn:schemas-microsoft-com:office:excel");
+
+        writer.start_element("o:shapelayout");
+        writer.write_attribute("v:ext", "edit");
+        writer.start_element("o:idmap");
+        writer.write_attribute("v:ext", "edit");
+        let blocks = self.notes.len().div_ceil(SHAPES_PER_BLOCK).max(1);
+        let blocks: Vec<String> = (0..blocks)
+            .map(|b| (self.first_shape_block + b).to_string())
+            .collect();
+        writer.write_attribute("data", &blocks.join(","));
+        writer.end_element();
+        writer.end_element();
+
+        writer.start_element("v:shapetype");
+        writer.write_attribute("id", "_x0000_t202");
+        writer.write_attribute("coordsize", "21600,21600");
+        writer.write_attribute("o:spt", "202");
+        writer.write_attribute("path", "m,l,21600r21600,l21600,xe");
+        writer.start_element("v:stroke");
+        writer.write_attribute("joinstyle", "miter");
+        writer.end_element();
+        writer.start_element("v:path");
+        writer.write_attribute("gradientshapeok", "t");
+        writer.write_attribute("o:connecttype", "rect");
+        writer.end_element();
+        writer.end_element();
+
+        for (i, note) in self.notes.iter().enumerate() {
+            let block = self.first_shape_block + i / SHAPES_PER_BLOCK;
+            let shape_id = block * 1024 + i % SHAPES_PER_BLOCK + 1;
+            write_note_shape(&mut writer, note, shape_id, i + 1);
+        }
+
+        writer.end_document()
+    }
+}
+
+fn write_runs(writer: &mut XmlWriter, runs: &[Run]) {
+    writer.start_element("text");
+    rich_text::write_runs(writer, runs);
+    writer.end_element();
+}
+
+fn write_note_shape(writer: &mut XmlWriter, note: &Note, shape_id: usize, z_index: usize) {
+    // the box sits one column right of the cell, starting a row above.
+    let left_column = note.column + 1;
+    let top_row = note.row.saturating_sub(1);
+    let right_column = left_column + note.width.div_ceil(COLUMN_WIDTH_PX) as usize;
+    let bottom_row = top_row + note.height.div_ceil(ROW_HEIGHT_PX) as usize;
+
+    // points, 1px = 0.75pt
+    let style = format!(
+        "position:absolute;margin-left:{}pt;margin-top:{}pt;width:{}pt;height:{}pt;z-index:{};visibility:{}",
+        left_column as f64 * COLUMN_WIDTH_PX as f64 * 0.75 + 11.25,
+        top_row as f64 * ROW_HEIGHT_PX as f64 * 0.75 + 1.5,
+        note.width as f64 * 0.75,
+        note.height as f64 * 0.75,
+        z_index,
+        if note.visible { "visible" } else { "hidden" }
+    );
+
+    writer.start_element("v:shape");
+    writer.write_attribute("id", &format!("_x0000_s{}", shape_id));
+    writer.write_attribute("type", "#_x0000_t202");
+    writer.write_attribute("style", &style);
+    writer.write_attribute("fillcolor", "#ffffe1");
+    writer.write_attribute("o:insetmode", "auto");
+
+    writer.start_element("v:fill");
+    writer.write_attribute("color2", "#ffffe1");
+    writer.end_element();
+    writer.start_element("v:shadow");
+    writer.write_attribute("on", "t");
+    writer.write_attribute("color", "black");
+    writer.write_attribute("obscured", "t");
+    writer.end_element();
+    writer.start_element("v:path");
+    writer.write_attribute("o:connecttype", "none");
+    writer.end_element();
+    writer.start_element("v:textbox");
+    writer.write_attribute("style", "mso-direction-alt:auto");
+    writer.start_element("div");
+    writer.write_attribute("style", "text-align:left");
+    writer.end_element();
+    writer.end_element();
+
+    writer.start_element("x:ClientData");
+    writer.write_attribute("ObjectType", "Note");
+    writer.start_element("x:MoveWithCells");
+    writer.end_element();
+    writer.start_element("x:SizeWithCells");
+    writer.end_element();
+    writer.start_element("x:Anchor");
+    writer.write_text(&format!(
+        "{}, 15, {}, 10, {}, 15, {}, 4",
+        left_column, top_row, right_column, bottom_row
+    ));
+    writer.end_element();
+    writer.start_element("x:AutoFill");
+    writer.write_text("False");
+    writer.end_element();
+    writer.start_element("x:Row");
+    writer.write_text(&note.row.to_string());
+    writer.end_element();
+    writer.start_element("x:Column");
+    writer.write_text(&note.column.to_string());
+    writer.end_element();
+    if note.visible {
+        writer.start_element("x:Visible");
+        writer.end_element();
+    }
+    writer.end_element();
+
+    writer.end_element();
+}
+
+// threaded comments (excel 365): xl/threadedComments/threadedCommentN.xml and xl/persons/person.xml
+
+static THREADED_XMLNS: &str =
+    "http://schemas.microsoft.com/office/spreadsheetml/2018/threadedcomments";
+static SML_XMLNS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+
+// what older excel versions show in place of a thread.
+static THREAD_FALLBACK_HEADER: &str = "[Threaded comment]\n\nYour version of Excel allows you to read this threaded comment; however, any edits to it will get removed if the file is opened in a newer version of Excel. Learn more: https://go.microsoft.com/fwlink/?linkid=870924\n\nComment:\n    ";
+
+#[derive(Debug, Clone)]
+pub struct ThreadedComment {
+    pub author: String,
+    pub text: String,
+    // eg. 2024-01-31T10:00:00.00
+    pub timestamp: String,
+    // the time it was made, not given; a deterministic save changes it
+    made_now: bool,
+}
+
+impl ThreadedComment {
+    fn new(author: &str, text: &str, timestamp: Option<&str>) -> Self {
+        ThreadedComment {
+            author: author.to_string(),
+            text: text.to_string(),
+            timestamp: timestamp
+                .map(|t| t.to_string())
+                .unwrap_or_else(|| threaded_timestamp(&now_w3cdtf())),
+            made_now: timestamp.is_none(),
+        }
+    }
+}
+
+/// a conversation on a cell, the first comment and its replies.
+#[derive(Debug, Clone)]
+pub struct CommentThread {
+    reference: String,
+    comments: Vec<ThreadedComment>,
+    resolved: bool,
+    // set when the sheet is added to a work book
+    id: Option<String>,
+}
+
+impl CommentThread {
+    pub fn new(cell_ref: &str, author: &str, text: &str) -> Result<Self, &'static str> {
+        parse_cell_ref(cell_ref)?;
+        Ok(CommentThread {
+            reference: cell_ref.replace('$', "").to_ascii_uppercase(),
+            comments: vec![ThreadedComment::new(author, text, None)],
+            resolved: false,
+            id: None,
+        })
+    }
+
+    pub fn add_reply(&mut self, author: &str, text: &str) -> &mut Self {
+        self.comments.push(ThreadedComment::new(author, text, None));
+        self
+    }
+
+    /// a reply with a given timestamp, eg. `2024-01-31T10:00:00.00`
+    pub fn add_reply_at(&mut self, author: &str, text: &str, timestamp: &str) -> &mut Self {
+        self.comments
+            .push(ThreadedComment::new(author, text, Some(timestamp)));
+        self
+    }
+
+    /// timestamp of the first comment, eg. `2024-01-31T10:00:00.00`
+    pub fn set_timestamp(&mut self, timestamp: &str) -> &mut Self {
+        self.comments[0].timestamp = timestamp.to_string();
+        self.comments[0].made_now = false;
+        self
+    }
+
+    pub fn set_resolved(&mut self, resolved: bool) -> &mut Self {
+        self.resolved = resolved;
+        self
+    }
+
+    pub fn get_reference(&self) -> &str {
+        &self.reference
+    }
+
+    pub fn get_comments(&self) -> &[ThreadedComment] {
+        &self.comments
+    }
+
+    pub fn authors(&self) -> impl Iterator<Item = &str> {
+        self.comments.iter().map(|c| c.author.as_str())
+    }
+
+    /// the legacy note older readers show for this thread.
+    fn fallback_note(&self, thread_id: &str) -> Note {
+        let mut text = format!("{}{}", THREAD_FALLBACK_HEADER, self.comments[0].text);
+        for reply in self.comments.iter().skip(1) {
+            text.push_str("\nReply:\n    ");
+            text.push_str(&reply.text);
+        }
+        // the reference is already validated
+        Note::new(&self.reference, &format!("tc={}", thread_id), &text).unwrap()
+    }
+}
+
+/// threads of one sheet
+#[derive(Debug, Default)]
+pub struct CommentThreads {
+    threads: Vec<CommentThread>,
+}
+
+impl CommentThreads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add a thread, a thread already on the same cell is replaced.
+    pub fn add(&mut self, thread: CommentThread) -> &mut CommentThread {
+        self.threads.retain(|t| t.reference != thread.reference);
+        self.threads.push(thread);
+        self.threads.last_mut().unwrap()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.threads.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, CommentThread> {
+        self.threads.iter()
+    }
+
+    /// date the comments not given a time, eg. `FIXED_TIMESTAMP` for a deterministic save.
+    pub fn date_untimed(&mut self, w3cdtf: &str) {
+        let comments = self.threads.iter_mut().flat_map(|t| t.comments.iter_mut());
+        for comment in comments.filter(|c| c.made_now) {
+            comment.timestamp = threaded_timestamp(w3cdtf);
+        }
+    }
+
+    /// give every thread its id and add the legacy notes older readers show instead.
+    pub fn link(&mut self, sheet_number: usize, notes: &mut Notes) {
+        for (i, thread) in self.threads.iter_mut().enumerate() {
+            let id = guid(&format!(
+                "thread-{}-{}-{}",
+                sheet_number, i, thread.reference
+            ));
+            notes.add(thread.fallback_note(&id));
+            thread.id = Some(id);
+        }
+    }
+
+    /// xl/threadedComments/threadedCommentN.xml
+    pub fn to_xml(&self) -> String {
+        let mut writer = XmlWriter::new(writer_options());
+        writer.write_declaration();
+        writer.start_element("ThreadedComments");
+        writer.write_attribute("xmlns", THREADED_XMLNS);
+        writer.write_attribute("xmlns:x", SML_XMLNS);
+
+        for thread in self.threads.iter() {
+            let thread_id = thread.id.clone().unwrap_or_else(|| guid(&thread.reference));
+            for (i, comment) in thread.comments.iter().enumerate() {
+                writer.start_element("threadedComment");
+                writer.write_attribute("ref", &thread.reference);
+                writer.write_attribute("dT", &comment.timestamp);
+                writer.write_attribute("personId", &person_id(&comment.author));
+                if i == 0 {
+                    writer.write_attribute("id", &thread_id);
+                    if thread.resolved {
+                        writer.write_attribute("done", "1");
+                    }
+                } else {
+                    writer.write_attribute("id", &guid(&format!("{}-reply-{}", thread_id, i)));
+                    writer.write_attribute("parentId", &thread_id);
+                }
+                writer.start_element("text");
+                writer.write_text(&escape_xml(&comment.text));
+                writer.end_element();
+                writer.end_element();
+            }
+        }
+
+        writer.end_document()
+    }
+}
+
+/// xl/persons/person.xml, every author of a threaded comment in the work book.
+pub fn persons_to_xml(persons: &[String]) -> String {
+    let mut writer = XmlWriter::new(writer_options());
+    writer.write_declaration();
+    writer.start_element("personList");
+    writer.write_attribute("xmlns", THREADED_XMLNS);
+    writer.write_attribute("xmlns:x", SML_XMLNS);
+    for person in persons {
+        writer.start_element("person");
+        writer.write_attribute("displayName", &escape_xml(person));
+        writer.write_attribute("id", &person_id(person));
+        writer.write_attribute("userId", &escape_xml(person));
+        writer.write_attribute("providerId", "None");
+        writer.end_element();
+    }
+    writer.end_document()
+}
+
+// a person has the same id in every sheet, so it is derived from the name.
+fn person_id(name: &str) -> String {
+    guid(&format!("person-{}", name))
+}
+
+/// a stable guid like `{8B1C9A0E-3D2F-4A6B-9C1D-2E3F4A5B6C7D}` derived from `seed`.
+pub fn guid(seed: &str) -> String {
+    let high = fnv1a(seed.as_bytes(), 0xcbf2_9ce4_8422_2325);
+    let low = fnv1a(seed.as_bytes(), high);
+    format!(
+        "{{{:08X}-{:04X}-4{:03X}-{:04X}-{:012X}}}",
+        high >> 32,
+        (high >> 16) & 0xFFFF,
+        high & 0x0FFF,
+        ((low >> 48) & 0x3FFF) | 0x8000,
+        low & 0xFFFF_FFFF_FFFF
+    )
+}
+
+fn fnv1a(bytes: &[u8], offset: u64) -> u64 {
+    let mut hash = offset;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+// 2024-01-31T10:00:00Z as 2024-01-31T10:00:00.00
+fn threaded_timestamp(w3cdtf: &str) -> String {
+    format!("{}.00", w3cdtf.trim_end_matches('Z'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::column_number_to_name;
+
+    #[test]
+    fn shape_ids_take_a_block_per_1023_notes() {
+        let mut notes = Notes::new();
+        for i in 0..1025 {
+            let cell_ref = format!("{}1", column_number_to_name(i + 1));
+            notes.add(Note::new(&cell_ref, "Author", "text").unwrap());
+        }
+        let mut numbers = DrawingNumbers::default();
+        notes.link(&mut numbers);
+        assert_eq!(numbers.next_shape_block, 3);
+
+        let vml = notes.to_vml_xml();
+        assert!(vml.contains(r#"data="1,2""#));
+        assert!(vml.contains(r#"id="_x0000_s2047""#));
+        assert!(vml.contains(r#"id="_x0000_s2049""#));
+        assert!(!vml.contains(r#"id="_x0000_s2048""#));
+        assert!(vml.contains("z-index:1025;"));
+    }
+
+    #[test]
+    fn sheets_get_blocks_after_each_other() {
+        let mut numbers = DrawingNumbers::default();
+        let mut first = Notes::new();
+        first.add(Note::new("A1", "Author", "text").unwrap());
+        first.link(&mut numbers);
+        let mut second = Notes::new();
+        second.add(Note::new("A1", "Author", "text").unwrap());
+        second.link(&mut numbers);
+        assert!(second.to_vml_xml().contains(r#"data="2""#));
+        assert!(second.to_vml_xml().contains(r#"id="_x0000_s2049""#));
+    }
+
+    #[test]
+    fn untimed_comments_are_dated() {
+        let mut thread = CommentThread::new("A1", "Author", "text").unwrap();
+        thread
+            .add_reply("Author", "now")
+            .add_reply_at("Author", "given", "2024-01-31T10:00:00.00");
+        let mut threads = CommentThreads::new();
+        threads.add(thread);
+        threads.date_untimed("1980-01-01T00:00:00Z");
+        let timestamps: Vec<_> = threads
+            .iter()
+            .next()
+            .unwrap()
+            .get_comments()
+            .iter()
+            .map(|c| c.timestamp.as_str())
+            .collect();
+        assert_eq!(
+            timestamps,
+            [
+                "1980-01-01T00:00:00.00",
+                "1980-01-01T00:00:00.00",
+                "2024-01-31T10:00:00.00"
+            ]
+        );
+    }
+}
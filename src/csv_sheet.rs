@@ -0,0 +1,410 @@
+// work sheets from and to CSV files, see `WorkSheet::from_csv` and `WorkSheet::write_csv`.
+
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use encoding_rs::Encoding;
+
+use crate::{
+    cell::{Cell, CellValue},
+    rich_text,
+    row::{column_number_to_name, parse_cell_ref, ColMovement, Row, MAX_COLUMNS, MAX_ROWS},
+    row_view::{format_number, iso_to_serial},
+    work_sheet::WorkSheet,
+};
+
+/// what a CSV field becomes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsvType {
+    /// the first of bool, percentage, date and number the text reads as, else a string
+    Infer,
+    Text,
+    Number,
+    /// `true` or `false` in any case
+    Bool,
+    /// ISO 8601, `2024-01-31`, `2024-01-31T10:30:00` or `2024-01-31 10:30`
+    Date,
+    /// a number followed by `%`, `12.5%` is 0.125 shown as `0.0%`
+    Percentage,
+}
+
+/// options of `WorkSheet::from_csv`
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub quote: u8,
+    /// the first record is a header, kept as text
+    pub header: bool,
+    /// label of the encoding of the file, eg. `utf-8`, `windows-1252` or `utf-16le`.
+    /// a byte order mark wins over it.
+    pub encoding: String,
+    /// the type of a column (1 for column A) instead of `CsvType::Infer`
+    pub column_types: HashMap<usize, CsvType>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: b',',
+            quote: b'"',
+            header: false,
+            encoding: "utf-8".to_string(),
+            column_types: HashMap::new(),
+        }
+    }
+}
+
+/// the end of a line of a written CSV
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+/// options of `WorkSheet::write_csv`
+#[derive(Debug, Clone, Copy)]
+pub struct CsvWriteOptions {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub line_ending: LineEnding,
+    /// numbers shown by their number format, `0.125` with `0.0%` is `12.5%`, instead of
+    /// the value as it is stored.
+    pub formatted: bool,
+    /// the dates of the work book count from 1904, see `date1904` of a read work book.
+    pub date_1904: bool,
+}
+
+impl Default for CsvWriteOptions {
+    fn default() -> Self {
+        CsvWriteOptions {
+            delimiter: b',',
+            quote: b'"',
+            line_ending: LineEnding::Lf,
+            formatted: true,
+            date_1904: false,
+        }
+    }
+}
+
+impl CsvWriteOptions {
+    /// tab separated values
+    pub fn tsv() -> Self {
+        CsvWriteOptions {
+            delimiter: b'\t',
+            ..Default::default()
+        }
+    }
+}
+
+impl WorkSheet {
+    /// a sheet named `name` with a row for every record of the CSV in `reader`, empty
+    /// fields are left blank. fails on a field that does not read as the type given to
+    /// its column.
+    pub fn from_csv<R: Read>(
+        name: &str,
+        mut reader: R,
+        options: CsvOptions,
+    ) -> Result<WorkSheet, &'static str> {
+        let encoding =
+            Encoding::for_label(options.encoding.as_bytes()).ok_or("Unknown encoding")?;
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|_| "Could not read the CSV")?;
+        let (text, _, _) = encoding.decode(&bytes);
+
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(options.delimiter)
+            .quote(options.quote)
+            .from_reader(text.as_bytes());
+        let mut work_sheet = WorkSheet::blank(name);
+        for (index, record) in csv_reader.records().enumerate() {
+            let record = record.map_err(|_| "Invalid CSV")?;
+            if index >= MAX_ROWS {
+                return Err("Too many rows");
+            }
+            if record.len() > MAX_COLUMNS {
+                return Err("Too many columns");
+            }
+            let row = work_sheet.add_blank_row();
+            let header = options.header && index == 0;
+            for (column, field) in record.iter().enumerate() {
+                let column = column + 1;
+                if field.is_empty() {
+                    ColMovement::new(row).next();
+                } else if header {
+                    row.add_string(field.to_string())?;
+                } else {
+                    let csv_type = options.column_types.get(&column);
+                    add_field(row, column, field, *csv_type.unwrap_or(&CsvType::Infer))?;
+                }
+            }
+        }
+        Ok(work_sheet)
+    }
+
+    /// write the rows of the sheet as CSV, every record as wide as the widest row and a
+    /// blank record for a row that was skipped. fields are quoted when they need to be;
+    /// strings are written as their text, bools as `TRUE` and `FALSE`, formulas as their
+    /// last result.
+    pub fn write_csv<W: Write>(
+        &self,
+        writer: W,
+        options: CsvWriteOptions,
+    ) -> Result<(), &'static str> {
+        if self.has_streamed_rows() {
+            return Err("The rows of a streaming sheet are in the work book");
+        }
+        let terminator = match options.line_ending {
+            LineEnding::Lf => csv::Terminator::Any(b'\n'),
+            LineEnding::CrLf => csv::Terminator::CRLF,
+        };
+        let mut csv_writer = csv::WriterBuilder::new()
+            .delimiter(options.delimiter)
+            .quote(options.quote)
+            .terminator(terminator)
+            .flexible(true)
+            .from_writer(writer);
+
+        let columns = |row: &Row| -> Vec<usize> {
+            let references = row.get_cells().iter().map(|c| c.get_attributes());
+            references
+                .map(|a| a.reference.as_deref().and_then(|r| parse_cell_ref(r).ok()))
+                .map(|r| r.map_or(0, |(_, column)| column))
+                .collect()
+        };
+        let width = self.rows.iter().flat_map(&columns).max().unwrap_or(0);
+        let mut next_row = 1;
+        for row in self.rows.iter() {
+            let mut record = vec![String::new(); width];
+            for _ in next_row..row.get_row_number() {
+                csv_writer
+                    .write_record(&record)
+                    .map_err(|_| "Could not write the CSV")?;
+            }
+            next_row = row.get_row_number() + 1;
+            for (cell, column) in row.get_cells().iter().zip(columns(row)) {
+                if column > 0 {
+                    record[column - 1] = cell_text(cell, &options);
+                }
+            }
+            csv_writer
+                .write_record(&record)
+                .map_err(|_| "Could not write the CSV")?;
+        }
+        csv_writer.flush().map_err(|_| "Could not write the CSV")
+    }
+}
+
+// the text of a cell in a written CSV
+fn cell_text(cell: &Cell, options: &CsvWriteOptions) -> String {
+    match &cell.value {
+        CellValue::CString(text)
+        | CellValue::CInString(text)
+        | CellValue::CFomula(text)
+        | CellValue::CError(text) => text.clone(),
+        CellValue::CRichString(runs) => rich_text::plain_text(runs),
+        CellValue::CBool(true) => "TRUE".to_string(),
+        CellValue::CBool(false) => "FALSE".to_string(),
+        CellValue::CDate(date) => date.clone(),
+        CellValue::CNumber(number) => match (options.formatted, number.parse::<f64>()) {
+            (true, Ok(value)) => format_number(
+                value,
+                cell.number_format().unwrap_or("General"),
+                options.date_1904,
+            ),
+            _ => number.clone(),
+        },
+        CellValue::Empty => String::new(),
+    }
+}
+
+// add the field as the next cell of the row
+fn add_field(
+    row: &mut Row,
+    column: usize,
+    field: &str,
+    csv_type: CsvType,
+) -> Result<(), &'static str> {
+    let invalid = match csv_type {
+        CsvType::Number => "Invalid number in CSV",
+        CsvType::Bool => "Invalid bool in CSV",
+        CsvType::Date => "Invalid date in CSV",
+        CsvType::Percentage => "Invalid percentage in CSV",
+        CsvType::Text | CsvType::Infer => "",
+    };
+    let infer = csv_type == CsvType::Infer;
+    let reads_as = |t: CsvType| csv_type == t || infer;
+
+    if reads_as(CsvType::Bool) {
+        if let Some(value) = parse_bool(field) {
+            let reference = format!("{}{}", column_number_to_name(column), row.get_row_number());
+            row.add_cell(Cell::new(CellValue::CBool(value), reference))?;
+            return Ok(());
+        }
+    }
+    if reads_as(CsvType::Percentage) {
+        if let Some((value, decimals)) = parse_percentage(field, infer) {
+            let format_code = match decimals {
+                0 => "0%".to_string(),
+                n => format!("0.{}%", "0".repeat(n)),
+            };
+            let cell = row.add_number(value).map_err(|_| invalid)?;
+            cell.set_number_format(&format_code);
+            return Ok(());
+        }
+    }
+    if reads_as(CsvType::Date) {
+        if let Some((serial, has_time)) = iso_to_serial(field) {
+            let cell = row.add_number(serial.to_string()).map_err(|_| invalid)?;
+            cell.set_number_format(match has_time {
+                true => "yyyy-mm-dd hh:mm:ss",
+                false => "yyyy-mm-dd",
+            });
+            return Ok(());
+        }
+    }
+    if reads_as(CsvType::Number) {
+        if let Some(value) = parse_number(field, infer) {
+            row.add_number(value.to_string()).map_err(|_| invalid)?;
+            return Ok(());
+        }
+    }
+    if !invalid.is_empty() {
+        return Err(invalid);
+    }
+    row.add_string(field.to_string())?;
+    Ok(())
+}
+
+fn parse_bool(field: &str) -> Option<bool> {
+    if field.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if field.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+// `12.5%` as the number `0.125` and its 1 decimal, the point is moved in the text so no
+// digits are lost to rounding.
+fn parse_percentage(field: &str, infer: bool) -> Option<(String, usize)> {
+    let number = parse_number(field.strip_suffix('%')?.trim_end(), infer)?;
+    if number.contains(['e', 'E']) {
+        return None;
+    }
+    let (sign, number) = match number.strip_prefix('-') {
+        Some(number) => ("-", number),
+        None => ("", number),
+    };
+    let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let integer = format!("{:0>3}", integer);
+    let (integer, hundredths) = integer.split_at(integer.len() - 2);
+    let integer = match integer.trim_start_matches('0') {
+        "" => "0",
+        integer => integer,
+    };
+    let fraction = format!("{}{}", hundredths, fraction);
+    let value = match fraction.trim_end_matches('0') {
+        "" => format!("{}{}", sign, integer),
+        fraction => format!("{}{}.{}", sign, integer, fraction),
+    };
+    Some((value, fraction.len() - 2))
+}
+
+// a decimal number as it is written in the cell, without a leading `+`. `inf` and `NaN`
+// are never numbers. an inferred number starts with a digit and numbers like `007` keep
+// their zeros as text, a column of numbers takes `.5` and `007` too.
+fn parse_number(field: &str, infer: bool) -> Option<&str> {
+    let number = field.strip_prefix('+').unwrap_or(field);
+    let digits = number.strip_prefix('-').unwrap_or(number);
+    let mantissa = digits.split(['e', 'E']).next().unwrap_or_default();
+    if infer && !mantissa.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    if infer && mantissa.len() > 1 && mantissa.starts_with('0') && !mantissa.starts_with("0.") {
+        return None;
+    }
+    let allowed = digits
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '-' | '+'));
+    match allowed && number.parse::<f64>().is_ok_and(f64::is_finite) {
+        true => Some(number),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inferred_numbers_keep_leading_zeros_as_text() {
+        assert_eq!(parse_number("+12.5", true), Some("12.5"));
+        assert_eq!(parse_number("-1e3", true), Some("-1e3"));
+        assert_eq!(parse_number("0.5", true), Some("0.5"));
+        assert_eq!(parse_number("007", true), None);
+        assert_eq!(parse_number(".5", true), None);
+        assert_eq!(parse_number("inf", true), None);
+        assert_eq!(parse_number("NaN", true), None);
+    }
+
+    #[test]
+    fn a_column_of_numbers_takes_any_number() {
+        assert_eq!(parse_number("007", false), Some("007"));
+        assert_eq!(parse_number(".5", false), Some(".5"));
+        assert_eq!(parse_number("-.5", false), Some("-.5"));
+        assert_eq!(parse_number("inf", false), None);
+        assert_eq!(parse_number("1,000", false), None);
+
+        let mut row = Row::new(1);
+        add_field(&mut row, 1, "007", CsvType::Number).unwrap();
+        assert!(matches!(&row.get_cells()[0].value, CellValue::CNumber(v) if v == "007"));
+        add_field(&mut row, 2, "007", CsvType::Infer).unwrap();
+        assert!(matches!(&row.get_cells()[1].value, CellValue::CString(v) if v == "007"));
+    }
+
+    #[test]
+    fn percentages_move_the_point_in_the_text() {
+        assert_eq!(
+            parse_percentage("12.5%", true),
+            Some(("0.125".to_string(), 1))
+        );
+        assert_eq!(parse_percentage("100%", true), Some(("1".to_string(), 0)));
+        assert_eq!(
+            parse_percentage("-3 %", true),
+            Some(("-0.03".to_string(), 0))
+        );
+        assert_eq!(
+            parse_percentage("1234.50%", true),
+            Some(("12.345".to_string(), 2))
+        );
+        assert_eq!(
+            parse_percentage(".5%", false),
+            Some(("0.005".to_string(), 1))
+        );
+        assert_eq!(parse_percentage("1e2%", true), None);
+        assert_eq!(parse_percentage("12.5", true), None);
+    }
+
+    #[test]
+    fn iso_dates_are_serial_numbers() {
+        assert_eq!(iso_to_serial("2023-03-15"), Some((45000.0, false)));
+        assert_eq!(iso_to_serial("1900-03-01"), Some((61.0, false)));
+        assert_eq!(iso_to_serial("2023-03-15T12:00:00"), Some((45000.5, true)));
+        assert_eq!(iso_to_serial("2023-03-15 06:00"), Some((45000.25, true)));
+        assert_eq!(iso_to_serial("1900-01-01"), Some((1.0, false)));
+        assert_eq!(iso_to_serial("1900-02-28"), Some((59.0, false)));
+        assert_eq!(iso_to_serial("1899-12-31"), None);
+        assert_eq!(iso_to_serial("2023-02-30"), None);
+        assert_eq!(iso_to_serial("2023-3-15"), None);
+        assert_eq!(iso_to_serial("2023-03-15T24:00"), None);
+        assert_eq!(iso_to_serial("15/03/2023"), None);
+    }
+}
@@ -0,0 +1,158 @@
+use xmlwriter::XmlWriter;
+
+use crate::{escape::escape_xml, row::parse_cell_ref, traits::XMLString};
+
+/// Names excel itself understands, they are always scoped to a sheet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuiltInName {
+    PrintArea,
+    PrintTitles,
+    FilterDatabase,
+}
+
+impl BuiltInName {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuiltInName::PrintArea => "_xlnm.Print_Area",
+            BuiltInName::PrintTitles => "_xlnm.Print_Titles",
+            BuiltInName::FilterDatabase => "_xlnm._FilterDatabase",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DefinedName {
+    pub name: String,
+    // formula without the leading `=`
+    pub formula: String,
+    // index of the sheet in the work book, None for work book scope.
+    pub local_sheet_id: Option<usize>,
+    pub hidden: bool,
+}
+
+impl DefinedName {
+    /// a user defined name, validated against excel naming rules.
+    pub fn new(
+        name: &str,
+        formula: &str,
+        local_sheet_id: Option<usize>,
+    ) -> Result<Self, &'static str> {
+        validate_name(name)?;
+        Ok(Self::unchecked(name, formula, local_sheet_id))
+    }
+
+    pub fn built_in(name: BuiltInName, formula: &str, local_sheet_id: usize) -> Self {
+        let mut defined_name = Self::unchecked(name.as_str(), formula, Some(local_sheet_id));
+        defined_name.hidden = name == BuiltInName::FilterDatabase;
+        defined_name
+    }
+
+    fn unchecked(name: &str, formula: &str, local_sheet_id: Option<usize>) -> Self {
+        DefinedName {
+            name: name.to_string(),
+            formula: formula.trim_start_matches('=').to_string(),
+            local_sheet_id,
+            hidden: false,
+        }
+    }
+
+    // names are case insensitive, two names clash when they are equal in the same scope.
+    pub fn clashes_with(&self, other: &DefinedName) -> bool {
+        self.local_sheet_id == other.local_sheet_id && self.name.eq_ignore_ascii_case(&other.name)
+    }
+}
+
+impl XMLString for DefinedName {
+    fn to_xml(self, writer: &mut XmlWriter) {
+        writer.start_element("definedName");
+        writer.write_attribute("name", &self.name);
+        if let Some(id) = self.local_sheet_id {
+            writer.write_attribute("localSheetId", &id.to_string());
+        }
+        if self.hidden {
+            writer.write_attribute("hidden", "1");
+        }
+        writer.write_text(&escape_xml(&self.formula));
+        writer.end_element();
+    }
+}
+
+/// quote a sheet name for use in a formula when it is needed, `sheet 1` -> `'sheet 1'`,
+/// `A1` -> `'A1'` as it would be read as a reference.
+pub fn quote_sheet_name(sheet_name: &str) -> String {
+    if sheet_name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+        && !sheet_name.starts_with(|c: char| c.is_ascii_digit())
+        && !is_reference(sheet_name)
+    {
+        sheet_name.to_string()
+    } else {
+        format!("'{}'", sheet_name.replace('\'', "''"))
+    }
+}
+
+/// Excel naming rules:
+/// - start with a letter, `_` or `\`, then letters, digits, `_`, `.` or `\`
+/// - at most 255 characters
+/// - must not look like a cell reference (`A1`, `XFD1048576`) or a R1C1 reference (`R`, `C`, `R1C1`)
+/// - the `_xlnm.` prefix is reserved for the built in names
+pub fn validate_name(name: &str) -> Result<(), &'static str> {
+    if name.is_empty() {
+        return Err("Defined name can not be empty");
+    }
+    if name.chars().count() > 255 {
+        return Err("Defined name can not be longer than 255 characters");
+    }
+    let mut chars = name.chars();
+    let first = chars.next().unwrap();
+    if !(first.is_alphabetic() || first == '_' || first == '\\') {
+        return Err("Defined name must start with a letter, underscore or backslash");
+    }
+    if !chars.all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '\\') {
+        return Err("Defined name contains invalid characters");
+    }
+    if name.to_ascii_lowercase().starts_with("_xlnm.") {
+        return Err("Defined name prefix _xlnm. is reserved");
+    }
+    if is_reference(name) {
+        return Err("Defined name clashes with a cell reference");
+    }
+    Ok(())
+}
+
+// `A1`, `XFD1048576` or a R1C1 reference
+fn is_reference(name: &str) -> bool {
+    parse_cell_ref(name).is_ok() || is_r1c1_reference(name)
+}
+
+// R, C, R1, C1, RC, R1C1, RC1, R1C
+fn is_r1c1_reference(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    let mut rest = upper.as_str();
+    let mut matched = false;
+    if let Some(r) = rest.strip_prefix('R') {
+        rest = r.trim_start_matches(|c: char| c.is_ascii_digit());
+        matched = true;
+    }
+    if let Some(c) = rest.strip_prefix('C') {
+        rest = c.trim_start_matches(|c: char| c.is_ascii_digit());
+        matched = true;
+    }
+    matched && rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sheet_names_like_references_are_quoted() {
+        assert_eq!(quote_sheet_name("Data"), "Data");
+        assert_eq!(quote_sheet_name("sheet 1"), "'sheet 1'");
+        assert_eq!(quote_sheet_name("it's"), "'it''s'");
+        for name in ["A1", "XFD100", "R1C1", "R", "rc"] {
+            assert_eq!(quote_sheet_name(name), format!("'{}'", name));
+        }
+    }
+}
@@ -0,0 +1,465 @@
+// the drawing part of a sheet (xl/drawings/drawingN.xml), holds images and charts placed on the sheet.
+
+use xmlwriter::{Indent, Options, XmlWriter};
+
+use crate::{
+    chart::{Chart, ChartOptions},
+    escape::escape_xml,
+    image::{Anchor, Image, ImageOptions},
+    xml_templates::relation_ship::{RelationShip, RS_CHART, RS_IMAGE},
+};
+
+static XDR_XMLNS: &str = "http://schemas.openxmlformats.org/drawingml/2006/spreadsheetDrawing";
+static A_XMLNS: &str = "http://schemas.openxmlformats.org/drawingml/2006/main";
+static R_XMLNS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+static C_XMLNS: &str = "http://schemas.openxmlformats.org/drawingml/2006/chart";
+
+// default column width and row height in pixels, the sheet has no custom sizes.
+static COLUMN_WIDTH_PX: u32 = 64;
+static ROW_HEIGHT_PX: u32 = 20;
+static EMU_PER_PX: u64 = 9525;
+
+/// where an object sits on the sheet, all in pixels and 0 based cells.
+#[derive(Debug, Clone, Copy)]
+pub struct Position {
+    pub row: usize,
+    pub column: usize,
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub width: u32,
+    pub height: u32,
+    pub anchor: Anchor,
+}
+
+impl Position {
+    // (column, column offset, row, row offset) of the bottom right corner
+    fn end(&self) -> (usize, u32, usize, u32) {
+        let right = self.x_offset + self.width;
+        let bottom = self.y_offset + self.height;
+        (
+            self.column + (right / COLUMN_WIDTH_PX) as usize,
+            right % COLUMN_WIDTH_PX,
+            self.row + (bottom / ROW_HEIGHT_PX) as usize,
+            bottom % ROW_HEIGHT_PX,
+        )
+    }
+
+    fn absolute(&self) -> (u64, u64) {
+        let x = self.column as u64 * COLUMN_WIDTH_PX as u64 + self.x_offset as u64;
+        let y = self.row as u64 * ROW_HEIGHT_PX as u64 + self.y_offset as u64;
+        (x * EMU_PER_PX, y * EMU_PER_PX)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum DrawingObject {
+    Picture {
+        image: Image,
+        alt_text: Option<String>,
+        position: Position,
+        // set when the sheet is added to a work book
+        media_number: Option<usize>,
+        r_id: Option<String>,
+    },
+    Chart {
+        chart: Chart,
+        position: Position,
+        // set when the sheet is added to a work book
+        chart_number: Option<usize>,
+        r_id: Option<String>,
+    },
+}
+
+/// drawings, media and charts are numbered across the work book,
+/// xl/drawings/drawingN.xml, xl/media/imageN.ext and xl/charts/chartN.xml
+#[derive(Debug)]
+pub struct DrawingNumbers {
+    pub next_drawing: usize,
+    pub next_image: usize,
+    pub next_chart: usize,
+    // vml shape ids come in blocks of 1024, the blocks of a sheet are its `o:idmap`
+    pub next_shape_block: usize,
+}
+
+impl Default for DrawingNumbers {
+    fn default() -> Self {
+        Self {
+            next_drawing: 1,
+            next_image: 1,
+            next_chart: 1,
+            next_shape_block: 1,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Drawing {
+    objects: Vec<DrawingObject>,
+    relation_ship: RelationShip,
+    // xl/drawings/drawingN.xml, set when the sheet is added to a work book
+    number: Option<usize>,
+}
+
+fn writer_options() -> Options {
+    Options {
+        indent: Indent::None,
+        ..Default::default()
+    }
+}
+
+impl Drawing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// place an image with its top left corner in the given (0 based) cell.
+    pub fn add_image(&mut self, row: usize, column: usize, image: Image, options: ImageOptions) {
+        let (width, height) = image.display_size(options.x_scale, options.y_scale);
+        self.objects.push(DrawingObject::Picture {
+            image,
+            alt_text: options.alt_text,
+            position: Position {
+                row,
+                column,
+                x_offset: options.x_offset,
+                y_offset: options.y_offset,
+                width,
+                height,
+                anchor: options.anchor,
+            },
+            media_number: None,
+            r_id: None,
+        });
+    }
+
+    /// place a chart with its top left corner in the given (0 based) cell.
+    pub fn add_chart(&mut self, row: usize, column: usize, chart: Chart, options: ChartOptions) {
+        self.objects.push(DrawingObject::Chart {
+            chart,
+            position: Position {
+                row,
+                column,
+                x_offset: options.x_offset,
+                y_offset: options.y_offset,
+                width: options.width,
+                height: options.height,
+                anchor: options.anchor,
+            },
+            chart_number: None,
+            r_id: None,
+        });
+    }
+
+    /// number this drawing, its media and charts from `numbers`
+    /// and register them in the drawing relationships.
+    pub fn link(&mut self, numbers: &mut DrawingNumbers) {
+        self.number = Some(numbers.next_drawing);
+        numbers.next_drawing += 1;
+        for object in self.objects.iter_mut() {
+            match object {
+                DrawingObject::Picture {
+                    image,
+                    media_number,
+                    r_id,
+                    ..
+                } => {
+                    let target = format!(
+                        "../media/image{}.{}",
+                        numbers.next_image,
+                        image.format.extension()
+                    );
+                    *r_id = Some(self.relation_ship.add_relation(RS_IMAGE, &target, false));
+                    *media_number = Some(numbers.next_image);
+                    numbers.next_image += 1;
+                }
+                DrawingObject::Chart {
+                    chart_number, r_id, ..
+                } => {
+                    let target = format!("../charts/chart{}.xml", numbers.next_chart);
+                    *r_id = Some(self.relation_ship.add_relation(RS_CHART, &target, false));
+                    *chart_number = Some(numbers.next_chart);
+                    numbers.next_chart += 1;
+                }
+            }
+        }
+    }
+
+    pub fn number(&self) -> Option<usize> {
+        self.number
+    }
+
+    /// extensions and content types of the media, for the content type defaults.
+    pub fn media_types(&self) -> Vec<(&'static str, &'static str)> {
+        self.objects
+            .iter()
+            .filter_map(|object| match object {
+                DrawingObject::Picture { image, .. } => {
+                    Some((image.format.extension(), image.format.content_type()))
+                }
+                DrawingObject::Chart { .. } => None,
+            })
+            .collect()
+    }
+
+    /// numbers of the charts of this drawing, for the content type overrides.
+    pub fn chart_numbers(&self) -> Vec<usize> {
+        self.objects
+            .iter()
+            .filter_map(|object| match object {
+                DrawingObject::Chart { chart_number, .. } => *chart_number,
+                DrawingObject::Picture { .. } => None,
+            })
+            .collect()
+    }
+
+    /// the drawing xml, its relationships, the media files and the charts, as (path in the package, content).
+    pub fn parts(&self) -> Vec<(String, Vec<u8>)> {
+        let drawing_number = self.number.unwrap_or_default();
+        let mut parts = vec![(
+            format!("xl/drawings/drawing{}.xml", drawing_number),
+            self.to_xml().into_bytes(),
+        )];
+        if !self.relation_ship.is_empty() {
+            parts.push((
+                format!("xl/drawings/_rels/drawing{}.xml.rels", drawing_number),
+                self.relation_ship.to_xml().into_bytes(),
+            ));
+        }
+        for object in self.objects.iter() {
+            match object {
+                DrawingObject::Picture {
+                    image,
+                    media_number: Some(number),
+                    ..
+                } => parts.push((
+                    format!("xl/media/image{}.{}", number, image.format.extension()),
+                    image.data.clone(),
+                )),
+                DrawingObject::Chart {
+                    chart,
+                    chart_number: Some(number),
+                    ..
+                } => parts.push((
+                    format!("xl/charts/chart{}.xml", number),
+                    chart.to_xml().into_bytes(),
+                )),
+                _ => {}
+            }
+        }
+        parts
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut writer = XmlWriter::new(writer_options());
+        writer.write_declaration();
+        writer.start_element("xdr:wsDr");
+        writer.write_attribute("xmlns:xdr", XDR_XMLNS);
+        writer.write_attribute("xmlns:a", A_XMLNS);
+
+        for (i, object) in self.objects.iter().enumerate() {
+            // id 1 is the drawing itself
+            let shape_id = i + 2;
+            match object {
+                DrawingObject::Picture {
+                    alt_text,
+                    position,
+                    r_id,
+                    ..
+                } => {
+                    start_anchor(&mut writer, position);
+                    write_picture(
+                        &mut writer,
+                        shape_id,
+                        alt_text.as_deref(),
+                        r_id.as_deref().unwrap_or_default(),
+                        position,
+                    );
+                    end_anchor(&mut writer);
+                }
+                DrawingObject::Chart { position, r_id, .. } => {
+                    start_anchor(&mut writer, position);
+                    write_chart_frame(&mut writer, shape_id, r_id.as_deref().unwrap_or_default());
+                    end_anchor(&mut writer);
+                }
+            }
+        }
+
+        writer.end_document()
+    }
+}
+
+fn write_marker(
+    writer: &mut XmlWriter,
+    name: &str,
+    column: usize,
+    col_off: u32,
+    row: usize,
+    row_off: u32,
+) {
+    writer.start_element(name);
+    writer.start_element("xdr:col");
+    writer.write_text(&column.to_string());
+    writer.end_element();
+    writer.start_element("xdr:colOff");
+    writer.write_text(&(col_off as u64 * EMU_PER_PX).to_string());
+    writer.end_element();
+    writer.start_element("xdr:row");
+    writer.write_text(&row.to_string());
+    writer.end_element();
+    writer.start_element("xdr:rowOff");
+    writer.write_text(&(row_off as u64 * EMU_PER_PX).to_string());
+    writer.end_element();
+    writer.end_element();
+}
+
+fn write_ext(writer: &mut XmlWriter, position: &Position) {
+    writer.start_element("xdr:ext");
+    writer.write_attribute("cx", &(position.width as u64 * EMU_PER_PX).to_string());
+    writer.write_attribute("cy", &(position.height as u64 * EMU_PER_PX).to_string());
+    writer.end_element();
+}
+
+// opens the anchor element and writes where the object is, the object follows.
+pub fn start_anchor(writer: &mut XmlWriter, position: &Position) {
+    // offsets past a cell move to the next cells
+    let column = position.column + (position.x_offset / COLUMN_WIDTH_PX) as usize;
+    let col_off = position.x_offset % COLUMN_WIDTH_PX;
+    let row = position.row + (position.y_offset / ROW_HEIGHT_PX) as usize;
+    let row_off = position.y_offset % ROW_HEIGHT_PX;
+    match position.anchor {
+        Anchor::OneCell => {
+            writer.start_element("xdr:oneCellAnchor");
+            write_marker(writer, "xdr:from", column, col_off, row, row_off);
+            write_ext(writer, position);
+        }
+        Anchor::TwoCell => {
+            writer.start_element("xdr:twoCellAnchor");
+            writer.write_attribute("editAs", "twoCell");
+            write_marker(writer, "xdr:from", column, col_off, row, row_off);
+            let (end_column, end_col_off, end_row, end_row_off) = position.end();
+            write_marker(
+                writer,
+                "xdr:to",
+                end_column,
+                end_col_off,
+                end_row,
+                end_row_off,
+            );
+        }
+        Anchor::Absolute => {
+            writer.start_element("xdr:absoluteAnchor");
+            let (x, y) = position.absolute();
+            writer.start_element("xdr:pos");
+            writer.write_attribute("x", &x.to_string());
+            writer.write_attribute("y", &y.to_string());
+            writer.end_element();
+            write_ext(writer, position);
+        }
+    }
+}
+
+pub fn end_anchor(writer: &mut XmlWriter) {
+    writer.start_element("xdr:clientData");
+    writer.end_element();
+    writer.end_element();
+}
+
+fn write_picture(
+    writer: &mut XmlWriter,
+    shape_id: usize,
+    alt_text: Option<&str>,
+    r_id: &str,
+    position: &Position,
+) {
+    writer.start_element("xdr:pic");
+
+    writer.start_element("xdr:nvPicPr");
+    writer.start_element("xdr:cNvPr");
+    writer.write_attribute("id", &shape_id.to_string());
+    writer.write_attribute("name", &format!("Picture {}", shape_id - 1));
+    if let Some(alt_text) = alt_text {
+        writer.write_attribute("descr", &escape_xml(alt_text));
+    }
+    writer.end_element();
+    writer.start_element("xdr:cNvPicPr");
+    writer.start_element("a:picLocks");
+    writer.write_attribute("noChangeAspect", "1");
+    writer.end_element();
+    writer.end_element();
+    writer.end_element();
+
+    writer.start_element("xdr:blipFill");
+    writer.start_element("a:blip");
+    writer.write_attribute("xmlns:r", R_XMLNS);
+    writer.write_attribute("r:embed", r_id);
+    writer.end_element();
+    writer.start_element("a:stretch");
+    writer.start_element("a:fillRect");
+    writer.end_element();
+    writer.end_element();
+    writer.end_element();
+
+    writer.start_element("xdr:spPr");
+    writer.start_element("a:xfrm");
+    writer.start_element("a:off");
+    let (x, y) = position.absolute();
+    writer.write_attribute("x", &x.to_string());
+    writer.write_attribute("y", &y.to_string());
+    writer.end_element();
+    writer.start_element("a:ext");
+    writer.write_attribute("cx", &(position.width as u64 * EMU_PER_PX).to_string());
+    writer.write_attribute("cy", &(position.height as u64 * EMU_PER_PX).to_string());
+    writer.end_element();
+    writer.end_element();
+    writer.start_element("a:prstGeom");
+    writer.write_attribute("prst", "rect");
+    writer.start_element("a:avLst");
+    writer.end_element();
+    writer.end_element();
+    writer.end_element();
+
+    writer.end_element();
+}
+
+fn write_chart_frame(writer: &mut XmlWriter, shape_id: usize, r_id: &str) {
+    writer.start_element("xdr:graphicFrame");
+    writer.write_attribute("macro", "");
+
+    writer.start_element("xdr:nvGraphicFramePr");
+    writer.start_element("xdr:cNvPr");
+    writer.write_attribute("id", &shape_id.to_string());
+    writer.write_attribute("name", &format!("Chart {}", shape_id - 1));
+    writer.end_element();
+    writer.start_element("xdr:cNvGraphicFramePr");
+    writer.end_element();
+    writer.end_element();
+
+    // the anchor decides the size, the frame transform stays empty.
+    writer.start_element("xdr:xfrm");
+    writer.start_element("a:off");
+    writer.write_attribute("x", "0");
+    writer.write_attribute("y", "0");
+    writer.end_element();
+    writer.start_element("a:ext");
+    writer.write_attribute("cx", "0");
+    writer.write_attribute("cy", "0");
+    writer.end_element();
+    writer.end_element();
+
+    writer.start_element("a:graphic");
+    writer.start_element("a:graphicData");
+    writer.write_attribute("uri", C_XMLNS);
+    writer.start_element("c:chart");
+    writer.write_attribute("xmlns:c", C_XMLNS);
+    writer.write_attribute("xmlns:r", R_XMLNS);
+    writer.write_attribute("r:id", r_id);
+    writer.end_element();
+    writer.end_element();
+    writer.end_element();
+
+    writer.end_element();
+}
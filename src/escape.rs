@@ -0,0 +1,151 @@
+// xmlwriter only escapes `<` in text and `"` in attributes, everything else is ours.
+
+use xmlwriter::XmlWriter;
+
+/// the most characters a cell can hold
+pub const MAX_STRING_LENGTH: usize = 32_767;
+
+/// escape `&` and `<`, safe for both text nodes and attribute values.
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;")
+}
+
+/// escape the text of a string cell or a run. on top of `escape_xml`, characters xml
+/// does not allow are written the ooxml way as `_xHHHH_`, and a literal `_xHHHH_`
+/// becomes `_x005F_xHHHH_` so excel reads it back unchanged.
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for (i, c) in text.char_indices() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '_' if is_escape_sequence(&text[i..]) => escaped.push_str("_x005F_"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            '\u{0}'..='\u{1F}' | '\u{FFFE}' | '\u{FFFF}' => {
+                escaped.push_str(&format!("_x{:04X}_", c as u32))
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// the reverse of the `_xHHHH_` part of `escape_text`, for text read from a file.
+pub fn unescape_text(text: &str) -> String {
+    if !text.contains("_x") {
+        return text.to_string();
+    }
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("_x") {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let character = is_escape_sequence(rest)
+            .then(|| u32::from_str_radix(&rest[2..6], 16).ok())
+            .flatten()
+            .and_then(char::from_u32);
+        match character {
+            Some(c) => {
+                unescaped.push(c);
+                rest = &rest[7..];
+            }
+            None => {
+                unescaped.push('_');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+// `_xHHHH_` at the start of the text
+fn is_escape_sequence(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() >= 7
+        && bytes[1] == b'x'
+        && bytes[2..6].iter().all(u8::is_ascii_hexdigit)
+        && bytes[6] == b'_'
+}
+
+/// fails for text longer than excel allows in a cell
+pub fn check_string_length(text: &str) -> Result<(), &'static str> {
+    // excel counts utf-16 code units
+    if text.encode_utf16().count() > MAX_STRING_LENGTH {
+        return Err("String is longer than 32767 characters");
+    }
+    Ok(())
+}
+
+/// writes `<t>`, keeping leading and trailing white space with `xml:space="preserve"`.
+pub fn write_string(writer: &mut XmlWriter, text: &str) {
+    writer.start_element("t");
+    if text.starts_with(char::is_whitespace) || text.ends_with(char::is_whitespace) {
+        writer.write_attribute("xml:space", "preserve");
+    }
+    writer.write_text(&escape_text(text));
+    writer.end_element();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmlwriter::{Indent, Options};
+
+    fn round_trip(text: &str) -> String {
+        unescape_text(&escape_text(text))
+    }
+
+    #[test]
+    fn control_characters_round_trip() {
+        let text = "a\u{1}b\u{8}c\u{1F}d\u{FFFE}";
+        assert_eq!(escape_text(text), "a_x0001_b_x0008_c_x001F_d_xFFFE_");
+        assert_eq!(round_trip(text), text);
+        // tabs and line breaks are valid xml
+        assert_eq!(escape_text("a\tb\nc\rd"), "a\tb\nc\rd");
+    }
+
+    #[test]
+    fn literal_escape_sequences_round_trip() {
+        assert_eq!(escape_text("_x0041_"), "_x005F_x0041_");
+        assert_eq!(round_trip("_x0041_"), "_x0041_");
+        assert_eq!(round_trip("a_x0041_b_x00_"), "a_x0041_b_x00_");
+        assert_eq!(unescape_text("_x0041_"), "A");
+        // not a sequence, left alone
+        assert_eq!(round_trip("_xZZZZ_ and _x"), "_xZZZZ_ and _x");
+    }
+
+    #[test]
+    fn markup_is_escaped() {
+        assert_eq!(escape_text("a < b & c"), "a &lt; b &amp; c");
+        assert_eq!(escape_xml("<&>"), "&lt;&amp;>");
+    }
+
+    fn string_xml(text: &str) -> String {
+        let mut writer = XmlWriter::new(Options {
+            indent: Indent::None,
+            ..Default::default()
+        });
+        write_string(&mut writer, text);
+        writer.end_document()
+    }
+
+    #[test]
+    fn surrounding_white_space_is_preserved() {
+        assert_eq!(string_xml("text"), "<t>text</t>");
+        assert_eq!(string_xml(" lead"), r#"<t xml:space="preserve"> lead</t>"#);
+        assert_eq!(
+            string_xml("trail\n"),
+            "<t xml:space=\"preserve\">trail\n</t>"
+        );
+        assert_eq!(string_xml("in side"), "<t>in side</t>");
+    }
+
+    #[test]
+    fn length_is_counted_in_utf16_units() {
+        assert!(check_string_length(&"a".repeat(MAX_STRING_LENGTH)).is_ok());
+        assert!(check_string_length(&"a".repeat(MAX_STRING_LENGTH + 1)).is_err());
+        // every emoji takes two units
+        assert!(check_string_length(&"\u{1F600}".repeat(MAX_STRING_LENGTH / 2 + 1)).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use xmlwriter::XmlWriter;
+
+use crate::escape::escape_xml;
+
+// excel refuse header and footer text longer than this.
+pub const MAX_HEADER_FOOTER_LENGTH: usize = 255;
+
+/// One of the left, center or right part of a header or footer,
+/// built out of text and excel control codes.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderFooterSection {
+    content: String,
+}
+
+impl HeaderFooterSection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// literal text, `&` is escaped.
+    pub fn text(mut self, text: &str) -> Self {
+        // a number right after a font size code would be read as part of the size.
+        if text.starts_with(|c: char| c.is_ascii_digit()) && self.ends_with_font_size() {
+            self.content.push(' ');
+        }
+        self.content.push_str(&text.replace('&', "&&"));
+        self
+    }
+
+    /// current page number, `&P`
+    pub fn page_number(self) -> Self {
+        self.code("&P")
+    }
+
+    /// total number of pages, `&N`
+    pub fn total_pages(self) -> Self {
+        self.code("&N")
+    }
+
+    /// `&P of &N`
+    pub fn page_of_pages(self) -> Self {
+        self.page_number().text(" of ").total_pages()
+    }
+
+    /// current date, `&D`
+    pub fn date(self) -> Self {
+        self.code("&D")
+    }
+
+    /// current time, `&T`
+    pub fn time(self) -> Self {
+        self.code("&T")
+    }
+
+    /// file name, `&F`
+    pub fn file_name(self) -> Self {
+        self.code("&F")
+    }
+
+    /// sheet name, `&A`
+    pub fn sheet_name(self) -> Self {
+        self.code("&A")
+    }
+
+    /// change the font for the text after this, eg. `font("Arial", "Bold Italic")`
+    pub fn font(self, name: &str, style: &str) -> Self {
+        let code = format!("&\"{},{}\"", name.replace('"', ""), style.replace('"', ""));
+        self.code(&code)
+    }
+
+    /// change the font size (in points) for the text after this.
+    pub fn font_size(self, size: u8) -> Self {
+        let code = format!("&{}", size);
+        self.code(&code)
+    }
+
+    /// toggle bold on or off
+    pub fn bold(self) -> Self {
+        self.code("&B")
+    }
+
+    /// toggle italic on or off
+    pub fn italic(self) -> Self {
+        self.code("&I")
+    }
+
+    /// toggle underline on or off
+    pub fn underline(self) -> Self {
+        self.code("&U")
+    }
+
+    /// toggle strike through on or off
+    pub fn strike(self) -> Self {
+        self.code("&S")
+    }
+
+    fn code(mut self, code: &str) -> Self {
+        self.content.push_str(code);
+        self
+    }
+
+    fn ends_with_font_size(&self) -> bool {
+        let trimmed = self.content.trim_end_matches(|c: char| c.is_ascii_digit());
+        trimmed.len() < self.content.len() && trimmed.ends_with('&') && !trimmed.ends_with("&&")
+    }
+}
+
+/// A header or footer made of left, center and right sections.
+///
+/// ```ignore
+/// let footer = HeaderFooter::new()
+///     .left(HeaderFooterSection::new().sheet_name())
+///     .right(HeaderFooterSection::new().text("Page ").page_of_pages());
+/// work_sheet.set_footer(footer)?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HeaderFooter {
+    left: Option<HeaderFooterSection>,
+    center: Option<HeaderFooterSection>,
+    right: Option<HeaderFooterSection>,
+}
+
+impl HeaderFooter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn left(mut self, section: HeaderFooterSection) -> Self {
+        self.left = Some(section);
+        self
+    }
+
+    pub fn center(mut self, section: HeaderFooterSection) -> Self {
+        self.center = Some(section);
+        self
+    }
+
+    pub fn right(mut self, section: HeaderFooterSection) -> Self {
+        self.right = Some(section);
+        self
+    }
+
+    /// the `&L...&C...&R...` text excel stores, fails when it is over 255 characters.
+    pub fn to_code(&self) -> Result<String, &'static str> {
+        let mut code = String::new();
+        for (prefix, section) in [
+            ("&L", &self.left),
+            ("&C", &self.center),
+            ("&R", &self.right),
+        ] {
+            if let Some(section) = section {
+                code.push_str(prefix);
+                code.push_str(&section.content);
+            }
+        }
+        if code.chars().count() > MAX_HEADER_FOOTER_LENGTH {
+            return Err("Header or footer is longer than 255 characters");
+        }
+        Ok(code)
+    }
+}
+
+/// headers and footers of a sheet, already turned in to excel codes.
+#[derive(Debug, Default)]
+pub struct HeaderFooters {
+    pub odd_header: Option<String>,
+    pub odd_footer: Option<String>,
+    pub even_header: Option<String>,
+    pub even_footer: Option<String>,
+    pub first_header: Option<String>,
+    pub first_footer: Option<String>,
+}
+
+impl HeaderFooters {
+    pub fn is_empty(&self) -> bool {
+        self.odd_header.is_none()
+            && self.odd_footer.is_none()
+            && self.even_header.is_none()
+            && self.even_footer.is_none()
+            && self.first_header.is_none()
+            && self.first_footer.is_none()
+    }
+
+    pub fn to_xml(self, writer: &mut XmlWriter) {
+        if self.is_empty() {
+            return;
+        }
+        writer.start_element("headerFooter");
+        if self.even_header.is_some() || self.even_footer.is_some() {
+            writer.write_attribute("differentOddEven", "1");
+        }
+        if self.first_header.is_some() || self.first_footer.is_some() {
+            writer.write_attribute("differentFirst", "1");
+        }
+        for (name, value) in [
+            ("oddHeader", self.odd_header),
+            ("oddFooter", self.odd_footer),
+            ("evenHeader", self.even_header),
+            ("evenFooter", self.even_footer),
+            ("firstHeader", self.first_header),
+            ("firstFooter", self.first_footer),
+        ] {
+            if let Some(value) = value {
+                writer.start_element(name);
+                writer.write_text(&escape_xml(&value));
+                writer.end_element();
+            }
+        }
+        writer.end_element();
+    }
+}
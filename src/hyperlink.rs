@@ -0,0 +1,129 @@
+use xmlwriter::XmlWriter;
+
+use crate::{escape::escape_xml, row::parse_cell_ref, traits::XMLString};
+
+#[derive(Debug, Clone)]
+pub enum HyperlinkTarget {
+    // url, mailto: or a file path, goes through the sheet relationships.
+    External(String),
+    // a place in this work book, eg. Sheet2!A1
+    Location(String),
+}
+
+impl HyperlinkTarget {
+    /// - `https://..`, `ftp://..`, `mailto:..`, `file:///..` are external as they are
+    /// - `name@example.com` becomes `mailto:name@example.com`
+    /// - `internal:Sheet2!A1`, `#Sheet2!A1`, `Sheet2!A1` or `'My Sheet'!A1:B2` is a location in
+    ///   the work book
+    /// - everything else is taken as a (relative) file path
+    pub fn parse(target: &str) -> Self {
+        if target.contains("://") || target.starts_with("mailto:") {
+            HyperlinkTarget::External(target.to_string())
+        } else if let Some(location) = target
+            .strip_prefix("internal:")
+            .or_else(|| target.strip_prefix('#'))
+        {
+            HyperlinkTarget::Location(location.to_string())
+        } else if is_sheet_reference(target) {
+            HyperlinkTarget::Location(target.to_string())
+        } else if target.contains('@') && !target.contains(['/', '\\']) {
+            HyperlinkTarget::External(format!("mailto:{}", target))
+        } else {
+            HyperlinkTarget::External(target.to_string())
+        }
+    }
+}
+
+// `Sheet2!A1`, `'My Sheet'!$A$1:$B$2`, a sheet name followed by a cell or a range
+fn is_sheet_reference(target: &str) -> bool {
+    let Some((sheet, range)) = target.rsplit_once('!') else {
+        return false;
+    };
+    let sheet_ok = match sheet.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+        Some(quoted) => !quoted.is_empty() && !quoted.replace("''", "").contains('\''),
+        None => {
+            !sheet.is_empty() && !sheet.contains([' ', '\'', '/', '\\', '?', '*', '[', ']', ':'])
+        }
+    };
+    sheet_ok
+        && range.split(':').all(|cell| parse_cell_ref(cell).is_ok())
+        && range.split(':').count() <= 2
+}
+
+#[derive(Debug, Clone)]
+pub struct Hyperlink {
+    pub reference: String,
+    pub target: HyperlinkTarget,
+    pub tooltip: Option<String>,
+    // relationship id of an external target, set when the sheet register this link.
+    pub r_id: Option<String>,
+}
+
+impl Hyperlink {
+    pub fn new(reference: &str, target: &str, tooltip: Option<&str>) -> Self {
+        Hyperlink {
+            reference: reference.to_string(),
+            target: HyperlinkTarget::parse(target),
+            tooltip: tooltip.map(|t| t.to_string()),
+            r_id: None,
+        }
+    }
+}
+
+impl XMLString for Hyperlink {
+    fn to_xml(self, writer: &mut XmlWriter) {
+        writer.start_element("hyperlink");
+        writer.write_attribute("ref", &self.reference);
+        if let Some(r_id) = &self.r_id {
+            writer.write_attribute("r:id", r_id);
+        }
+        if let HyperlinkTarget::Location(location) = &self.target {
+            writer.write_attribute("location", &escape_xml(location));
+        }
+        if let Some(tooltip) = &self.tooltip {
+            writer.write_attribute("tooltip", &escape_xml(tooltip));
+        }
+        writer.end_element();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_location(target: &str) -> bool {
+        matches!(HyperlinkTarget::parse(target), HyperlinkTarget::Location(_))
+    }
+
+    #[test]
+    fn sheet_references_are_locations() {
+        assert!(is_location("Sheet2!A1"));
+        assert!(is_location("'My Sheet'!$A$1:$B$2"));
+        assert!(is_location("'It''s'!C3"));
+        assert!(is_location("#Summary"));
+        assert!(is_location("internal:Data!A1"));
+    }
+
+    #[test]
+    fn other_targets_with_a_bang_are_external() {
+        assert!(!is_location("reports/q1!final.xlsx"));
+        assert!(!is_location("My Sheet!A1"));
+        assert!(!is_location("Sheet2!Total"));
+        assert!(!is_location("www.example.com/#!/home"));
+    }
+
+    #[test]
+    fn a_replaced_link_drops_its_relationship() {
+        let mut work_sheet = crate::work_sheet::WorkSheet::blank("Links");
+        work_sheet
+            .add_hyperlink("A1", "https://old.example.com", None)
+            .unwrap();
+        work_sheet
+            .add_hyperlink("A1", "https://new.example.com", None)
+            .unwrap();
+        let (_, rels) = work_sheet.parts(1).remove(0);
+        let rels = String::from_utf8(rels).unwrap();
+        assert!(!rels.contains("old.example.com"));
+        assert_eq!(rels.matches("<Relationship ").count(), 1);
+    }
+}
@@ -0,0 +1,321 @@
+// images inserted in a sheet, their size and dpi are read from the file headers.
+
+use std::fs;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    Bmp,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::Gif => "gif",
+            ImageFormat::Bmp => "bmp",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Gif => "image/gif",
+            ImageFormat::Bmp => "image/bmp",
+        }
+    }
+}
+
+/// where an image comes from, a file path or the bytes of the file.
+pub enum ImageSource {
+    Path(String),
+    Bytes(Vec<u8>),
+}
+
+impl From<&str> for ImageSource {
+    fn from(path: &str) -> Self {
+        ImageSource::Path(path.to_string())
+    }
+}
+
+impl From<String> for ImageSource {
+    fn from(path: String) -> Self {
+        ImageSource::Path(path)
+    }
+}
+
+impl From<Vec<u8>> for ImageSource {
+    fn from(bytes: Vec<u8>) -> Self {
+        ImageSource::Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for ImageSource {
+    fn from(bytes: &[u8]) -> Self {
+        ImageSource::Bytes(bytes.to_vec())
+    }
+}
+
+/// how a drawing object moves and sizes with the cells under it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Anchor {
+    // moves with its top left cell, keeps its size
+    #[default]
+    OneCell,
+    // moves and sizes with the cells
+    TwoCell,
+    // fixed position on the sheet
+    Absolute,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageOptions {
+    // offset from the top left corner of the cell, in pixels
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub x_scale: f64,
+    pub y_scale: f64,
+    pub alt_text: Option<String>,
+    pub anchor: Anchor,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            x_offset: 0,
+            y_offset: 0,
+            x_scale: 1.0,
+            y_scale: 1.0,
+            alt_text: None,
+            anchor: Anchor::OneCell,
+        }
+    }
+}
+
+impl ImageOptions {
+    /// fails for a scale that is not a positive number
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let valid = |scale: f64| scale.is_finite() && scale > 0.0;
+        if !valid(self.x_scale) || !valid(self.y_scale) {
+            return Err("Image scale must be a positive number");
+        }
+        Ok(())
+    }
+}
+
+// dpi read from a file is kept in this range, a few dots per inch would make an image
+// many times the size of the sheet
+static MIN_DPI: f64 = 24.0;
+static MAX_DPI: f64 = 4800.0;
+
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub data: Vec<u8>,
+    pub format: ImageFormat,
+    // in pixels
+    pub width: u32,
+    pub height: u32,
+    pub x_dpi: f64,
+    pub y_dpi: f64,
+}
+
+impl Image {
+    pub fn new(source: ImageSource) -> Result<Self, &'static str> {
+        let data = match source {
+            ImageSource::Path(path) => fs::read(path).map_err(|_| "Could not read image file")?,
+            ImageSource::Bytes(bytes) => bytes,
+        };
+        let (format, width, height, x_dpi, y_dpi) = sniff(&data)?;
+        if width == 0 || height == 0 {
+            return Err("Image has no size");
+        }
+        let clamp_dpi = |dpi: f64| match dpi.is_finite() {
+            true => dpi.clamp(MIN_DPI, MAX_DPI),
+            false => 96.0,
+        };
+        Ok(Image {
+            data,
+            format,
+            width,
+            height,
+            x_dpi: clamp_dpi(x_dpi),
+            y_dpi: clamp_dpi(y_dpi),
+        })
+    }
+
+    /// size in pixels as excel shows it (at 96 dpi), scaled.
+    pub fn display_size(&self, x_scale: f64, y_scale: f64) -> (u32, u32) {
+        let width = self.width as f64 * x_scale * 96.0 / self.x_dpi;
+        let height = self.height as f64 * y_scale * 96.0 / self.y_dpi;
+        // at least a pixel, a tiny scale still leaves the image in the sheet
+        (
+            width.round().max(1.0) as u32,
+            height.round().max(1.0) as u32,
+        )
+    }
+}
+
+// (format, width, height, x dpi, y dpi)
+type ImageInfo = (ImageFormat, u32, u32, f64, f64);
+
+fn sniff(data: &[u8]) -> Result<ImageInfo, &'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        sniff_png(data)
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        sniff_jpeg(data)
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        sniff_gif(data)
+    } else if data.starts_with(b"BM") {
+        sniff_bmp(data)
+    } else {
+        Err("Unsupported image format")
+    }
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_u16(data: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le_i32(data: &[u8], at: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn sniff_png(data: &[u8]) -> Result<ImageInfo, &'static str> {
+    let invalid = "Invalid png image";
+    let width = be_u32(data, 16).ok_or(invalid)?;
+    let height = be_u32(data, 20).ok_or(invalid)?;
+    let (mut x_dpi, mut y_dpi) = (96.0, 96.0);
+
+    // walk the chunks for pHYs, it comes before the image data.
+    let mut at = 8;
+    while let (Some(length), Some(kind)) = (be_u32(data, at), data.get(at + 4..at + 8)) {
+        if kind == b"IDAT" || kind == b"IEND" {
+            break;
+        }
+        if kind == b"pHYs" && data.get(at + 16) == Some(&1) {
+            // pixels per meter
+            let x = be_u32(data, at + 8).ok_or(invalid)? as f64;
+            let y = be_u32(data, at + 12).ok_or(invalid)? as f64;
+            if x > 0.0 && y > 0.0 {
+                x_dpi = x * 0.0254;
+                y_dpi = y * 0.0254;
+            }
+        }
+        at += length as usize + 12;
+    }
+    Ok((ImageFormat::Png, width, height, x_dpi, y_dpi))
+}
+
+fn sniff_jpeg(data: &[u8]) -> Result<ImageInfo, &'static str> {
+    let invalid = "Invalid jpeg image";
+    let (mut x_dpi, mut y_dpi) = (96.0, 96.0);
+    let mut at = 2;
+    while at + 4 <= data.len() {
+        if data[at] != 0xFF {
+            return Err(invalid);
+        }
+        let marker = data[at + 1];
+        let length = be_u16(data, at + 2).ok_or(invalid)? as usize;
+        // APP0 JFIF, units 1 = dots per inch, 2 = dots per cm
+        if marker == 0xE0 && data.get(at + 4..at + 9) == Some(b"JFIF\0") {
+            let units = *data.get(at + 11).ok_or(invalid)?;
+            let x = be_u16(data, at + 12).ok_or(invalid)? as f64;
+            let y = be_u16(data, at + 14).ok_or(invalid)? as f64;
+            if x > 0.0 && y > 0.0 {
+                match units {
+                    1 => (x_dpi, y_dpi) = (x, y),
+                    2 => (x_dpi, y_dpi) = (x * 2.54, y * 2.54),
+                    _ => {}
+                }
+            }
+        }
+        // start of frame, except DHT (C4), JPG (C8) and DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+            let height = be_u16(data, at + 5).ok_or(invalid)?;
+            let width = be_u16(data, at + 7).ok_or(invalid)?;
+            return Ok((ImageFormat::Jpeg, width, height, x_dpi, y_dpi));
+        }
+        at += 2 + length;
+    }
+    Err(invalid)
+}
+
+fn sniff_gif(data: &[u8]) -> Result<ImageInfo, &'static str> {
+    let invalid = "Invalid gif image";
+    let width = le_u16(data, 6).ok_or(invalid)?;
+    let height = le_u16(data, 8).ok_or(invalid)?;
+    Ok((ImageFormat::Gif, width, height, 96.0, 96.0))
+}
+
+fn sniff_bmp(data: &[u8]) -> Result<ImageInfo, &'static str> {
+    let invalid = "Invalid bmp image";
+    let width = le_i32(data, 18).ok_or(invalid)?.unsigned_abs();
+    // negative height means a top down bitmap
+    let height = le_i32(data, 22).ok_or(invalid)?.unsigned_abs();
+    let (mut x_dpi, mut y_dpi) = (96.0, 96.0);
+    if let (Some(x), Some(y)) = (le_i32(data, 38), le_i32(data, 42)) {
+        if x > 0 && y > 0 {
+            // pixels per meter
+            x_dpi = x as f64 * 0.0254;
+            y_dpi = y as f64 * 0.0254;
+        }
+    }
+    Ok((ImageFormat::Bmp, width, height, x_dpi, y_dpi))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 10x20 png header with a pHYs chunk of this many pixels per meter
+    fn png(pixels_per_meter: u32) -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        data.extend(13u32.to_be_bytes());
+        data.extend(b"IHDR");
+        data.extend(10u32.to_be_bytes());
+        data.extend(20u32.to_be_bytes());
+        data.extend([8, 6, 0, 0, 0]);
+        data.extend([0; 4]);
+        data.extend(9u32.to_be_bytes());
+        data.extend(b"pHYs");
+        data.extend(pixels_per_meter.to_be_bytes());
+        data.extend(pixels_per_meter.to_be_bytes());
+        data.push(1);
+        data.extend([0; 4]);
+        data
+    }
+
+    #[test]
+    fn dpi_is_clamped() {
+        let image = Image::new(ImageSource::Bytes(png(1))).unwrap();
+        assert_eq!(image.x_dpi, MIN_DPI);
+        assert_eq!(image.display_size(1.0, 1.0), (40, 80));
+        let image = Image::new(ImageSource::Bytes(png(u32::MAX))).unwrap();
+        assert_eq!(image.y_dpi, MAX_DPI);
+        assert_eq!(image.display_size(1.0, 1.0), (1, 1));
+    }
+
+    #[test]
+    fn scales_must_be_positive() {
+        for scale in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let options = ImageOptions {
+                x_scale: scale,
+                ..Default::default()
+            };
+            assert!(options.validate().is_err());
+        }
+        assert!(ImageOptions::default().validate().is_ok());
+    }
+}
@@ -1,12 +1,46 @@
 pub mod cell;
+pub mod chart;
+pub mod chart_sheet;
+pub mod comment;
+pub mod csv_sheet;
+pub mod defined_name;
+pub mod drawing;
+pub mod escape;
+pub mod header_footer;
+pub mod hyperlink;
+pub mod image;
+pub mod package;
+pub mod page_setup;
+pub mod pivot_table;
+pub mod reader;
+pub mod rich_text;
 pub mod row;
+pub mod row_view;
+pub mod save_options;
+#[cfg(feature = "serde")]
+pub mod serde_sheet;
+pub mod sparkline;
+pub mod streaming_work_sheet;
 pub mod traits;
 pub mod work_book;
 pub mod work_sheet;
 pub mod xml_templates;
 
+use chart::{
+    Chart, ChartOptions, ChartSeries, ChartType, ErrorBarValue, ErrorBars, Grouping,
+    LegendPosition,
+    Trendline,
+};
+use csv_sheet::{CsvOptions, CsvWriteOptions};
+use defined_name::BuiltInName;
+use header_footer::{HeaderFooter, HeaderFooterSection};
+use page_setup::{Orientation, PageSetup, PaperSize};
+use pivot_table::{Aggregation, PivotTable};
+use rich_text::Run;
+use sparkline::{SparklineOptions, SparklineType};
 use work_book::WorkBook;
 use work_sheet::WorkSheet;
+use xml_templates::doc_props::{CustomPropertyValue, DocProperties};
 use xml_templates::style::{FontStyle, UnderLine};
 
 struct StudentMarks {
@@ -26,6 +60,18 @@ impl StudentMarks {
     }
 }
 
+// the days each student came in, a row of the sheet with the `serde` feature
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct Attendance {
+    #[serde(rename = "Name")]
+    name: &'static str,
+    days: u32,
+    excused: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<&'static str>,
+}
+
 fn main() {
     // create a work book
     let mut work_book = WorkBook::new();
@@ -35,10 +81,10 @@ fn main() {
 
     // add headers
     let header = work_sheet_1.add_blank_row();
-    header.add_string("Name".to_string());
-    header.add_string("English".to_string());
-    header.add_string("Maths".to_string());
-    header.add_string("Science".to_string());
+    header.add_string("Name".to_string()).unwrap();
+    header.add_string("English".to_string()).unwrap();
+    header.add_string("Maths".to_string()).unwrap();
+    header.add_string("Science".to_string()).unwrap();
 
     // add student marks
     let marks = vec![
@@ -48,38 +94,220 @@ fn main() {
     ];
     for mark in marks {
         let row = work_sheet_1.add_blank_row();
-        row.add_string(mark.name);
+        row.add_string(mark.name).unwrap();
         row.add_number(mark.english).unwrap();
         row.add_number(mark.maths).unwrap();
         row.add_number(mark.science).unwrap();
     }
 
+    // print setup
+    let page_setup = PageSetup::new()
+        .paper_size(PaperSize::A4)
+        .orientation(Orientation::Landscape)
+        .fit_to_pages(1, 0)
+        .print_gridlines(true)
+        .print_area("A1:D4")
+        .repeat_rows(1, 1);
+    work_sheet_1.page_setup(page_setup).unwrap();
+    work_sheet_1.add_row_break(3).unwrap();
+    let header = HeaderFooter::new().center(
+        HeaderFooterSection::new()
+            .font("Arial", "Bold")
+            .font_size(14)
+            .text("Marks & Grades"),
+    );
+    work_sheet_1.set_header(header).unwrap();
+    let footer = HeaderFooter::new()
+        .left(HeaderFooterSection::new().sheet_name())
+        .right(HeaderFooterSection::new().text("Page ").page_of_pages());
+    work_sheet_1.set_footer(footer).unwrap();
+
+    // trend of the marks of each student
+    for row in 2..=4 {
+        let options = SparklineOptions {
+            kind: SparklineType::Column,
+            high_point: true,
+            low_point: true,
+            ..Default::default()
+        };
+        work_sheet_1
+            .add_sparkline(&format!("E{row}"), &format!("B{row}:D{row}"), options)
+            .unwrap();
+    }
+
+    // chart of the marks, science as a line on a secondary axis
+    let mut chart = Chart::new(ChartType::Column)
+        .title("Marks")
+        .x_axis_title("Student")
+        .y_axis_title("Marks")
+        .legend(Some(LegendPosition::Bottom))
+        .style(10);
+    for (column, color) in [("B", "4472C4"), ("C", "ED7D31")] {
+        chart = chart.add_series(
+            ChartSeries::new(&format!("'sheet 1'!${column}$2:${column}$4"))
+                .categories("'sheet 1'!$A$2:$A$4")
+                .name(&format!("='sheet 1'!${column}$1"))
+                .color(color)
+                .error_bars(ErrorBars::new(ErrorBarValue::Percentage(5.0))),
+        );
+    }
+    let science = Chart::new(ChartType::Line)
+        .add_series(
+            ChartSeries::new("'sheet 1'!$D$2:$D$4")
+                .categories("'sheet 1'!$A$2:$A$4")
+                .name("='sheet 1'!$D$1")
+                .trendline(Trendline::Linear),
+        )
+        .y_axis_title("Science")
+        .secondary_axis(true);
+    let chart = chart.combine(science);
+    work_sheet_1
+        .insert_chart("F2", chart, ChartOptions::default())
+        .unwrap();
+
     // add this work sheet to the work book
     work_book.add_sheet(work_sheet_1);
 
+    // a sheet with only a chart, share of each subject per student
+    let mut share = Chart::new(ChartType::Bar)
+        .title("Share of marks")
+        .grouping(Grouping::PercentStacked);
+    for column in ["B", "C", "D"] {
+        share = share.add_series(
+            ChartSeries::new(&format!("'sheet 1'!${column}$2:${column}$4"))
+                .categories("'sheet 1'!$A$2:$A$4")
+                .name(&format!("='sheet 1'!${column}$1")),
+        );
+    }
+    work_book.add_chartsheet("Share", share).unwrap();
+
     let mut work_sheet_2 = WorkSheet::blank("sheet 2");
     let row_1 = work_sheet_2.add_blank_row();
 
     // bold cell
-    let bold_cell = row_1.add_string("Bold word".to_string());
+    let bold_cell = row_1.add_string("Bold word".to_string()).unwrap();
     let font_style = FontStyle::new().bold(true);
     bold_cell.set_font_style(font_style);
 
     // strike cell
-    let strike_cell = row_1.add_string("Crossed this word".to_string());
+    let strike_cell = row_1.add_string("Crossed this word".to_string()).unwrap();
     let s_style = FontStyle::new().strike(true);
     strike_cell.set_font_style(s_style);
 
-    let under_line_cell = row_1.add_string("Has undeline".to_string());
+    let under_line_cell = row_1.add_string("Has undeline".to_string()).unwrap();
     let und_style = FontStyle::new().underline(Some(UnderLine::Double));
     under_line_cell.set_font_style(und_style);
 
+    // one cell, mixed formatting
+    row_1.add_rich_string(vec![
+        Run::new("Total: ").bold(),
+        Run::new("42").color("FFFF0000"),
+    ])
+    .unwrap();
+
+    // links
+    let row_2 = work_sheet_2.add_blank_row();
+    row_2
+        .add_url("https://example.com/?a=1&b=2", "Example".to_string())
+        .unwrap();
+    row_2.add_string("Back to marks".to_string()).unwrap();
+    // kept as typed: the spaces, the tab and the literal _x0041_
+    row_2
+        .add_string("  R&D\tcode _x0041_\u{1} ".to_string())
+        .unwrap();
+    work_sheet_2
+        .add_hyperlink("B2", "'sheet 1'!A1", Some("Go to sheet 1"))
+        .unwrap();
+
     
 
 
+    // notes
+    work_sheet_2
+        .add_note("A1", "Teacher", "Bold words stand out.")
+        .unwrap()
+        .add_run(" Use them sparingly.", FontStyle::new().italic(true))
+        .set_visible(true);
+    work_sheet_2
+        .add_threaded_comment("C1", "Teacher", "Is the strike needed here?")
+        .unwrap()
+        .add_reply("Student", "Yes, the word was crossed out.")
+        .set_resolved(true);
+
     // add this work sheet to the work book
     work_book.add_sheet(work_sheet_2);
 
+    // pivot of the marks on its own sheet
+    work_book.add_sheet(WorkSheet::blank("Summary"));
+    let pivot = PivotTable::new("MarksPivot", "'sheet 1'!$A$1:$D$4")
+        .row_field("Name")
+        .data_field("English", Aggregation::Sum)
+        .data_field("Maths", Aggregation::Average);
+    work_book.add_pivot_table("Summary", "A3", pivot).unwrap();
+
+    // a sheet written row by row, for exports too large to hold in memory
+    let mut log = work_book.add_streaming_sheet("Log").unwrap();
+    for day in 1..=3 {
+        let row = log.add_blank_row().unwrap();
+        row.add_string(format!("Day {}", day)).unwrap();
+        row.add_number((day * 10).to_string()).unwrap();
+    }
+    log.finish().unwrap();
+
+    // a sheet from a CSV dump, the types of its fields are guessed
+    let csv = "Name,Joined,Active,Score\nLinda,2024-01-31,true,12.5%\nTom,2024-02-15,FALSE,7%\n";
+    let csv_options = CsvOptions {
+        header: true,
+        ..Default::default()
+    };
+    work_book.add_sheet(WorkSheet::from_csv("Import", csv.as_bytes(), csv_options).unwrap());
+
+    // a sheet of serializable records, a header row from their fields
+    #[cfg(feature = "serde")]
+    {
+        let attendance = [
+            Attendance { name: "Copper", days: 180, excused: false, note: None },
+            Attendance { name: "Gold", days: 172, excused: true, note: Some("Flu in May") },
+        ];
+        work_book.add_sheet(WorkSheet::from_serialize("Attendance", &attendance).unwrap());
+    }
+
+    // names
+    work_book.define_name("Marks", "='sheet 1'!$B$2:$D$4").unwrap();
+    work_book
+        .define_built_in_name("sheet 2", BuiltInName::PrintArea, "'sheet 2'!$A$1:$C$1")
+        .unwrap();
+
+    // document properties
+    work_book.set_properties(DocProperties {
+        title: Some("Student marks".to_string()),
+        author: Some("Sheet".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    work_book
+        .add_custom_property("Term", CustomPropertyValue::Text("Final".to_string()))
+        .unwrap();
+    work_book
+        .add_custom_property("Published", CustomPropertyValue::Bool(true))
+        .unwrap();
+
     // save the work book
-    work_book.save();
+    work_book.save("test.xlsx").unwrap();
+
+    // read it back
+    let saved = WorkBook::open("test.xlsx").unwrap();
+    for work_sheet in saved.work_sheets.iter() {
+        println!("{}: {} rows", work_sheet.name, work_sheet.rows.len());
+    }
+    // or one row at a time
+    for row in WorkBook::stream_rows("test.xlsx", "sheet 1").unwrap() {
+        let row = row.unwrap();
+        println!("row {}: {} cells", row.row_number, row.cells.len());
+    }
+    // and the imported sheet as CSV again
+    let import = saved.work_sheets.iter().find(|w| w.name == "Import").unwrap();
+    import
+        .write_csv(std::io::stdout(), CsvWriteOptions::default())
+        .unwrap();
 }
@@ -0,0 +1,316 @@
+// what a read package holds beyond the types of this crate: parts, relationships and xml
+// elements kept as they were, so a template made in excel survives a round trip.
+
+use xmlwriter::XmlWriter;
+
+use crate::{
+    escape::escape_xml,
+    xml_templates::{content_type::ContentType, relation_ship::RelationShip},
+};
+
+// parts this crate writes itself, a kept part with one of these names is renamed.
+static NUMBERED_PARTS: [(&str, &str); 11] = [
+    ("xl/worksheets/sheet", ".xml"),
+    ("xl/chartsheets/sheet", ".xml"),
+    ("xl/drawings/drawing", ".xml"),
+    ("xl/drawings/vmlDrawing", ".vml"),
+    ("xl/charts/chart", ".xml"),
+    ("xl/media/image", "."),
+    ("xl/comments", ".xml"),
+    ("xl/threadedComments/threadedComment", ".xml"),
+    ("xl/pivotTables/pivotTable", ".xml"),
+    ("xl/pivotCache/pivotCacheDefinition", ".xml"),
+    ("xl/pivotCache/pivotCacheRecords", ".xml"),
+];
+static FIXED_PARTS: [&str; 7] = [
+    "xl/workbook.xml",
+    "xl/styles.xml",
+    "xl/sharedStrings.xml",
+    "xl/persons/person.xml",
+    "docProps/core.xml",
+    "docProps/app.xml",
+    "docProps/custom.xml",
+];
+
+/// the name a kept part is written under, `xl/drawings/drawing1.xml` becomes
+/// `xl/drawings/kept_drawing1.xml` so it never meets a part this crate writes.
+pub fn kept_part_name(name: &str) -> String {
+    let generated = FIXED_PARTS.contains(&name)
+        || NUMBERED_PARTS.iter().any(|(prefix, suffix)| {
+            name.strip_prefix(prefix)
+                .and_then(|rest| rest.find(suffix).map(|at| &rest[..at]))
+                .is_some_and(|number| {
+                    !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+                })
+        });
+    if !generated {
+        return name.to_string();
+    }
+    match name.rsplit_once('/') {
+        Some((dir, file)) => format!("{}/kept_{}", dir, file),
+        None => format!("kept_{}", name),
+    }
+}
+
+/// the id a kept relationship of a part this crate writes gets, `rId3` becomes `rId3k`;
+/// relationships this crate adds are `rId` and a number so the two never meet.
+pub fn kept_relation_id(id: &str) -> String {
+    format!("{}k", id)
+}
+
+/// a relationship to a kept part or an external target
+#[derive(Debug, Clone)]
+pub struct PreservedRelation {
+    pub id: String,
+    pub rel_type: String,
+    // the path in the package, or the url of an external target
+    pub target: String,
+    pub external: bool,
+}
+
+impl PreservedRelation {
+    // `.../relationships/worksheet` is a `worksheet`, in transitional and strict files alike.
+    pub fn is(&self, kind: &str) -> bool {
+        self.rel_type.rsplit('/').next() == Some(kind)
+    }
+
+    /// add to the relationships of a part this crate writes.
+    pub fn add_to(&self, relation_ship: &mut RelationShip) {
+        let target = match self.external {
+            true => self.target.clone(),
+            false => format!("/{}", self.target),
+        };
+        relation_ship.add_preserved(&self.id, &self.rel_type, &target, self.external);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PreservedNode {
+    Element(PreservedElement),
+    Text(String),
+}
+
+/// an xml element of a read part that this crate does not model, eg. `<cols>` of a sheet.
+#[derive(Debug, Clone)]
+pub struct PreservedElement {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<PreservedNode>,
+}
+
+impl PreservedElement {
+    /// the local name, `x14:sparklineGroups` is `sparklineGroups`
+    pub fn local_name(&self) -> &str {
+        self.name.rsplit(':').next().unwrap_or(&self.name)
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &PreservedElement> {
+        self.children.iter().filter_map(|child| match child {
+            PreservedNode::Element(element) => Some(element),
+            PreservedNode::Text(_) => None,
+        })
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // `r:id` and the like, the relationships this element points to.
+    fn relation_ids(&self) -> impl Iterator<Item = &str> {
+        self.attributes
+            .iter()
+            .filter(|(name, _)| name.ends_with(":id") && !name.starts_with("xmlns"))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn write(&self, writer: &mut XmlWriter) {
+        writer.start_element(&self.name);
+        for (name, value) in self.attributes.iter() {
+            writer.write_attribute(name, &escape_xml(value));
+        }
+        for child in self.children.iter() {
+            match child {
+                PreservedNode::Element(element) => element.write(writer),
+                PreservedNode::Text(text) => writer.write_text(&escape_xml(text)),
+            }
+        }
+        writer.end_element();
+    }
+}
+
+/// the elements of a read part (a sheet, the work book, the style sheet) this crate
+/// does not model, written back among the ones it writes in the order the schema asks for.
+#[derive(Debug, Default, Clone)]
+pub struct PreservedElements {
+    // namespace declarations and `mc:Ignorable` of the root element
+    root_attributes: Vec<(String, String)>,
+    elements: Vec<PreservedElement>,
+}
+
+impl PreservedElements {
+    pub fn new(root_attributes: Vec<(String, String)>, elements: Vec<PreservedElement>) -> Self {
+        PreservedElements {
+            root_attributes,
+            elements,
+        }
+    }
+
+    /// the namespaces the kept elements use, on the root element after the ones it writes.
+    pub fn write_root_attributes(&self, writer: &mut XmlWriter) {
+        for (name, value) in self.root_attributes.iter() {
+            // the writers declare these themselves
+            if name != "xmlns" && name != "xmlns:r" {
+                writer.write_attribute(name, &escape_xml(value));
+            }
+        }
+    }
+
+    pub fn get(&self, local_name: &str) -> Option<&PreservedElement> {
+        self.elements.iter().find(|e| e.local_name() == local_name)
+    }
+
+    /// take out the element with this name, to be written by the caller.
+    pub fn take(&mut self, local_name: &str) -> Option<PreservedElement> {
+        let position = self
+            .elements
+            .iter()
+            .position(|e| e.local_name() == local_name)?;
+        Some(self.elements.remove(position))
+    }
+
+    /// leave out the elements with these names, the part writes its own. returns the
+    /// relationship ids the removed elements pointed to.
+    pub fn remove(&mut self, local_names: &[&str]) -> Vec<String> {
+        let mut relation_ids = vec![];
+        self.elements.retain(|e| {
+            if !local_names.contains(&e.local_name()) {
+                return true;
+            }
+            relation_ids.extend(e.relation_ids().map(|id| id.to_string()));
+            false
+        });
+        relation_ids
+    }
+
+    /// write the kept elements coming before `name` in `order` and not written yet,
+    /// an empty `name` writes the rest. an element missing from `order` (eg.
+    /// `mc:AlternateContent`) stays after the element it followed in the read part.
+    pub fn write_before(&mut self, writer: &mut XmlWriter, order: &[&str], name: &str) {
+        let limit = match name {
+            "" => usize::MAX,
+            _ => order.iter().position(|o| *o == name).unwrap_or(order.len()),
+        };
+        let mut position = 0;
+        let mut rest = vec![];
+        for element in self.elements.drain(..) {
+            if let Some(p) = order.iter().position(|o| *o == element.local_name()) {
+                position = p;
+            }
+            if position < limit {
+                element.write(writer);
+            } else {
+                rest.push(element);
+            }
+        }
+        self.elements = rest;
+    }
+}
+
+/// a tab of a read work book this crate does not model, eg. a chart sheet or a
+/// dialog sheet, its part is kept as it was.
+#[derive(Debug, Clone)]
+pub struct PreservedSheet {
+    pub name: String,
+    // relationship id from the work book, see `kept_relation_id`
+    pub r_id: String,
+    pub state: Option<String>,
+}
+
+/// a part kept with its content type and relationships
+#[derive(Debug)]
+struct PreservedPart {
+    name: String,
+    // the override of the read package, None when the extension gives the type
+    content_type: Option<String>,
+    data: Vec<u8>,
+    relations: Vec<PreservedRelation>,
+}
+
+/// every part of a read package this crate does not model.
+#[derive(Debug, Default)]
+pub struct PreservedParts {
+    parts: Vec<PreservedPart>,
+    // (extension, content type) of the read package
+    defaults: Vec<(String, String)>,
+}
+
+impl PreservedParts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.parts.iter().any(|p| p.name == name)
+    }
+
+    pub fn add(
+        &mut self,
+        name: &str,
+        content_type: Option<String>,
+        data: Vec<u8>,
+        relations: Vec<PreservedRelation>,
+    ) {
+        self.parts.push(PreservedPart {
+            name: name.to_string(),
+            content_type,
+            data,
+            relations,
+        });
+    }
+
+    pub fn add_default(&mut self, extension: &str, content_type: &str) {
+        self.defaults
+            .push((extension.to_string(), content_type.to_string()));
+    }
+
+    /// the content types of the kept parts
+    pub fn register(&self, content_type: &mut ContentType) {
+        if self.parts.is_empty() {
+            return;
+        }
+        for (extension, default) in self.defaults.iter() {
+            if !matches!(extension.as_str(), "rels" | "xml") {
+                content_type.add_default(extension, default);
+            }
+        }
+        for part in self.parts.iter() {
+            if let Some(override_type) = &part.content_type {
+                content_type.add_override(&format!("/{}", part.name), override_type);
+            }
+        }
+    }
+
+    /// the kept parts and their relationships, as (path in the package, content).
+    pub fn parts(&self) -> Vec<(String, Vec<u8>)> {
+        let mut parts = vec![];
+        for part in self.parts.iter() {
+            parts.push((part.name.clone(), part.data.clone()));
+            if part.relations.is_empty() {
+                continue;
+            }
+            let mut relation_ship = RelationShip::new();
+            for relation in part.relations.iter() {
+                relation.add_to(&mut relation_ship);
+            }
+            let rels_name = match part.name.rsplit_once('/') {
+                Some((dir, file)) => format!("{}/_rels/{}.rels", dir, file),
+                None => format!("_rels/{}.rels", part.name),
+            };
+            parts.push((rels_name, relation_ship.to_xml().into_bytes()));
+        }
+        parts
+    }
+}
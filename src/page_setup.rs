@@ -0,0 +1,315 @@
+use xmlwriter::XmlWriter;
+
+use crate::{
+    defined_name::quote_sheet_name,
+    row::{column_number_to_name, parse_cell_ref, MAX_ROWS},
+    traits::XMLString,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum PaperSize {
+    Letter,
+    Tabloid,
+    Legal,
+    A3,
+    A4,
+    A5,
+    // any other paper size id from the spec
+    Other(u32),
+}
+
+impl PaperSize {
+    fn id(&self) -> u32 {
+        match self {
+            PaperSize::Letter => 1,
+            PaperSize::Tabloid => 3,
+            PaperSize::Legal => 5,
+            PaperSize::A3 => 8,
+            PaperSize::A4 => 9,
+            PaperSize::A5 => 11,
+            PaperSize::Other(id) => *id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+/// page margins in inches
+#[derive(Debug, Clone, Copy)]
+pub struct PageMargins {
+    pub left: f64,
+    pub right: f64,
+    pub top: f64,
+    pub bottom: f64,
+    pub header: f64,
+    pub footer: f64,
+}
+
+impl Default for PageMargins {
+    // excel's "Normal" margins
+    fn default() -> Self {
+        Self {
+            left: 0.7,
+            right: 0.7,
+            top: 0.75,
+            bottom: 0.75,
+            header: 0.3,
+            footer: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PageSetup {
+    paper_size: Option<PaperSize>,
+    orientation: Option<Orientation>,
+    scale: Option<u16>,
+    // (width, height) in pages, 0 means automatic
+    fit_to_pages: Option<(u16, u16)>,
+    margins: PageMargins,
+    center_horizontally: bool,
+    center_vertically: bool,
+    print_gridlines: bool,
+    print_headings: bool,
+    print_area: Option<String>,
+    // (first, last) row numbers, 1 based
+    repeat_rows: Option<(usize, usize)>,
+    // (first, last) column letters
+    repeat_columns: Option<(String, String)>,
+}
+
+impl PageSetup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn paper_size(mut self, paper_size: PaperSize) -> Self {
+        self.paper_size = Some(paper_size);
+        self
+    }
+
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = Some(orientation);
+        self
+    }
+
+    /// print scale in percent (10 - 400), ignored when fit to pages is set.
+    pub fn scale(mut self, scale: u16) -> Self {
+        self.scale = Some(scale);
+        self
+    }
+
+    /// fit the print out to `width` x `height` pages, 0 leaves that direction automatic.
+    pub fn fit_to_pages(mut self, width: u16, height: u16) -> Self {
+        self.fit_to_pages = Some((width, height));
+        self
+    }
+
+    pub fn margins(mut self, margins: PageMargins) -> Self {
+        self.margins = margins;
+        self
+    }
+
+    pub fn center_horizontally(mut self, center: bool) -> Self {
+        self.center_horizontally = center;
+        self
+    }
+
+    pub fn center_vertically(mut self, center: bool) -> Self {
+        self.center_vertically = center;
+        self
+    }
+
+    pub fn print_gridlines(mut self, print: bool) -> Self {
+        self.print_gridlines = print;
+        self
+    }
+
+    pub fn print_headings(mut self, print: bool) -> Self {
+        self.print_headings = print;
+        self
+    }
+
+    /// range to print, eg. `A1:D20`
+    pub fn print_area(mut self, range: &str) -> Self {
+        self.print_area = Some(range.to_string());
+        self
+    }
+
+    /// rows repeated at the top of every page, eg. `repeat_rows(1, 1)`
+    pub fn repeat_rows(mut self, first_row: usize, last_row: usize) -> Self {
+        self.repeat_rows = Some((first_row, last_row));
+        self
+    }
+
+    /// columns repeated at the left of every page, eg. `repeat_columns("A", "B")`
+    pub fn repeat_columns(mut self, first_column: &str, last_column: &str) -> Self {
+        self.repeat_columns = Some((
+            first_column.to_ascii_uppercase(),
+            last_column.to_ascii_uppercase(),
+        ));
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if let Some(scale) = self.scale {
+            if !(10..=400).contains(&scale) {
+                return Err("Print scale must be between 10 and 400");
+            }
+        }
+        if let Some(range) = &self.print_area {
+            absolute_range(range)?;
+        }
+        if let Some((first, last)) = self.repeat_rows {
+            if first == 0 || first > last || last > MAX_ROWS {
+                return Err("Invalid repeat rows");
+            }
+        }
+        if let Some((first, last)) = &self.repeat_columns {
+            let (_, first) = parse_cell_ref(&format!("{}1", first))?;
+            let (_, last) = parse_cell_ref(&format!("{}1", last))?;
+            if first > last {
+                return Err("Invalid repeat columns");
+            }
+        }
+        let margins = &self.margins;
+        if [
+            margins.left,
+            margins.right,
+            margins.top,
+            margins.bottom,
+            margins.header,
+            margins.footer,
+        ]
+        .iter()
+        .any(|margin| !margin.is_finite() || *margin < 0.0)
+        {
+            return Err("Page margins must be finite and not negative");
+        }
+        Ok(())
+    }
+
+    pub fn fit_to_page(&self) -> bool {
+        self.fit_to_pages.is_some()
+    }
+
+    /// formula of the `_xlnm.Print_Area` name, if any.
+    pub fn print_area_formula(&self, sheet_name: &str) -> Option<String> {
+        let range = absolute_range(self.print_area.as_ref()?).ok()?;
+        Some(format!("{}!{}", quote_sheet_name(sheet_name), range))
+    }
+
+    /// formula of the `_xlnm.Print_Titles` name, if any.
+    pub fn print_titles_formula(&self, sheet_name: &str) -> Option<String> {
+        let sheet = quote_sheet_name(sheet_name);
+        let mut parts = vec![];
+        if let Some((first, last)) = &self.repeat_columns {
+            parts.push(format!("{}!${}:${}", sheet, first, last));
+        }
+        if let Some((first, last)) = self.repeat_rows {
+            parts.push(format!("{}!${}:${}", sheet, first, last));
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(","))
+        }
+    }
+}
+
+/// `A1:D20` -> `$A$1:$D$20`
+pub fn absolute_range(range: &str) -> Result<String, &'static str> {
+    let mut parts = vec![];
+    for cell_ref in range.split(':') {
+        let (row, column) = parse_cell_ref(cell_ref)?;
+        parts.push(format!("${}${}", column_number_to_name(column), row));
+    }
+    if parts.is_empty() || parts.len() > 2 {
+        return Err("Invalid range");
+    }
+    Ok(parts.join(":"))
+}
+
+// writes <printOptions>, <pageMargins> and <pageSetup>, in that order.
+impl XMLString for PageSetup {
+    fn to_xml(self, writer: &mut XmlWriter) {
+        if self.center_horizontally
+            || self.center_vertically
+            || self.print_gridlines
+            || self.print_headings
+        {
+            writer.start_element("printOptions");
+            if self.center_horizontally {
+                writer.write_attribute("horizontalCentered", "1");
+            }
+            if self.center_vertically {
+                writer.write_attribute("verticalCentered", "1");
+            }
+            if self.print_headings {
+                writer.write_attribute("headings", "1");
+            }
+            if self.print_gridlines {
+                writer.write_attribute("gridLines", "1");
+            }
+            writer.end_element();
+        }
+
+        writer.start_element("pageMargins");
+        writer.write_attribute("left", &self.margins.left.to_string());
+        writer.write_attribute("right", &self.margins.right.to_string());
+        writer.write_attribute("top", &self.margins.top.to_string());
+        writer.write_attribute("bottom", &self.margins.bottom.to_string());
+        writer.write_attribute("header", &self.margins.header.to_string());
+        writer.write_attribute("footer", &self.margins.footer.to_string());
+        writer.end_element();
+
+        writer.start_element("pageSetup");
+        if let Some(paper_size) = self.paper_size {
+            writer.write_attribute("paperSize", &paper_size.id().to_string());
+        }
+        if let Some(scale) = self.scale {
+            writer.write_attribute("scale", &scale.to_string());
+        }
+        if let Some((width, height)) = self.fit_to_pages {
+            writer.write_attribute("fitToWidth", &width.to_string());
+            writer.write_attribute("fitToHeight", &height.to_string());
+        }
+        if let Some(orientation) = self.orientation {
+            match orientation {
+                Orientation::Portrait => writer.write_attribute("orientation", "portrait"),
+                Orientation::Landscape => writer.write_attribute("orientation", "landscape"),
+            }
+        }
+        writer.end_element();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeat_columns_run_left_to_right() {
+        assert!(PageSetup::new().repeat_columns("B", "D").validate().is_ok());
+        assert!(PageSetup::new()
+            .repeat_columns("D", "B")
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn margins_are_finite_and_not_negative() {
+        let margins = |left: f64| PageMargins {
+            left,
+            ..Default::default()
+        };
+        assert!(PageSetup::new().margins(margins(0.0)).validate().is_ok());
+        for left in [-0.1, f64::NAN, f64::INFINITY] {
+            assert!(PageSetup::new().margins(margins(left)).validate().is_err());
+        }
+    }
+}
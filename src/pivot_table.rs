@@ -0,0 +1,790 @@
+// pivot tables (xl/pivotTables/pivotTableN.xml) and their caches
+// (xl/pivotCache/pivotCacheDefinitionN.xml, pivotCacheRecordsN.xml).
+// the cache is marked to refresh on load, excel lays the pivot out again when it opens.
+
+use std::collections::{HashMap, HashSet};
+
+use xmlwriter::{Indent, Options, XmlWriter};
+
+use crate::{
+    escape::escape_xml,
+    row::column_number_to_name,
+    xml_templates::relation_ship::{
+        RelationShip, RS_PIVOT_CACHE_DEFINITION, RS_PIVOT_CACHE_RECORDS,
+    },
+};
+
+static MAIN_XMLNS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+static R_XMLNS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+// the "Values" field, where the data fields go when there is more than one
+static VALUES_FIELD: &str = "-2";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregation {
+    Sum,
+    Count,
+    Average,
+    Min,
+    Max,
+}
+
+impl Aggregation {
+    fn caption(&self) -> &'static str {
+        match self {
+            Aggregation::Sum => "Sum",
+            Aggregation::Count => "Count",
+            Aggregation::Average => "Average",
+            Aggregation::Min => "Min",
+            Aggregation::Max => "Max",
+        }
+    }
+
+    // the subtotal attribute of a data field, sum is the default
+    fn subtotal(&self) -> Option<&'static str> {
+        match self {
+            Aggregation::Sum => None,
+            Aggregation::Count => Some("count"),
+            Aggregation::Average => Some("average"),
+            Aggregation::Min => Some("min"),
+            Aggregation::Max => Some("max"),
+        }
+    }
+}
+
+/// a value of the source range, as the pivot cache keeps it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheValue {
+    Text(String),
+    Number(f64),
+    Blank,
+}
+
+impl CacheValue {
+    // equal values have equal keys, 0.0 and -0.0 are the same number
+    fn key(&self) -> CacheKey<'_> {
+        match self {
+            CacheValue::Text(text) => CacheKey::Text(text),
+            CacheValue::Number(n) if *n == 0.0 => CacheKey::Number(0),
+            CacheValue::Number(n) => CacheKey::Number(n.to_bits()),
+            CacheValue::Blank => CacheKey::Blank,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum CacheKey<'a> {
+    Text(&'a str),
+    Number(u64),
+    Blank,
+}
+
+#[derive(Debug, Clone)]
+struct DataField {
+    field: String,
+    aggregation: Aggregation,
+}
+
+/// A pivot table over a range with a header row, like `Data!A1:D100`,
+/// or over a defined name of such a range.
+///
+/// ```ignore
+/// let pivot = PivotTable::new("Sales", "Data!A1:D100")
+///     .row_field("Region")
+///     .column_field("Year")
+///     .data_field("Amount", Aggregation::Sum);
+/// work_book.add_pivot_table("Summary", "A3", pivot)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct PivotTable {
+    name: String,
+    source: String,
+    rows: Vec<String>,
+    columns: Vec<String>,
+    pages: Vec<String>,
+    data: Vec<DataField>,
+}
+
+impl PivotTable {
+    pub fn new(name: &str, source: &str) -> Self {
+        PivotTable {
+            name: name.to_string(),
+            source: source.trim_start_matches('=').to_string(),
+            rows: vec![],
+            columns: vec![],
+            pages: vec![],
+            data: vec![],
+        }
+    }
+
+    pub fn row_field(mut self, field: &str) -> Self {
+        self.rows.push(field.to_string());
+        self
+    }
+
+    pub fn column_field(mut self, field: &str) -> Self {
+        self.columns.push(field.to_string());
+        self
+    }
+
+    /// a filter above the table
+    pub fn page_field(mut self, field: &str) -> Self {
+        self.pages.push(field.to_string());
+        self
+    }
+
+    pub fn data_field(mut self, field: &str, aggregation: Aggregation) -> Self {
+        self.data.push(DataField {
+            field: field.to_string(),
+            aggregation,
+        });
+        self
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// the source range or defined name
+    pub fn get_source(&self) -> &str {
+        &self.source
+    }
+
+    /// number of rows the page fields take above the table, with the empty row after them.
+    pub fn page_rows(&self) -> usize {
+        if self.pages.is_empty() {
+            0
+        } else {
+            self.pages.len() + 1
+        }
+    }
+
+    // index of every axis field in the header, checks every field is known and used once.
+    fn field_indexes(&self, header: &[String]) -> Result<Vec<usize>, &'static str> {
+        if self.data.is_empty() {
+            return Err("Pivot table has no data field");
+        }
+        let mut used = HashSet::new();
+        let mut indexes = vec![];
+        for field in self.rows.iter().chain(&self.columns).chain(&self.pages) {
+            let index = field_index(header, field)?;
+            if !used.insert(index) {
+                return Err("Pivot field is used more than once");
+            }
+            indexes.push(index);
+        }
+        for data_field in self.data.iter() {
+            field_index(header, &data_field.field)?;
+        }
+        Ok(indexes)
+    }
+}
+
+fn field_index(header: &[String], field: &str) -> Result<usize, &'static str> {
+    header
+        .iter()
+        .position(|h| h == field)
+        .ok_or("Pivot field not found in the source header")
+}
+
+/// where the cache reads its data from
+#[derive(Debug, Clone)]
+pub enum CacheSource {
+    // (sheet name, range without the sheet)
+    Range(String, String),
+    Name(String),
+}
+
+// a column of the source range
+struct CacheField {
+    name: String,
+    // shared items are listed for text and axis fields, records point to them
+    items: Option<Vec<CacheValue>>,
+    // the index of every item
+    item_indexes: ItemIndexes,
+    has_text: bool,
+    has_number: bool,
+    has_blank: bool,
+    all_integer: bool,
+    min: f64,
+    max: f64,
+}
+
+// the index of an item by its value
+#[derive(Debug, Default)]
+struct ItemIndexes {
+    texts: HashMap<String, usize>,
+    numbers: HashMap<u64, usize>,
+    blank: Option<usize>,
+}
+
+impl ItemIndexes {
+    fn get(&self, value: &CacheValue) -> Option<usize> {
+        match value.key() {
+            CacheKey::Text(text) => self.texts.get(text).copied(),
+            CacheKey::Number(bits) => self.numbers.get(&bits).copied(),
+            CacheKey::Blank => self.blank,
+        }
+    }
+
+    fn insert(&mut self, value: &CacheValue, index: usize) {
+        match value.key() {
+            CacheKey::Text(text) => self.texts.insert(text.to_string(), index),
+            CacheKey::Number(bits) => self.numbers.insert(bits, index),
+            CacheKey::Blank => self.blank.replace(index),
+        };
+    }
+}
+
+impl CacheField {
+    fn new(name: &str, values: &[&CacheValue], listed: bool) -> Self {
+        let mut field = CacheField {
+            name: name.to_string(),
+            items: None,
+            item_indexes: ItemIndexes::default(),
+            has_text: false,
+            has_number: false,
+            has_blank: false,
+            all_integer: true,
+            min: f64::MAX,
+            max: f64::MIN,
+        };
+        for value in values {
+            match value {
+                CacheValue::Text(_) => field.has_text = true,
+                CacheValue::Number(n) => {
+                    field.has_number = true;
+                    field.all_integer &= n.fract() == 0.0;
+                    field.min = field.min.min(*n);
+                    field.max = field.max.max(*n);
+                }
+                CacheValue::Blank => field.has_blank = true,
+            }
+        }
+        // a number field that is not on an axis keeps its values in the records
+        if listed || field.has_text || !field.has_number {
+            let mut items: Vec<CacheValue> = vec![];
+            for value in values {
+                if field.item_indexes.get(value).is_none() {
+                    field.item_indexes.insert(value, items.len());
+                    items.push((*value).clone());
+                }
+            }
+            field.items = Some(items);
+        }
+        field
+    }
+
+    fn item_index(&self, value: &CacheValue) -> Option<usize> {
+        self.items.as_ref()?;
+        self.item_indexes.get(value)
+    }
+
+    fn to_xml(&self, writer: &mut XmlWriter) {
+        writer.start_element("cacheField");
+        writer.write_attribute("name", &escape_xml(&self.name));
+        writer.write_attribute("numFmtId", "0");
+        writer.start_element("sharedItems");
+        if self.has_number {
+            if self.has_text {
+                writer.write_attribute("containsMixedTypes", "1");
+            } else {
+                // semi mixed is text or blank
+                if !self.has_blank {
+                    writer.write_attribute("containsSemiMixedTypes", "0");
+                }
+                writer.write_attribute("containsString", "0");
+            }
+            writer.write_attribute("containsNumber", "1");
+            if self.all_integer {
+                writer.write_attribute("containsInteger", "1");
+            }
+            writer.write_attribute("minValue", &self.min.to_string());
+            writer.write_attribute("maxValue", &self.max.to_string());
+        } else if !self.has_text {
+            // blanks only
+            writer.write_attribute("containsNonDate", "0");
+            writer.write_attribute("containsString", "0");
+        }
+        if self.has_blank {
+            writer.write_attribute("containsBlank", "1");
+        }
+        if let Some(items) = &self.items {
+            writer.write_attribute("count", &items.len().to_string());
+            for item in items {
+                write_value(writer, item);
+            }
+        }
+        writer.end_element();
+        writer.end_element();
+    }
+}
+
+fn write_value(writer: &mut XmlWriter, value: &CacheValue) {
+    match value {
+        CacheValue::Text(text) => {
+            writer.start_element("s");
+            writer.write_attribute("v", &escape_xml(text));
+        }
+        CacheValue::Number(number) => {
+            writer.start_element("n");
+            writer.write_attribute("v", &number.to_string());
+        }
+        CacheValue::Blank => writer.start_element("m"),
+    }
+    writer.end_element();
+}
+
+fn writer_options() -> Options {
+    Options {
+        indent: Indent::None,
+        ..Default::default()
+    }
+}
+
+/// the xml of a pivot table and its cache, built when the table is added to a work book.
+#[derive(Debug)]
+pub struct PivotParts {
+    name: String,
+    // the id the work book lists the cache under, above the ones of a read work book
+    cache_id: usize,
+    table: String,
+    cache_definition: String,
+    cache_records: String,
+}
+
+impl PivotParts {
+    /// build the parts of `pivot_table` with the cache `cache_id`, placed at the (1 based)
+    /// `row` and `column`. `values` are the rows of the source range, header first.
+    pub fn new(
+        pivot_table: &PivotTable,
+        cache_id: usize,
+        row: usize,
+        column: usize,
+        source: &CacheSource,
+        values: &[Vec<CacheValue>],
+    ) -> Result<Self, &'static str> {
+        let (header, records) = values.split_first().ok_or("Pivot source is empty")?;
+        if records.is_empty() {
+            return Err("Pivot source has no data rows");
+        }
+        let header = header
+            .iter()
+            .map(|value| match value {
+                CacheValue::Text(text) if !text.is_empty() => Ok(text.clone()),
+                CacheValue::Number(number) => Ok(number.to_string()),
+                _ => Err("Pivot source header has an empty cell"),
+            })
+            .collect::<Result<Vec<String>, &'static str>>()?;
+        let axis_fields = pivot_table.field_indexes(&header)?;
+
+        let fields: Vec<CacheField> = header
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let column: Vec<&CacheValue> = records
+                    .iter()
+                    .map(|record| record.get(i).unwrap_or(&CacheValue::Blank))
+                    .collect();
+                CacheField::new(name, &column, axis_fields.contains(&i))
+            })
+            .collect();
+
+        Ok(PivotParts {
+            name: pivot_table.name.clone(),
+            cache_id,
+            table: table_xml(
+                pivot_table,
+                cache_id,
+                row,
+                column,
+                &header,
+                &fields,
+                records,
+            )?,
+            cache_definition: cache_definition_xml(source, &fields, records.len()),
+            cache_records: cache_records_xml(&fields, records),
+        })
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_cache_id(&self) -> usize {
+        self.cache_id
+    }
+
+    /// the pivot table, its cache and their relationships, as (path in the package, content).
+    pub fn parts(&self, number: usize) -> Vec<(String, Vec<u8>)> {
+        let mut table_rels = RelationShip::new();
+        table_rels.add_relation(
+            RS_PIVOT_CACHE_DEFINITION,
+            &format!("../pivotCache/pivotCacheDefinition{}.xml", number),
+            false,
+        );
+        let mut cache_rels = RelationShip::new();
+        cache_rels.add_relation(
+            RS_PIVOT_CACHE_RECORDS,
+            &format!("pivotCacheRecords{}.xml", number),
+            false,
+        );
+        vec![
+            (
+                format!("xl/pivotTables/pivotTable{}.xml", number),
+                self.table.clone().into_bytes(),
+            ),
+            (
+                format!("xl/pivotTables/_rels/pivotTable{}.xml.rels", number),
+                table_rels.to_xml().into_bytes(),
+            ),
+            (
+                format!("xl/pivotCache/pivotCacheDefinition{}.xml", number),
+                self.cache_definition.clone().into_bytes(),
+            ),
+            (
+                format!(
+                    "xl/pivotCache/_rels/pivotCacheDefinition{}.xml.rels",
+                    number
+                ),
+                cache_rels.to_xml().into_bytes(),
+            ),
+            (
+                format!("xl/pivotCache/pivotCacheRecords{}.xml", number),
+                self.cache_records.clone().into_bytes(),
+            ),
+        ]
+    }
+}
+
+fn cache_definition_xml(
+    source: &CacheSource,
+    fields: &[CacheField],
+    record_count: usize,
+) -> String {
+    let mut writer = XmlWriter::new(writer_options());
+    writer.write_declaration();
+    writer.start_element("pivotCacheDefinition");
+    writer.write_attribute("xmlns", MAIN_XMLNS);
+    writer.write_attribute("xmlns:r", R_XMLNS);
+    writer.write_attribute("r:id", "rId1");
+    writer.write_attribute("refreshOnLoad", "1");
+    writer.write_attribute("createdVersion", "6");
+    writer.write_attribute("refreshedVersion", "6");
+    writer.write_attribute("minRefreshableVersion", "3");
+    writer.write_attribute("recordCount", &record_count.to_string());
+
+    writer.start_element("cacheSource");
+    writer.write_attribute("type", "worksheet");
+    writer.start_element("worksheetSource");
+    match source {
+        CacheSource::Range(sheet, range) => {
+            writer.write_attribute("ref", range);
+            writer.write_attribute("sheet", &escape_xml(sheet));
+        }
+        CacheSource::Name(name) => writer.write_attribute("name", &escape_xml(name)),
+    }
+    writer.end_element();
+    writer.end_element();
+
+    writer.start_element("cacheFields");
+    writer.write_attribute("count", &fields.len().to_string());
+    for field in fields {
+        field.to_xml(&mut writer);
+    }
+    writer.end_element();
+
+    writer.end_document()
+}
+
+fn cache_records_xml(fields: &[CacheField], records: &[Vec<CacheValue>]) -> String {
+    let mut writer = XmlWriter::new(writer_options());
+    writer.write_declaration();
+    writer.start_element("pivotCacheRecords");
+    writer.write_attribute("xmlns", MAIN_XMLNS);
+    writer.write_attribute("xmlns:r", R_XMLNS);
+    writer.write_attribute("count", &records.len().to_string());
+    for record in records {
+        writer.start_element("r");
+        for (i, field) in fields.iter().enumerate() {
+            let value = record.get(i).unwrap_or(&CacheValue::Blank);
+            if let Some(index) = field.item_index(value) {
+                writer.start_element("x");
+                writer.write_attribute("v", &index.to_string());
+                writer.end_element();
+            } else {
+                write_value(&mut writer, value);
+            }
+        }
+        writer.end_element();
+    }
+    writer.end_document()
+}
+
+// number of distinct values of the first 1, 2, .. n of the `indexes` fields, together.
+fn distinct_prefixes(records: &[Vec<CacheValue>], indexes: &[usize]) -> Vec<usize> {
+    (1..=indexes.len())
+        .map(|n| {
+            let mut seen: HashSet<Vec<CacheKey>> = HashSet::new();
+            for record in records {
+                let key: Vec<CacheKey> = indexes[..n]
+                    .iter()
+                    .map(|i| record.get(*i).unwrap_or(&CacheValue::Blank).key())
+                    .collect();
+                seen.insert(key);
+            }
+            seen.len()
+        })
+        .collect()
+}
+
+// (header rows, first header row) of the table, the column labels take a row for every
+// column field and the values field, and one for their items.
+fn header_rows(columns: &[usize], data_count: usize) -> (usize, usize) {
+    match (columns.is_empty(), data_count > 1) {
+        (true, false) => (1, 1),
+        // the data captions are the header
+        (true, true) => (1, 0),
+        (false, false) => (2, 1),
+        (false, true) => (3, 1),
+    }
+}
+
+// the range the laid out table takes, the same as excel's compact layout.
+fn location_size(
+    pivot_table: &PivotTable,
+    rows: &[usize],
+    columns: &[usize],
+    records: &[Vec<CacheValue>],
+) -> (usize, usize) {
+    let data_count = pivot_table.data.len();
+    // header rows, the column labels take one more
+    let header_rows = header_rows(columns, data_count).0;
+    // every level of row items, and the grand total
+    let body_rows = distinct_prefixes(records, rows).iter().sum::<usize>() + 1;
+    let label_columns = if rows.is_empty() { 0 } else { 1 };
+    // every level of column items with their totals, for every data field
+    let body_columns = if columns.is_empty() {
+        data_count
+    } else {
+        (distinct_prefixes(records, columns).iter().sum::<usize>() + 1) * data_count
+    };
+    let height = if rows.is_empty() {
+        header_rows + 1
+    } else {
+        header_rows + body_rows
+    };
+    (height, label_columns + body_columns)
+}
+
+fn table_xml(
+    pivot_table: &PivotTable,
+    cache_id: usize,
+    row: usize,
+    column: usize,
+    header: &[String],
+    fields: &[CacheField],
+    records: &[Vec<CacheValue>],
+) -> Result<String, &'static str> {
+    let indexes = |names: &[String]| -> Result<Vec<usize>, &'static str> {
+        names.iter().map(|name| field_index(header, name)).collect()
+    };
+    let rows = indexes(&pivot_table.rows)?;
+    let columns = indexes(&pivot_table.columns)?;
+    let pages = indexes(&pivot_table.pages)?;
+    let data_indexes: Vec<usize> = pivot_table
+        .data
+        .iter()
+        .map(|data_field| field_index(header, &data_field.field))
+        .collect::<Result<_, _>>()?;
+
+    let mut writer = XmlWriter::new(writer_options());
+    writer.write_declaration();
+    writer.start_element("pivotTableDefinition");
+    writer.write_attribute("xmlns", MAIN_XMLNS);
+    writer.write_attribute("name", &escape_xml(&pivot_table.name));
+    writer.write_attribute("cacheId", &cache_id.to_string());
+    writer.write_attribute("applyNumberFormats", "0");
+    writer.write_attribute("applyBorderFormats", "0");
+    writer.write_attribute("applyFontFormats", "0");
+    writer.write_attribute("applyPatternFormats", "0");
+    writer.write_attribute("applyAlignmentFormats", "0");
+    writer.write_attribute("applyWidthHeightFormats", "1");
+    writer.write_attribute("dataCaption", "Values");
+    writer.write_attribute("updatedVersion", "6");
+    writer.write_attribute("minRefreshableVersion", "3");
+    writer.write_attribute("createdVersion", "6");
+    writer.write_attribute("useAutoFormatting", "1");
+    writer.write_attribute("itemPrintTitles", "1");
+    writer.write_attribute("indent", "0");
+    writer.write_attribute("outline", "1");
+    writer.write_attribute("outlineData", "1");
+
+    // the table starts below the page fields
+    let first_row = row + pivot_table.page_rows();
+    let (height, width) = location_size(pivot_table, &rows, &columns, records);
+    let location = format!(
+        "{}{}:{}{}",
+        column_number_to_name(column),
+        first_row,
+        column_number_to_name(column + width - 1),
+        first_row + height - 1
+    );
+    let has_values_field = pivot_table.data.len() > 1;
+    writer.start_element("location");
+    writer.write_attribute("ref", &location);
+    let (first_data_row, first_header_row) = header_rows(&columns, pivot_table.data.len());
+    writer.write_attribute("firstHeaderRow", &first_header_row.to_string());
+    writer.write_attribute("firstDataRow", &first_data_row.to_string());
+    writer.write_attribute("firstDataCol", if rows.is_empty() { "0" } else { "1" });
+    if !pages.is_empty() {
+        writer.write_attribute("rowPageCount", &pages.len().to_string());
+        writer.write_attribute("colPageCount", "1");
+    }
+    writer.end_element();
+
+    writer.start_element("pivotFields");
+    writer.write_attribute("count", &fields.len().to_string());
+    for (i, field) in fields.iter().enumerate() {
+        writer.start_element("pivotField");
+        let axis = if rows.contains(&i) {
+            Some("axisRow")
+        } else if columns.contains(&i) {
+            Some("axisCol")
+        } else if pages.contains(&i) {
+            Some("axisPage")
+        } else {
+            None
+        };
+        if let Some(axis) = axis {
+            writer.write_attribute("axis", axis);
+        }
+        if data_indexes.contains(&i) {
+            writer.write_attribute("dataField", "1");
+        }
+        writer.write_attribute("showAll", "0");
+        if let (Some(_), Some(items)) = (axis, &field.items) {
+            writer.start_element("items");
+            writer.write_attribute("count", &(items.len() + 1).to_string());
+            for x in 0..items.len() {
+                writer.start_element("item");
+                writer.write_attribute("x", &x.to_string());
+                writer.end_element();
+            }
+            writer.start_element("item");
+            writer.write_attribute("t", "default");
+            writer.end_element();
+            writer.end_element();
+        }
+        writer.end_element();
+    }
+    writer.end_element();
+
+    let mut column_fields: Vec<String> = columns.iter().map(|i| i.to_string()).collect();
+    if has_values_field {
+        column_fields.push(VALUES_FIELD.to_string());
+    }
+    let row_fields: Vec<String> = rows.iter().map(|i| i.to_string()).collect();
+    for (name, fields) in [("rowFields", row_fields), ("colFields", column_fields)] {
+        if fields.is_empty() {
+            continue;
+        }
+        writer.start_element(name);
+        writer.write_attribute("count", &fields.len().to_string());
+        for x in fields {
+            writer.start_element("field");
+            writer.write_attribute("x", &x);
+            writer.end_element();
+        }
+        writer.end_element();
+    }
+
+    if !pages.is_empty() {
+        writer.start_element("pageFields");
+        writer.write_attribute("count", &pages.len().to_string());
+        for i in pages {
+            writer.start_element("pageField");
+            writer.write_attribute("fld", &i.to_string());
+            writer.write_attribute("hier", "-1");
+            writer.end_element();
+        }
+        writer.end_element();
+    }
+
+    writer.start_element("dataFields");
+    writer.write_attribute("count", &pivot_table.data.len().to_string());
+    for (data_field, i) in pivot_table.data.iter().zip(data_indexes) {
+        writer.start_element("dataField");
+        let name = format!(
+            "{} of {}",
+            data_field.aggregation.caption(),
+            data_field.field
+        );
+        writer.write_attribute("name", &escape_xml(&name));
+        writer.write_attribute("fld", &i.to_string());
+        if let Some(subtotal) = data_field.aggregation.subtotal() {
+            writer.write_attribute("subtotal", subtotal);
+        }
+        writer.write_attribute("baseField", "0");
+        writer.write_attribute("baseItem", "0");
+        writer.end_element();
+    }
+    writer.end_element();
+
+    writer.start_element("pivotTableStyleInfo");
+    writer.write_attribute("name", "PivotStyleLight16");
+    writer.write_attribute("showRowHeaders", "1");
+    writer.write_attribute("showColHeaders", "1");
+    writer.write_attribute("showRowStripes", "0");
+    writer.write_attribute("showColStripes", "0");
+    writer.write_attribute("showLastColumn", "1");
+    writer.end_element();
+
+    Ok(writer.end_document())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_items_are_distinct() {
+        let values = [
+            CacheValue::Text("b".to_string()),
+            CacheValue::Text("a".to_string()),
+            CacheValue::Text("b".to_string()),
+            CacheValue::Number(0.0),
+            CacheValue::Number(-0.0),
+            CacheValue::Blank,
+        ];
+        let values: Vec<&CacheValue> = values.iter().collect();
+        let field = CacheField::new("Name", &values, false);
+        assert_eq!(field.items.as_ref().unwrap().len(), 4);
+        assert_eq!(field.item_index(values[2]), Some(0));
+        assert_eq!(field.item_index(values[4]), Some(2));
+        assert_eq!(field.item_index(values[5]), Some(3));
+    }
+
+    #[test]
+    fn numbers_off_the_axes_have_no_items() {
+        let values = [CacheValue::Number(1.0), CacheValue::Number(2.5)];
+        let values: Vec<&CacheValue> = values.iter().collect();
+        let field = CacheField::new("Sales", &values, false);
+        assert!(field.items.is_none());
+        assert_eq!(field.item_index(values[0]), None);
+        assert!(CacheField::new("Sales", &values, true).items.is_some());
+    }
+
+    #[test]
+    fn distinct_prefixes_count_combinations() {
+        let record = |a: &str, b: f64| vec![CacheValue::Text(a.to_string()), CacheValue::Number(b)];
+        let records = vec![
+            record("x", 1.0),
+            record("x", 2.0),
+            record("y", 1.0),
+            record("x", 1.0),
+        ];
+        assert_eq!(distinct_prefixes(&records, &[0, 1]), vec![2, 3]);
+        assert_eq!(distinct_prefixes(&records, &[1]), vec![2]);
+    }
+}
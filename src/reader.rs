@@ -0,0 +1,1487 @@
+// reading a work book back: the parts of a package in to sheets, rows and cells.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use zip::ZipArchive;
+
+use crate::{
+    cell::{Cell, CellStyle, CellValue, FormulaKind},
+    defined_name::DefinedName,
+    escape::unescape_text,
+    hyperlink::{Hyperlink, HyperlinkTarget},
+    package::{
+        kept_part_name, kept_relation_id, PreservedElement, PreservedElements, PreservedNode,
+        PreservedParts, PreservedRelation, PreservedSheet,
+    },
+    rich_text::{self, Run},
+    row::{column_number_to_name, parse_cell_ref, Row, RowFormat},
+    row_view::RowView,
+    work_sheet::WorkSheet,
+    xml_templates::style::{built_in_number_format, FontStyle, ReadStyles, UnderLine},
+};
+
+static INVALID_XML: &str = "Invalid XML in the work book";
+
+/// what a package holds in the types this crate writes, and what it does not kept as it was.
+pub struct ReadWorkBook {
+    pub sheets: Vec<ReadSheet>,
+    // scoped names point in to `sheets`, names of dropped tabs are dropped.
+    pub defined_names: Vec<DefinedName>,
+    // a macro enabled work book keeps its content type
+    pub work_book_content_type: String,
+    pub root_relations: Vec<PreservedRelation>,
+    pub work_book_relations: Vec<PreservedRelation>,
+    pub work_book_elements: PreservedElements,
+    pub styles: ReadStyles,
+    pub parts: PreservedParts,
+}
+
+/// a tab of a read work book
+pub enum ReadSheet {
+    WorkSheet(Box<WorkSheet>),
+    Preserved(PreservedSheet),
+}
+
+/// strings and styles the cells of every sheet refer to by index.
+pub struct ReadContext {
+    // a plain string is one run without a font
+    strings: Vec<Vec<Run>>,
+    // by cell xf index
+    styles: Vec<CellStyle>,
+}
+
+// content types of the parts, by part name and by extension.
+struct ContentTypes {
+    overrides: HashMap<String, String>,
+    defaults: Vec<(String, String)>,
+}
+
+// relationships kept from a part this crate writes, with their new ids by the read ones.
+type KeptRelations = (Vec<PreservedRelation>, HashMap<String, String>);
+
+struct Package<R: Read + Seek> {
+    archive: ZipArchive<R>,
+}
+
+impl<R: Read + Seek> Package<R> {
+    fn new(reader: R) -> Result<Self, &'static str> {
+        let archive = ZipArchive::new(reader).map_err(|_| "Not a valid xlsx file")?;
+        Ok(Package { archive })
+    }
+
+    fn part(&mut self, name: &str) -> Result<String, &'static str> {
+        self.optional_part(name)?
+            .ok_or("Missing part in the work book")
+    }
+
+    fn optional_part(&mut self, name: &str) -> Result<Option<String>, &'static str> {
+        match self.optional_bytes(name)? {
+            Some(data) => String::from_utf8(data)
+                .map(Some)
+                .map_err(|_| "Could not read a part of the work book"),
+            None => Ok(None),
+        }
+    }
+
+    fn optional_bytes(&mut self, name: &str) -> Result<Option<Vec<u8>>, &'static str> {
+        let mut file = match self.archive.by_name(name) {
+            Ok(file) => file,
+            Err(_) => return Ok(None),
+        };
+        let mut content = vec![];
+        file.read_to_end(&mut content)
+            .map_err(|_| "Could not read a part of the work book")?;
+        Ok(Some(content))
+    }
+
+    // relationships of a part, from `dir/_rels/name.rels`
+    fn relations(&mut self, part_name: &str) -> Result<Vec<PreservedRelation>, &'static str> {
+        let (dir, name) = part_name.rsplit_once('/').unwrap_or(("", part_name));
+        let rels_name = match dir {
+            "" => format!("_rels/{}.rels", name),
+            _ => format!("{}/_rels/{}.rels", dir, name),
+        };
+        match self.optional_part(&rels_name)? {
+            Some(xml) => read_relations(&xml, dir),
+            None => Ok(vec![]),
+        }
+    }
+
+    // the shared strings and the styles, from the relationships of the work book.
+    fn read_context(
+        &mut self,
+        relations: &[PreservedRelation],
+    ) -> Result<(ReadContext, ReadStyles), &'static str> {
+        let strings = match relations.iter().find(|r| r.is("sharedStrings")) {
+            Some(relation) => read_shared_strings(&self.part(&relation.target)?)?,
+            None => vec![],
+        };
+        let (styles, style_sheet) = match relations.iter().find(|r| r.is("styles")) {
+            Some(relation) => read_styles(&self.part(&relation.target)?)?,
+            None => (vec![], ReadStyles::default()),
+        };
+        Ok((ReadContext { strings, styles }, style_sheet))
+    }
+
+    // the content of a part as it is read from the package, not held in memory. a thread
+    // holds the package and inflates the part a chunk ahead of the reader.
+    fn into_part_reader(mut self, name: &str) -> Result<PartReader, &'static str>
+    where
+        R: Send + 'static,
+    {
+        self.archive
+            .by_name(name)
+            .map_err(|_| "Could not read a part of the work book")?;
+        let name = name.to_string();
+        let (sender, chunks) = mpsc::sync_channel(PART_CHUNKS_AHEAD);
+        thread::spawn(move || {
+            let Ok(mut file) = self.archive.by_name(&name) else {
+                return;
+            };
+            loop {
+                let mut chunk = vec![0; PART_CHUNK_SIZE];
+                let chunk = match file.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(size) => {
+                        chunk.truncate(size);
+                        Ok(chunk)
+                    }
+                    Err(error) => Err(error),
+                };
+                // a bad checksum ends the part, a dropped reader the thread
+                let failed = chunk.is_err();
+                if sender.send(chunk).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Ok(PartReader {
+            chunks,
+            chunk: vec![],
+            position: 0,
+        })
+    }
+
+    // keep a part this crate does not model and every part it points to, once.
+    fn keep_part(
+        &mut self,
+        name: &str,
+        content_types: &ContentTypes,
+        kept: &mut PreservedParts,
+    ) -> Result<(), &'static str> {
+        let kept_name = kept_part_name(name);
+        if kept.contains(&kept_name) {
+            return Ok(());
+        }
+        let Some(data) = self.optional_bytes(name)? else {
+            return Ok(());
+        };
+        let relations = self.relations(name)?;
+        let kept_relations = relations
+            .iter()
+            .map(|relation| PreservedRelation {
+                target: kept_target(relation),
+                ..relation.clone()
+            })
+            .collect();
+        let content_type = content_types.overrides.get(name).cloned();
+        kept.add(&kept_name, content_type, data, kept_relations);
+        for relation in relations.iter().filter(|r| !r.external) {
+            self.keep_part(&relation.target, content_types, kept)?;
+        }
+        Ok(())
+    }
+
+    // keep the relationships of a part this crate writes to what it does not model,
+    // and their targets.
+    fn keep_relations(
+        &mut self,
+        relations: Vec<PreservedRelation>,
+        content_types: &ContentTypes,
+        kept: &mut PreservedParts,
+    ) -> Result<KeptRelations, &'static str> {
+        let mut ids = HashMap::new();
+        let mut kept_relations = vec![];
+        for relation in relations {
+            if !relation.external {
+                self.keep_part(&relation.target, content_types, kept)?;
+            }
+            let id = kept_relation_id(&relation.id);
+            ids.insert(relation.id.clone(), id.clone());
+            kept_relations.push(PreservedRelation {
+                id,
+                target: kept_target(&relation),
+                ..relation
+            });
+        }
+        Ok((kept_relations, ids))
+    }
+}
+
+static PART_CHUNK_SIZE: usize = 64 * 1024;
+static PART_CHUNKS_AHEAD: usize = 4;
+
+// the data of a part, inflated as it is read
+struct PartReader {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    // of the next byte in `chunk`
+    position: usize,
+}
+
+impl Read for PartReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.chunk.len() {
+            match self.chunks.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk?;
+                    self.position = 0;
+                }
+                // the whole part has been read
+                Err(_) => return Ok(0),
+            }
+        }
+        let size = buf.len().min(self.chunk.len() - self.position);
+        buf[..size].copy_from_slice(&self.chunk[self.position..self.position + size]);
+        self.position += size;
+        Ok(size)
+    }
+}
+
+fn kept_target(relation: &PreservedRelation) -> String {
+    match relation.external {
+        true => relation.target.clone(),
+        false => kept_part_name(&relation.target),
+    }
+}
+
+/// read the work sheets, strings and styles of a package. the other tabs and parts
+/// (charts, drawings, vba, custom xml, ..) are kept as they were to be written back.
+pub fn read_work_book<R: Read + Seek>(reader: R) -> Result<ReadWorkBook, &'static str> {
+    let mut package = Package::new(reader)?;
+    let content_types = read_content_types(&package.part("[Content_Types].xml")?)?;
+    let mut parts = PreservedParts::new();
+    for (extension, content_type) in content_types.defaults.iter() {
+        parts.add_default(extension, content_type);
+    }
+
+    let (work_book_part, root_relations): (Vec<_>, Vec<_>) = package
+        .relations("")?
+        .into_iter()
+        .partition(|r| r.is("officeDocument"));
+    let work_book_part = work_book_part
+        .into_iter()
+        .next()
+        .map(|r| r.target)
+        .ok_or("Missing work book part")?;
+    let work_book_content_type = content_types
+        .overrides
+        .get(&work_book_part)
+        .filter(|t| t.ends_with(".main+xml"))
+        .cloned()
+        .ok_or("Not a spreadsheet")?;
+    // document properties are written from the work book
+    let root_relations = root_relations
+        .into_iter()
+        .filter(|r| !r.is("core-properties") && !r.is("extended-properties"))
+        .collect();
+    let (root_relations, _) = package.keep_relations(root_relations, &content_types, &mut parts)?;
+
+    // the sheets, strings and styles are read and the calculation chain is dropped
+    // as the cells may change, the rest is kept.
+    let (modeled_relations, work_book_relations): (Vec<_>, Vec<_>) = package
+        .relations(&work_book_part)?
+        .into_iter()
+        .filter(|r| !r.is("calcChain"))
+        .partition(|r| r.is("worksheet") || r.is("sharedStrings") || r.is("styles"));
+    let (work_book_relations, ids) =
+        package.keep_relations(work_book_relations, &content_types, &mut parts)?;
+    let (sheets, defined_names, work_book_elements) =
+        read_work_book_xml(&package.part(&work_book_part)?, &ids)?;
+
+    let (context, styles) = package.read_context(&modeled_relations)?;
+
+    // position of every tab among the sheets read, None for a dropped one
+    let mut positions = vec![];
+    let mut read_sheets = vec![];
+    for (name, r_id, state) in sheets {
+        let relation = modeled_relations
+            .iter()
+            .find(|r| r.id == r_id && r.is("worksheet"));
+        let read_sheet = match (relation, ids.get(&r_id)) {
+            (Some(relation), _) => {
+                let xml = package.part(&relation.target)?;
+                let (hyperlinks, relations): (Vec<_>, Vec<_>) = package
+                    .relations(&relation.target)?
+                    .into_iter()
+                    .partition(|r| r.is("hyperlink"));
+                let (relations, sheet_ids) =
+                    package.keep_relations(relations, &content_types, &mut parts)?;
+                let (mut work_sheet, elements) =
+                    read_work_sheet(&xml, &name, &hyperlinks, &sheet_ids, &context)?;
+                work_sheet.preserve(elements, relations);
+                ReadSheet::WorkSheet(Box::new(work_sheet))
+            }
+            // a chart sheet, a dialog sheet, ..
+            (None, Some(kept_id)) => ReadSheet::Preserved(PreservedSheet {
+                name,
+                r_id: kept_id.clone(),
+                state,
+            }),
+            (None, None) => {
+                positions.push(None);
+                continue;
+            }
+        };
+        positions.push(Some(read_sheets.len()));
+        read_sheets.push(read_sheet);
+    }
+
+    let defined_names = defined_names
+        .into_iter()
+        .filter_map(|mut defined_name| {
+            if let Some(id) = defined_name.local_sheet_id {
+                defined_name.local_sheet_id = Some((*positions.get(id)?)?);
+            }
+            Some(defined_name)
+        })
+        .collect();
+
+    Ok(ReadWorkBook {
+        sheets: read_sheets,
+        defined_names,
+        work_book_content_type,
+        root_relations,
+        work_book_relations,
+        work_book_elements,
+        styles,
+        parts,
+    })
+}
+
+// attributes of an element by local name, `r:id` is `id`.
+fn attributes(element: &BytesStart) -> Result<HashMap<String, String>, &'static str> {
+    let mut attributes = HashMap::new();
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(|_| INVALID_XML)?;
+        let key = String::from_utf8_lossy(attribute.key.local_name().as_ref()).to_string();
+        let value = attribute.unescape_value().map_err(|_| INVALID_XML)?;
+        attributes.insert(key, value.to_string());
+    }
+    Ok(attributes)
+}
+
+fn local_name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.local_name().as_ref()).to_string()
+}
+
+// `/xl/workbook.xml` or `worksheets/sheet1.xml` from `xl`, as a path in the package.
+fn resolve_target(base_dir: &str, target: &str) -> String {
+    let joined = match target.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None if base_dir.is_empty() => target.to_string(),
+        None => format!("{}/{}", base_dir, target),
+    };
+    let mut segments: Vec<&str> = vec![];
+    for segment in joined.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+// content types by part name from the overrides, and by extension from the defaults.
+fn read_content_types(xml: &str) -> Result<ContentTypes, &'static str> {
+    let mut content_types = ContentTypes {
+        overrides: HashMap::new(),
+        defaults: vec![],
+    };
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event().map_err(|_| INVALID_XML)? {
+            Event::Start(e) | Event::Empty(e) if local_name(&e) == "Override" => {
+                let attributes = attributes(&e)?;
+                if let (Some(part), Some(content_type)) =
+                    (attributes.get("PartName"), attributes.get("ContentType"))
+                {
+                    content_types
+                        .overrides
+                        .insert(resolve_target("", part), content_type.clone());
+                }
+            }
+            Event::Start(e) | Event::Empty(e) if local_name(&e) == "Default" => {
+                let mut attributes = attributes(&e)?;
+                if let (Some(extension), Some(content_type)) = (
+                    attributes.remove("Extension"),
+                    attributes.remove("ContentType"),
+                ) {
+                    content_types
+                        .defaults
+                        .push((extension.to_lowercase(), content_type));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(content_types)
+}
+
+fn read_relations(xml: &str, base_dir: &str) -> Result<Vec<PreservedRelation>, &'static str> {
+    let mut relations = vec![];
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event().map_err(|_| INVALID_XML)? {
+            Event::Start(e) | Event::Empty(e) if local_name(&e) == "Relationship" => {
+                let mut attributes = attributes(&e)?;
+                let external = attributes.get("TargetMode").map(|m| m.as_str()) == Some("External");
+                let target = attributes.remove("Target").unwrap_or_default();
+                relations.push(PreservedRelation {
+                    id: attributes.remove("Id").unwrap_or_default(),
+                    rel_type: attributes.remove("Type").unwrap_or_default(),
+                    target: if external {
+                        target
+                    } else {
+                        resolve_target(base_dir, &target)
+                    },
+                    external,
+                });
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(relations)
+}
+
+// a tab as (name, relationship id, state)
+type Tab = (String, String, Option<String>);
+
+// the tabs, the defined names scoped by tab position and the elements kept.
+type WorkBookXml = (Vec<Tab>, Vec<DefinedName>, PreservedElements);
+
+fn read_work_book_xml(
+    xml: &str,
+    ids: &HashMap<String, String>,
+) -> Result<WorkBookXml, &'static str> {
+    let mut reader = Reader::from_str(xml);
+    let (root, _) = next_element(&mut reader)?.ok_or(INVALID_XML)?;
+    let root_attributes = root_attributes(&root)?;
+    let mut sheets = vec![];
+    let mut defined_names = vec![];
+    let mut elements = vec![];
+    while let Some((e, empty)) = next_element(&mut reader)? {
+        match local_name(&e).as_str() {
+            "sheets" if !empty => sheets = read_sheets(&mut reader)?,
+            "definedNames" if !empty => defined_names = read_defined_names(&mut reader)?,
+            "sheets" | "definedNames" => {}
+            _ => elements.push(read_preserved_element(&mut reader, &e, empty, ids)?),
+        }
+    }
+    let elements = PreservedElements::new(root_attributes, elements);
+    Ok((sheets, defined_names, elements))
+}
+
+fn read_sheets(reader: &mut Reader<&[u8]>) -> Result<Vec<Tab>, &'static str> {
+    let mut sheets = vec![];
+    loop {
+        match reader.read_event().map_err(|_| INVALID_XML)? {
+            Event::Start(e) | Event::Empty(e) if local_name(&e) == "sheet" => {
+                let mut attributes = attributes(&e)?;
+                if let (Some(name), Some(r_id)) =
+                    (attributes.remove("name"), attributes.remove("id"))
+                {
+                    sheets.push((name, r_id, attributes.remove("state")));
+                }
+            }
+            Event::End(e) if e.local_name().as_ref() == b"sheets" => break,
+            Event::Eof => return Err(INVALID_XML),
+            _ => {}
+        }
+    }
+    Ok(sheets)
+}
+
+fn read_defined_names(reader: &mut Reader<&[u8]>) -> Result<Vec<DefinedName>, &'static str> {
+    let mut defined_names = vec![];
+    // the defined name being read
+    let mut defined_name: Option<DefinedName> = None;
+    loop {
+        match reader.read_event().map_err(|_| INVALID_XML)? {
+            Event::Start(e) if local_name(&e) == "definedName" => {
+                let attributes = attributes(&e)?;
+                defined_name = Some(DefinedName {
+                    name: attributes.get("name").cloned().unwrap_or_default(),
+                    formula: String::new(),
+                    local_sheet_id: attributes
+                        .get("localSheetId")
+                        .and_then(|id| id.parse().ok()),
+                    hidden: matches!(
+                        attributes.get("hidden").map(|h| h.as_str()),
+                        Some("1" | "true")
+                    ),
+                });
+            }
+            Event::Text(e) => {
+                if let Some(defined_name) = defined_name.as_mut() {
+                    defined_name
+                        .formula
+                        .push_str(&e.unescape().map_err(|_| INVALID_XML)?);
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"definedName" => defined_names.extend(defined_name.take()),
+                b"definedNames" => break,
+                _ => {}
+            },
+            Event::Eof => return Err(INVALID_XML),
+            _ => {}
+        }
+    }
+    Ok(defined_names)
+}
+
+// the next start tag and whether it is empty, None at the end of the part. called
+// right after a child of the root was read, it gives the next child.
+fn next_element<'a>(
+    reader: &mut Reader<&'a [u8]>,
+) -> Result<Option<(BytesStart<'a>, bool)>, &'static str> {
+    loop {
+        match reader.read_event().map_err(|_| INVALID_XML)? {
+            Event::Start(e) => return Ok(Some((e, false))),
+            Event::Empty(e) => return Ok(Some((e, true))),
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+// the namespaces of the root element and which of them are ignorable,
+// the kept elements may use them.
+fn root_attributes(root: &BytesStart) -> Result<Vec<(String, String)>, &'static str> {
+    let mut root_attributes = vec![];
+    for attribute in root.attributes() {
+        let attribute = attribute.map_err(|_| INVALID_XML)?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+        if key.starts_with("xmlns:") || attribute.key.local_name().as_ref() == b"Ignorable" {
+            let value = attribute.unescape_value().map_err(|_| INVALID_XML)?;
+            root_attributes.push((key, value.to_string()));
+        }
+    }
+    Ok(root_attributes)
+}
+
+/// an element and everything in it as it was, the reader is right after its start tag.
+/// relationship ids are changed to the ids of the kept relationships.
+fn read_preserved_element(
+    reader: &mut Reader<&[u8]>,
+    start: &BytesStart,
+    empty: bool,
+    ids: &HashMap<String, String>,
+) -> Result<PreservedElement, &'static str> {
+    let mut attributes = vec![];
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(|_| INVALID_XML)?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+        let mut value = attribute
+            .unescape_value()
+            .map_err(|_| INVALID_XML)?
+            .to_string();
+        // `r:id="rId2"`, never an attribute of this namespace or a declaration
+        if key.contains(':') && !key.starts_with("xmlns") {
+            if let Some(id) = ids.get(&value) {
+                value = id.clone();
+            }
+        }
+        attributes.push((key, value));
+    }
+    let mut element = PreservedElement {
+        name: String::from_utf8_lossy(start.name().as_ref()).to_string(),
+        attributes,
+        children: vec![],
+    };
+    if empty {
+        return Ok(element);
+    }
+    loop {
+        let child = match reader.read_event().map_err(|_| INVALID_XML)? {
+            Event::Start(e) => {
+                PreservedNode::Element(read_preserved_element(reader, &e, false, ids)?)
+            }
+            Event::Empty(e) => {
+                PreservedNode::Element(read_preserved_element(reader, &e, true, ids)?)
+            }
+            Event::Text(e) => PreservedNode::Text(e.unescape().map_err(|_| INVALID_XML)?.into()),
+            Event::CData(e) => PreservedNode::Text(String::from_utf8_lossy(&e).to_string()),
+            Event::End(_) => break,
+            Event::Eof => return Err(INVALID_XML),
+            _ => continue,
+        };
+        element.children.push(child);
+    }
+    // the indentation between child elements
+    if element.elements().next().is_some() {
+        element
+            .children
+            .retain(|child| !matches!(child, PreservedNode::Text(text) if text.trim().is_empty()));
+    }
+    Ok(element)
+}
+
+// a font property of `<font>` in the style sheet or `<rPr>` of a run.
+fn read_font_property(
+    font: FontStyle,
+    name: &str,
+    attributes: &HashMap<String, String>,
+) -> FontStyle {
+    let value = attributes.get("val").map(|v| v.as_str());
+    // `<b/>` is on, `<b val="0"/>` is off
+    let on = !matches!(value, Some("0" | "false"));
+    match name {
+        "b" => font.bold(on),
+        "i" => font.italic(on),
+        "strike" => font.strike(on),
+        "u" => font.underline(match value {
+            None | Some("single") => Some(UnderLine::Single),
+            Some("double") => Some(UnderLine::Double),
+            Some("singleAccounting") => Some(UnderLine::SingleAcccounting),
+            Some("doubleAccounting") => Some(UnderLine::DoubleAccouting),
+            Some(_) => None,
+        }),
+        "sz" => match value.and_then(|v| v.parse::<f64>().ok()) {
+            Some(size) => font.size(size.round().clamp(1.0, 255.0) as u8),
+            None => font,
+        },
+        "color" => match attributes.get("rgb") {
+            Some(rgb) => font.color(rgb),
+            None => font,
+        },
+        "name" | "rFont" => match value {
+            Some(font_name) => font.name(font_name),
+            None => font,
+        },
+        _ => font,
+    }
+}
+
+// the text of every `<si>`, as runs
+fn read_shared_strings(xml: &str) -> Result<Vec<Vec<Run>>, &'static str> {
+    let mut strings = vec![];
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event().map_err(|_| INVALID_XML)? {
+            Event::Start(e) if local_name(&e) == "si" => {
+                strings.push(read_runs(&mut reader, "si")?);
+            }
+            Event::Empty(e) if local_name(&e) == "si" => strings.push(vec![]),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(strings)
+}
+
+/// the runs of a string item (`<si>` or `<is>`) up to its end tag,
+/// a plain `<t>` is one run without a font. phonetic hints are left out.
+fn read_runs<B: BufRead>(reader: &mut Reader<B>, end: &str) -> Result<Vec<Run>, &'static str> {
+    let mut runs: Vec<Run> = vec![];
+    let mut in_text = false;
+    let mut in_phonetic = false;
+    let mut font: Option<FontStyle> = None;
+    let mut buf = vec![];
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf).map_err(|_| INVALID_XML)? {
+            Event::Start(e) => match local_name(&e).as_str() {
+                "t" if !in_phonetic => {
+                    in_text = true;
+                    runs.push(Run {
+                        text: String::new(),
+                        font: font.take(),
+                    });
+                }
+                "rPh" => in_phonetic = true,
+                "rPr" => font = Some(FontStyle::new()),
+                name => {
+                    if let Some(run_font) = font.take() {
+                        font = Some(read_font_property(run_font, name, &attributes(&e)?));
+                    }
+                }
+            },
+            Event::Empty(e) => {
+                let name = local_name(&e);
+                if let Some(run_font) = font.take() {
+                    font = Some(read_font_property(run_font, &name, &attributes(&e)?));
+                }
+            }
+            Event::Text(e) if in_text => {
+                let text = e.unescape().map_err(|_| INVALID_XML)?;
+                runs.last_mut()
+                    .unwrap()
+                    .text
+                    .push_str(&unescape_text(&text));
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"rPh" => in_phonetic = false,
+                name if name == end.as_bytes() => break,
+                _ => {}
+            },
+            Event::Eof => return Err(INVALID_XML),
+            _ => {}
+        }
+    }
+    Ok(runs)
+}
+
+// the style of every cell xf, a cell refers to one with its `s` attribute. the lists of
+// the style sheet are kept, the cells, named styles and differential formats refer to them.
+fn read_styles(xml: &str) -> Result<(Vec<CellStyle>, ReadStyles), &'static str> {
+    let mut reader = Reader::from_str(xml);
+    let (root, _) = next_element(&mut reader)?.ok_or(INVALID_XML)?;
+    let namespaces = root_attributes(&root)?;
+    let mut read = ReadStyles::default();
+    let mut elements = vec![];
+    while let Some((e, empty)) = next_element(&mut reader)? {
+        let element = read_preserved_element(&mut reader, &e, empty, &HashMap::new())?;
+        match element.local_name() {
+            "numFmts" => {
+                for num_fmt in element.elements() {
+                    if let (Some(id), Some(code)) = (
+                        num_fmt.attribute("numFmtId").and_then(|id| id.parse().ok()),
+                        num_fmt.attribute("formatCode"),
+                    ) {
+                        read.num_fmts.push((id, code.to_string()));
+                    }
+                }
+            }
+            "fonts" => {
+                for font in element.elements() {
+                    let font_style = font.elements().fold(FontStyle::new(), |font, property| {
+                        let attributes = property.attributes.iter().cloned().collect();
+                        read_font_property(font, property.local_name(), &attributes)
+                    });
+                    read.fonts.push((font.clone(), font_style));
+                }
+            }
+            "cellXfs" => read.cell_xfs = element.elements().cloned().collect(),
+            _ => elements.push(element),
+        }
+    }
+    read.elements = PreservedElements::new(namespaces, elements);
+
+    let styles = read
+        .cell_xfs
+        .iter()
+        .enumerate()
+        .map(|(index, xf)| {
+            let id = |key: &str| {
+                xf.attribute(key)
+                    .and_then(|id| id.parse::<usize>().ok())
+                    .unwrap_or(0)
+            };
+            let num_fmt_id = id("numFmtId");
+            let number_format = read
+                .num_fmts
+                .iter()
+                .find(|(id, _)| *id == num_fmt_id)
+                .map(|(_, code)| code.as_str())
+                .or_else(|| built_in_number_format(num_fmt_id))
+                .filter(|code| *code != "General")
+                .map(|code| code.to_string());
+            CellStyle {
+                font_style: read
+                    .fonts
+                    .get(id("fontId"))
+                    .map(|(_, font)| font.clone())
+                    .unwrap_or_default(),
+                number_format,
+                base_xf: Some(index),
+            }
+        })
+        .collect();
+    Ok((styles, read))
+}
+
+// the rows and hyperlinks of a sheet, and the elements kept (columns, merged cells, ..).
+fn read_work_sheet(
+    xml: &str,
+    name: &str,
+    hyperlinks: &[PreservedRelation],
+    ids: &HashMap<String, String>,
+    context: &ReadContext,
+) -> Result<(WorkSheet, PreservedElements), &'static str> {
+    let mut work_sheet = WorkSheet::blank(name);
+    let mut reader = Reader::from_str(xml);
+    let (root, _) = next_element(&mut reader)?.ok_or(INVALID_XML)?;
+    let root_attributes = root_attributes(&root)?;
+    let mut elements = vec![];
+    while let Some((e, empty)) = next_element(&mut reader)? {
+        match local_name(&e).as_str() {
+            "sheetData" if !empty => read_sheet_data(&mut reader, &mut work_sheet, context)?,
+            "hyperlinks" if !empty => read_hyperlinks(&mut reader, &mut work_sheet, hyperlinks)?,
+            // the used range is not known until the sheet is written
+            "dimension" if !empty => {
+                reader.read_to_end(e.name()).map_err(|_| INVALID_XML)?;
+            }
+            "sheetData" | "hyperlinks" | "dimension" => {}
+            _ => elements.push(read_preserved_element(&mut reader, &e, empty, ids)?),
+        }
+    }
+    Ok((
+        work_sheet,
+        PreservedElements::new(root_attributes, elements),
+    ))
+}
+
+/// the rows of one sheet of a package, parsed as they are asked for. only the strings,
+/// the styles and the row being read are held in memory.
+pub struct RowStream {
+    reader: Reader<BufReader<PartReader>>,
+    context: ReadContext,
+    date_1904: bool,
+    next_row: usize,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+/// stream the rows of the sheet with this name, see `WorkBook::stream_rows`.
+pub fn open_row_stream(path: &str, sheet: &str) -> Result<RowStream, &'static str> {
+    let file = File::open(path).map_err(|_| "Could not open the file")?;
+    let mut package = Package::new(file)?;
+    let work_book_part = package
+        .relations("")?
+        .into_iter()
+        .find(|r| r.is("officeDocument"))
+        .map(|r| r.target)
+        .ok_or("Missing work book part")?;
+    let relations = package.relations(&work_book_part)?;
+    let (sheets, _, elements) =
+        read_work_book_xml(&package.part(&work_book_part)?, &HashMap::new())?;
+    let (_, r_id, _) = sheets
+        .into_iter()
+        .find(|(name, _, _)| name == sheet)
+        .ok_or("No sheet with this name")?;
+    let sheet_part = relations
+        .iter()
+        .find(|r| r.id == r_id && r.is("worksheet"))
+        .map(|r| r.target.clone())
+        .ok_or("Not a work sheet")?;
+    // serial numbers count from 1904-01-01 instead of 1900-01-01
+    let date_1904 = elements
+        .get("workbookPr")
+        .and_then(|e| e.attribute("date1904"))
+        .is_some_and(|v| matches!(v, "1" | "true"));
+    let (context, _) = package.read_context(&relations)?;
+    let part = package.into_part_reader(&sheet_part)?;
+    Ok(RowStream {
+        reader: Reader::from_reader(BufReader::new(part)),
+        context,
+        date_1904,
+        next_row: 1,
+        buf: vec![],
+        done: false,
+    })
+}
+
+impl RowStream {
+    fn next_row(&mut self) -> Result<Option<RowView>, &'static str> {
+        loop {
+            self.buf.clear();
+            let event = self.reader.read_event_into(&mut self.buf);
+            let row = match event.map_err(|error| match error {
+                // the checksum of the part is checked at its end
+                quick_xml::Error::Io(_) => "Could not read a part of the work book",
+                _ => INVALID_XML,
+            })? {
+                Event::Start(e) if local_name(&e) == "row" => {
+                    read_row(&mut self.reader, &e, self.next_row, &self.context)?
+                }
+                // a row with a height or a style and no cells
+                Event::Empty(e) if local_name(&e) == "row" => {
+                    Row::new(row_number(&e, self.next_row)?)
+                }
+                // the elements after the rows are read too, to the end of the part
+                Event::Eof => return Ok(None),
+                _ => continue,
+            };
+            self.next_row = row.get_row_number() + 1;
+            return Ok(Some(RowView::from_row(row, self.date_1904)));
+        }
+    }
+}
+
+impl Iterator for RowStream {
+    type Item = Result<RowView, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let row = self.next_row();
+        // nothing more after the end of the rows or an error
+        if !matches!(row, Ok(Some(_))) {
+            self.done = true;
+        }
+        row.transpose()
+    }
+}
+
+fn read_sheet_data(
+    reader: &mut Reader<&[u8]>,
+    work_sheet: &mut WorkSheet,
+    context: &ReadContext,
+) -> Result<(), &'static str> {
+    loop {
+        match reader.read_event().map_err(|_| INVALID_XML)? {
+            Event::Start(e) if local_name(&e) == "row" => {
+                let row = read_row(reader, &e, work_sheet.next_row_number(), context)?;
+                work_sheet.push_row(row)?;
+            }
+            Event::Empty(e) if local_name(&e) == "row" => {
+                let row_number = row_number(&e, work_sheet.next_row_number())?;
+                let row = work_sheet.add_row(row_number)?;
+                row.set_format(read_row_format(&e)?)?;
+            }
+            Event::End(e) if e.local_name().as_ref() == b"sheetData" => break,
+            Event::Eof => return Err(INVALID_XML),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn read_hyperlinks(
+    reader: &mut Reader<&[u8]>,
+    work_sheet: &mut WorkSheet,
+    relations: &[PreservedRelation],
+) -> Result<(), &'static str> {
+    loop {
+        match reader.read_event().map_err(|_| INVALID_XML)? {
+            Event::Start(e) | Event::Empty(e) if local_name(&e) == "hyperlink" => {
+                let attributes = attributes(&e)?;
+                let target = match (attributes.get("id"), attributes.get("location")) {
+                    (Some(r_id), _) => relations
+                        .iter()
+                        .find(|r| &r.id == r_id && r.external)
+                        .map(|r| HyperlinkTarget::External(r.target.clone())),
+                    (None, Some(location)) => Some(HyperlinkTarget::Location(location.clone())),
+                    (None, None) => None,
+                };
+                if let (Some(reference), Some(target)) = (attributes.get("ref"), target) {
+                    work_sheet.push_hyperlink(Hyperlink {
+                        reference: reference.clone(),
+                        target,
+                        tooltip: attributes.get("tooltip").cloned(),
+                        r_id: None,
+                    });
+                }
+            }
+            Event::End(e) if e.local_name().as_ref() == b"hyperlinks" => break,
+            Event::Eof => return Err(INVALID_XML),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn row_number(element: &BytesStart, next_row: usize) -> Result<usize, &'static str> {
+    match attributes(element)?.get("r") {
+        Some(r) => r.parse().map_err(|_| "Invalid row number"),
+        None => Ok(next_row),
+    }
+}
+
+/// a `<row>` and its cells, the reader is right after the start tag.
+/// rows and cells without a reference follow the one before.
+pub fn read_row<B: BufRead>(
+    reader: &mut Reader<B>,
+    start: &BytesStart,
+    next_row: usize,
+    context: &ReadContext,
+) -> Result<Row, &'static str> {
+    let mut row = Row::new(row_number(start, next_row)?);
+    row.set_format(read_row_format(start)?)?;
+    let mut next_column = 1;
+    let mut buf = vec![];
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf).map_err(|_| INVALID_XML)? {
+            Event::Start(e) if local_name(&e) == "c" => {
+                let cell = read_cell(reader, &e, false, &row, next_column, context)?;
+                next_column = push_cell(&mut row, cell)?;
+            }
+            Event::Empty(e) if local_name(&e) == "c" => {
+                let cell = read_cell(reader, &e, true, &row, next_column, context)?;
+                next_column = push_cell(&mut row, cell)?;
+            }
+            Event::End(e) if e.local_name().as_ref() == b"row" => break,
+            Event::Eof => return Err(INVALID_XML),
+            _ => {}
+        }
+    }
+    Ok(row)
+}
+
+// the height, visibility, outline and style of a `<row>`. the spans are left out, the
+// cells may change.
+fn read_row_format(start: &BytesStart) -> Result<RowFormat, &'static str> {
+    let mut format = RowFormat::default();
+    let mut custom_format = false;
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(|_| INVALID_XML)?;
+        let value = attribute.unescape_value().map_err(|_| INVALID_XML)?;
+        let on = matches!(value.as_ref(), "1" | "true");
+        match attribute.key.as_ref() {
+            b"r" | b"spans" => {}
+            b"ht" => format.height = Some(value.parse().map_err(|_| "Invalid row height")?),
+            b"customHeight" => format.custom_height = on,
+            b"hidden" => format.hidden = on,
+            b"outlineLevel" => {
+                format.outline_level = value.parse().map_err(|_| "Invalid outline level")?
+            }
+            b"collapsed" => format.collapsed = on,
+            b"s" => format.style_index = value.parse().ok(),
+            b"customFormat" => custom_format = on,
+            key => format
+                .kept_attributes
+                .push((String::from_utf8_lossy(key).to_string(), value.to_string())),
+        }
+    }
+    // the style of a row only counts with `customFormat`
+    if !custom_format {
+        format.style_index = None;
+    }
+    Ok(format)
+}
+
+// add the cell and return the column after it.
+fn push_cell(row: &mut Row, cell: Cell) -> Result<usize, &'static str> {
+    let reference = cell.get_attributes().reference.clone().unwrap_or_default();
+    let (_, column) = parse_cell_ref(&reference)?;
+    row.add_cell(cell)?;
+    Ok(column + 1)
+}
+
+fn read_cell<B: BufRead>(
+    reader: &mut Reader<B>,
+    start: &BytesStart,
+    empty: bool,
+    row: &Row,
+    next_column: usize,
+    context: &ReadContext,
+) -> Result<Cell, &'static str> {
+    let attributes = attributes(start)?;
+    let reference = attributes.get("r").cloned().unwrap_or_else(|| {
+        format!(
+            "{}{}",
+            column_number_to_name(next_column),
+            row.get_row_number()
+        )
+    });
+    let cell_type = attributes.get("t").map(|t| t.as_str()).unwrap_or("n");
+
+    let mut value: Option<String> = None;
+    let mut formula: Option<String> = None;
+    let mut formula_kind = FormulaKind::Normal;
+    let mut inline_runs: Option<Vec<Run>> = None;
+    // the element whose text is being read, `v` or `f`
+    let mut in_element: Option<Vec<u8>> = None;
+    let mut buf = vec![];
+    if !empty {
+        loop {
+            buf.clear();
+            match reader.read_event_into(&mut buf).map_err(|_| INVALID_XML)? {
+                Event::Start(e) => match e.local_name().as_ref() {
+                    b"is" => inline_runs = Some(read_runs(reader, "is")?),
+                    b"f" => {
+                        formula = Some(String::new());
+                        formula_kind = read_formula_kind(&e)?;
+                        in_element = Some(b"f".to_vec());
+                    }
+                    b"v" => in_element = Some(b"v".to_vec()),
+                    _ => {}
+                },
+                // a cell sharing the formula of another one
+                Event::Empty(e) if e.local_name().as_ref() == b"f" => {
+                    formula = Some(String::new());
+                    formula_kind = read_formula_kind(&e)?;
+                }
+                Event::Text(e) => {
+                    let text = e.unescape().map_err(|_| INVALID_XML)?;
+                    match in_element.as_deref() {
+                        Some(b"v") => value.get_or_insert_with(String::new).push_str(&text),
+                        Some(b"f") => formula.get_or_insert_with(String::new).push_str(&text),
+                        _ => {}
+                    }
+                }
+                Event::End(e) => match e.local_name().as_ref() {
+                    b"c" => break,
+                    _ => in_element = None,
+                },
+                Event::Eof => return Err(INVALID_XML),
+                _ => {}
+            }
+        }
+    }
+
+    let cell_value = match (cell_type, value) {
+        ("s", Some(index)) => {
+            let runs = index
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| context.strings.get(i))
+                .ok_or("Invalid shared string index")?;
+            string_value(runs.clone(), false)
+        }
+        ("inlineStr", _) => string_value(inline_runs.unwrap_or_default(), true),
+        ("b", Some(v)) => CellValue::CBool(matches!(v.trim(), "1" | "true")),
+        ("str", Some(v)) => CellValue::CFomula(unescape_text(&v)),
+        ("e", Some(v)) => CellValue::CError(unescape_text(&v)),
+        ("d", Some(v)) => CellValue::CDate(v),
+        ("n", Some(v)) => CellValue::CNumber(v.trim().to_string()),
+        _ => CellValue::Empty,
+    };
+    let mut cell = Cell::new(cell_value, reference);
+    // cells sharing a formula only have it on the first one
+    let shared = matches!(formula_kind, FormulaKind::Shared { .. });
+    if let Some(formula) = formula.filter(|f| shared || !f.is_empty()) {
+        cell.set_formula(&formula);
+        cell.set_formula_kind(formula_kind);
+    }
+    let style = attributes
+        .get("s")
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|s| *s != 0)
+        .and_then(|s| context.styles.get(s));
+    if let Some(style) = style {
+        cell.set_style(style.clone());
+    }
+    Ok(cell)
+}
+
+// the `t`, `ref` and `si` of a `<f>`
+fn read_formula_kind(start: &BytesStart) -> Result<FormulaKind, &'static str> {
+    let attributes = attributes(start)?;
+    let range = attributes.get("ref").cloned();
+    match attributes.get("t").map(|t| t.as_str()) {
+        Some("shared") => {
+            let index = attributes
+                .get("si")
+                .and_then(|i| i.parse::<u32>().ok())
+                .ok_or("Invalid shared formula index")?;
+            Ok(FormulaKind::Shared { index, range })
+        }
+        Some("array") => Ok(FormulaKind::Array {
+            range: range.ok_or("Array formula without a range")?,
+        }),
+        _ => Ok(FormulaKind::Normal),
+    }
+}
+
+// plain text stays a plain (shared or inline) string, formatted runs a rich string.
+fn string_value(runs: Vec<Run>, inline: bool) -> CellValue {
+    if runs.iter().any(|r| r.font.is_some()) {
+        CellValue::CRichString(runs)
+    } else if inline {
+        CellValue::CInString(rich_text::plain_text(&runs))
+    } else {
+        CellValue::CString(rich_text::plain_text(&runs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use xmlwriter::{Indent, Options, XmlWriter};
+    use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+    use super::*;
+    use crate::traits::XMLString;
+
+    // a work book of one sheet with the given parts under `xl/`
+    fn package(sheet_xml: &str, styles_xml: Option<&str>) -> Cursor<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        let mut add = |name: &str, xml: &str| {
+            // stored, so a test can change the data after the checksum
+            let options =
+                SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+            zip.start_file(name, options).unwrap();
+            zip.write_all(xml.as_bytes()).unwrap();
+        };
+        let main = "application/vnd.openxmlformats-officedocument.spreadsheetml";
+        let relations = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+        add(
+            "[Content_Types].xml",
+            &format!(
+                r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="{main}.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="{main}.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="{main}.styles+xml"/></Types>"#
+            ),
+        );
+        add(
+            "_rels/.rels",
+            &format!(
+                r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="{relations}/officeDocument" Target="xl/workbook.xml"/></Relationships>"#
+            ),
+        );
+        add(
+            "xl/workbook.xml",
+            &format!(
+                r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="{relations}"><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets></workbook>"#
+            ),
+        );
+        let styles = match styles_xml {
+            Some(_) => format!(
+                r#"<Relationship Id="rId2" Type="{relations}/styles" Target="styles.xml"/>"#
+            ),
+            None => String::new(),
+        };
+        add(
+            "xl/_rels/workbook.xml.rels",
+            &format!(
+                r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="{relations}/worksheet" Target="worksheets/sheet1.xml"/>{styles}</Relationships>"#
+            ),
+        );
+        add(
+            "xl/worksheets/sheet1.xml",
+            &format!(
+                r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>{sheet_xml}</sheetData></worksheet>"#
+            ),
+        );
+        if let Some(styles_xml) = styles_xml {
+            add("xl/styles.xml", styles_xml);
+        }
+        let mut cursor = zip.finish().unwrap();
+        cursor.set_position(0);
+        cursor
+    }
+
+    fn read_sheet(sheet_xml: &str, styles_xml: Option<&str>) -> WorkSheet {
+        let read = read_work_book(package(sheet_xml, styles_xml)).unwrap();
+        match read.sheets.into_iter().next() {
+            Some(ReadSheet::WorkSheet(work_sheet)) => *work_sheet,
+            _ => panic!("not a work sheet"),
+        }
+    }
+
+    fn row_xml(row: Row) -> String {
+        let mut writer = XmlWriter::new(Options {
+            indent: Indent::None,
+            ..Default::default()
+        });
+        row.to_xml(&mut writer);
+        writer.end_document()
+    }
+
+    #[test]
+    fn shared_formulas_are_kept() {
+        let mut work_sheet = read_sheet(
+            r#"<row r="1"><c r="A1"><f t="shared" ref="A1:A2" si="0">B1*2</f><v>2</v></c></row><row r="2"><c r="A2"><f t="shared" si="0"/><v>4</v></c></row>"#,
+            None,
+        );
+        let second = work_sheet.rows.remove(1);
+        let first = work_sheet.rows.remove(0);
+        let master = &first.get_cells()[0];
+        assert_eq!(master.get_formula(), Some("B1*2"));
+        assert_eq!(
+            master.get_formula_kind(),
+            &FormulaKind::Shared {
+                index: 0,
+                range: Some("A1:A2".to_string())
+            }
+        );
+        let dependent = &second.get_cells()[0];
+        assert_eq!(dependent.get_formula(), Some(""));
+        assert_eq!(
+            row_xml(first),
+            r#"<row r="1"><c r="A1" t="n"><f t="shared" ref="A1:A2" si="0">B1*2</f><v>2</v></c></row>"#
+        );
+        assert_eq!(
+            row_xml(second),
+            r#"<row r="2"><c r="A2" t="n"><f t="shared" si="0"/><v>4</v></c></row>"#
+        );
+    }
+
+    #[test]
+    fn array_formulas_are_kept() {
+        let mut work_sheet = read_sheet(
+            r#"<row r="1"><c r="C1"><f t="array" ref="C1:C3">A1:A3*B1:B3</f><v>3</v></c></row>"#,
+            None,
+        );
+        let row = work_sheet.rows.remove(0);
+        assert_eq!(
+            row.get_cells()[0].get_formula_kind(),
+            &FormulaKind::Array {
+                range: "C1:C3".to_string()
+            }
+        );
+        assert_eq!(
+            row_xml(row),
+            r#"<row r="1"><c r="C1" t="n"><f t="array" ref="C1:C3">A1:A3*B1:B3</f><v>3</v></c></row>"#
+        );
+    }
+
+    #[test]
+    fn plain_formulas_and_values_are_read() {
+        let mut work_sheet = read_sheet(
+            r#"<row r="1"><c r="A1" t="str"><f>"a"&amp;"b"</f><v>ab</v></c><c r="B1" t="b"><v>1</v></c><c r="C1"><f></f><v>5</v></c></row>"#,
+            None,
+        );
+        let row = work_sheet.rows.remove(0);
+        let cells = row.get_cells();
+        assert_eq!(cells[0].get_formula(), Some(r#""a"&"b""#));
+        assert_eq!(cells[0].get_formula_kind(), &FormulaKind::Normal);
+        assert!(matches!(cells[1].value, CellValue::CBool(true)));
+        assert_eq!(cells[2].get_formula(), None);
+    }
+
+    #[test]
+    fn error_values_are_written_back_as_errors() {
+        let mut work_sheet = read_sheet(
+            r#"<row r="1"><c r="A1" t="e"><f>1/0</f><v>#DIV/0!</v></c></row>"#,
+            None,
+        );
+        let row = work_sheet.rows.remove(0);
+        assert!(matches!(&row.get_cells()[0].value, CellValue::CError(e) if e == "#DIV/0!"));
+        assert_eq!(
+            row_xml(row),
+            r#"<row r="1"><c r="A1" t="e"><f>1/0</f><v>#DIV/0!</v></c></row>"#
+        );
+    }
+
+    static STYLES_XML: &str = r#"<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><numFmts count="1"><numFmt numFmtId="164" formatCode="0.0"/></numFmts><fonts count="2"><font><sz val="11"/><name val="Calibri"/><family val="2"/></font><font><b/><sz val="11"/><name val="Calibri"/><family val="2"/></font></fonts><fills count="3"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill><fill><patternFill patternType="solid"><fgColor rgb="FFFFFF00"/></patternFill></fill></fills><borders count="2"><border/><border><left style="thin"/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="2"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="164" fontId="1" fillId="2" borderId="1" xfId="0" applyFill="1" applyAlignment="1"><alignment horizontal="center" wrapText="1"/></xf></cellXfs><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles></styleSheet>"#;
+
+    // the style sheet written for the read one with the styles of the cells added
+    fn styles_xml(work_sheet: &mut WorkSheet, styles: ReadStyles) -> (String, Vec<usize>) {
+        let mut style = crate::xml_templates::style::Style::new();
+        style.preserve(styles);
+        let mut indexes = vec![];
+        for cell in work_sheet.rows[0].get_cells_mut().iter_mut() {
+            let cell_style = cell.get_style().clone().unwrap();
+            indexes.push(style.add_cell_xf(
+                Some(&cell_style.font_style),
+                cell_style.number_format.as_deref(),
+                cell_style.base_xf,
+            ));
+        }
+        (style.to_xml(), indexes)
+    }
+
+    #[test]
+    fn read_cell_formats_are_kept() {
+        let sheet_xml =
+            r#"<row r="1"><c r="A1" s="1"><v>1</v></c><c r="B1" s="1"><v>2</v></c></row>"#;
+        let read = read_work_book(package(sheet_xml, Some(STYLES_XML))).unwrap();
+        let Some(ReadSheet::WorkSheet(mut work_sheet)) = read.sheets.into_iter().next() else {
+            panic!("not a work sheet");
+        };
+        let cell = &mut work_sheet.rows[0].get_cells_mut()[1];
+        assert_eq!(cell.number_format(), Some("0.0"));
+        cell.set_font_style(FontStyle::new().italic(true));
+
+        let (xml, indexes) = styles_xml(&mut work_sheet, read.styles);
+        // the unchanged cell keeps its xf, the changed one gets a new xf after the read ones
+        assert_eq!(indexes, [1, 2]);
+        assert!(xml.contains(r#"<fills count="3">"#));
+        assert!(xml.contains(r#"<fgColor rgb="FFFFFF00"/>"#));
+        assert!(xml.contains(r#"<left style="thin"/>"#));
+        assert!(xml.contains(r#"<cellStyle name="Normal" xfId="0" builtinId="0"/>"#));
+        assert!(xml.contains(r#"<family val="2"/>"#));
+        assert!(xml.contains(r#"<numFmt numFmtId="164" formatCode="0.0"/>"#));
+        assert!(xml.contains(r#"<fonts count="3">"#));
+        assert!(xml.contains(r#"<xf numFmtId="164" fontId="1" fillId="2" borderId="1" xfId="0" applyFill="1" applyAlignment="1">"#));
+        assert!(xml.contains(r#"<xf numFmtId="164" fontId="2" fillId="2" borderId="1" xfId="0" applyFill="1" applyAlignment="1" applyFont="1">"#));
+        assert_eq!(
+            xml.matches(r#"<alignment horizontal="center" wrapText="1"/>"#)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn row_formats_are_kept() {
+        let mut work_sheet = read_sheet(
+            r#"<row r="1" spans="1:2" ht="30.5" customHeight="1" s="1" customFormat="1" x14ac:dyDescent="0.25"><c r="A1"><v>1</v></c></row><row r="2" hidden="1" outlineLevel="2" collapsed="1" s="1"/>"#,
+            None,
+        );
+        let second = work_sheet.rows.remove(1);
+        let first = work_sheet.rows.remove(0);
+        assert_eq!(first.get_format().height, Some(30.5));
+        assert_eq!(second.get_format().outline_level, 2);
+        // a style without `customFormat` does not count
+        assert_eq!(second.get_format().style_index, None);
+        assert_eq!(
+            row_xml(first),
+            r#"<row r="1" s="1" customFormat="1" ht="30.5" customHeight="1" x14ac:dyDescent="0.25"><c r="A1" t="n"><v>1</v></c></row>"#
+        );
+        assert_eq!(
+            row_xml(second),
+            r#"<row r="2" hidden="1" outlineLevel="2" collapsed="1"/>"#
+        );
+    }
+
+    fn stream_rows(
+        package: Cursor<Vec<u8>>,
+        file_name: &str,
+    ) -> Vec<Result<RowView, &'static str>> {
+        let path = std::env::temp_dir().join(file_name);
+        std::fs::write(&path, package.into_inner()).unwrap();
+        let rows = open_row_stream(path.to_str().unwrap(), "Sheet1")
+            .unwrap()
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        rows
+    }
+
+    #[test]
+    fn rows_are_streamed() {
+        let sheet_xml =
+            r#"<row r="1"><c r="A1"><v>1</v></c></row><row r="3"><c r="B3"><v>2</v></c></row>"#;
+        let rows = stream_rows(package(sheet_xml, None), "xml1_stream_rows.xlsx");
+        let rows: Vec<_> = rows.into_iter().map(|row| row.unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].row_number, 3);
+        assert_eq!(rows[1].cells[0].column, 2);
+    }
+
+    #[test]
+    fn a_changed_part_fails_its_checksum() {
+        let sheet_xml = r#"<row r="1"><c r="A1"><v>1</v></c></row>"#;
+        let mut data = package(sheet_xml, None).into_inner();
+        let at = data.windows(8).position(|w| w == b"<v>1</v>").unwrap();
+        data[at + 3] = b'2';
+        let rows = stream_rows(Cursor::new(data), "xml1_changed_part.xlsx");
+        assert_eq!(
+            rows.last().unwrap().as_ref().err(),
+            Some(&"Could not read a part of the work book")
+        );
+    }
+
+    #[test]
+    fn a_work_book_is_written_to_any_writer() {
+        let sheet_xml = r#"<row r="1"><c r="A1"><v>7</v></c></row>"#;
+        let work_book = crate::work_book::WorkBook::from_reader(package(sheet_xml, None)).unwrap();
+        let mut out = Cursor::new(vec![]);
+        work_book.write_to(&mut out, Default::default()).unwrap();
+        out.set_position(0);
+        let mut read = crate::work_book::WorkBook::from_reader(out).unwrap();
+        let row = read.work_sheets[0].rows.remove(0);
+        assert!(matches!(row.get_cells()[0].value, CellValue::CNumber(ref n) if n == "7"));
+    }
+}
@@ -0,0 +1,97 @@
+// rich text: a string made of runs, each with its own font. used by cells and notes.
+
+use xmlwriter::XmlWriter;
+
+use crate::{
+    escape::write_string,
+    xml_templates::style::{FontStyle, UnderLine},
+};
+
+/// A piece of text with its formatting, a run without a font takes the cell font.
+///
+/// ```ignore
+/// row.add_rich_string(vec![
+///     Run::new("Total: ").bold(),
+///     Run::new("42").color("FFFF0000"),
+/// ]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub text: String,
+    pub font: Option<FontStyle>,
+}
+
+impl Run {
+    pub fn new(text: &str) -> Self {
+        Run {
+            text: text.to_string(),
+            font: None,
+        }
+    }
+
+    /// use this font for the run, replaces the formatting set so far.
+    pub fn font(mut self, font: FontStyle) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    pub fn bold(self) -> Self {
+        self.style(|font| font.bold(true))
+    }
+
+    pub fn italic(self) -> Self {
+        self.style(|font| font.italic(true))
+    }
+
+    pub fn strike(self) -> Self {
+        self.style(|font| font.strike(true))
+    }
+
+    pub fn underline(self, underline: UnderLine) -> Self {
+        self.style(|font| font.underline(Some(underline)))
+    }
+
+    /// font color as ARGB hex, eg. `FFFF0000`
+    pub fn color(self, argb: &str) -> Self {
+        self.style(|font| font.color(argb))
+    }
+
+    pub fn size(self, size: u8) -> Self {
+        self.style(|font| font.size(size))
+    }
+
+    pub fn font_name(self, name: &str) -> Self {
+        self.style(|font| font.name(name))
+    }
+
+    fn style(mut self, change: impl FnOnce(FontStyle) -> FontStyle) -> Self {
+        self.font = Some(change(self.font.unwrap_or_default()));
+        self
+    }
+
+    // text and formatting, two runs with the same id are the same.
+    pub(crate) fn unique_id(&self) -> String {
+        let font = self
+            .font
+            .as_ref()
+            .map_or_else(|| "f_none".to_string(), |f| f.unqiue_id());
+        format!("{}:{}", font, self.text)
+    }
+}
+
+/// the text of the runs without formatting
+pub fn plain_text(runs: &[Run]) -> String {
+    runs.iter().map(|r| r.text.as_str()).collect()
+}
+
+/// writes the runs as `<r>` elements, the caller writes the element around them.
+pub fn write_runs(writer: &mut XmlWriter, runs: &[Run]) {
+    for run in runs {
+        writer.start_element("r");
+        if let Some(font) = &run.font {
+            font.to_run_properties_xml(writer);
+        }
+        write_string(writer, &run.text);
+        writer.end_element();
+    }
+}
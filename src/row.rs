@@ -1,6 +1,8 @@
 use std::{collections::HashSet, num::ParseFloatError};
 
 use crate::cell::Cell;
+use crate::escape::{check_string_length, escape_xml};
+use crate::rich_text::{self, Run};
 use crate::traits;
 use traits::XMLString;
 use xmlwriter::*;
@@ -9,11 +11,49 @@ static ALP: [char; 26] = [
     'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
     'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
 ];
+// sheet limits
+pub const MAX_ROWS: usize = 1_048_576;
+pub const MAX_COLUMNS: usize = 16_384;
+
 pub struct Row {
     cells: Vec<Cell>,
     row_number: usize,
     column_number: usize,
     cell_reference_set: HashSet<String>,
+    format: RowFormat,
+}
+
+/// the height, visibility and outline level of a row
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RowFormat {
+    /// in points, up to 409
+    pub height: Option<f64>,
+    /// the height is set, not fitted to the cells
+    pub custom_height: bool,
+    pub hidden: bool,
+    /// 0 to 7, the rows of a group are a level below the row summing them up
+    pub outline_level: u8,
+    /// the group next to this row is collapsed
+    pub collapsed: bool,
+    // the cell xf of the whole row in a read style sheet
+    pub(crate) style_index: Option<usize>,
+    // the other attributes of a read row, eg. `thickBot` or `x14ac:dyDescent`
+    pub(crate) kept_attributes: Vec<(String, String)>,
+}
+
+impl RowFormat {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self
+            .height
+            .is_some_and(|h| !h.is_finite() || !(0.0..=409.0).contains(&h))
+        {
+            return Err("Row height must be between 0 and 409 points");
+        }
+        if self.outline_level > 7 {
+            return Err("Outline level must be between 0 and 7");
+        }
+        Ok(())
+    }
 }
 
 fn split_cell_ref(cell_ref: &str) -> Result<(String, String), &'static str> {
@@ -32,6 +72,48 @@ fn split_cell_ref(cell_ref: &str) -> Result<(String, String), &'static str> {
     Ok((col_ref, row_ref))
 }
 
+/// column number (1 based) to its letters, 1 -> A, 27 -> AA
+pub fn column_number_to_name(column_number: usize) -> String {
+    let mut result = Vec::with_capacity(3);
+    let mut idx = column_number;
+    while idx > 0 {
+        let remainder = (idx - 1) % 26;
+        result.push(ALP[remainder]);
+        idx = (idx - 1) / 26;
+    }
+    result.into_iter().rev().collect()
+}
+
+/// column letters to its number (1 based), A -> 1, AA -> 27
+pub fn column_name_to_number(column_name: &str) -> usize {
+    let mut index = 0;
+    for ch in column_name.chars() {
+        let value = (ch.to_ascii_uppercase() as u8 - b'A' + 1) as usize;
+        index = index * 26 + value;
+    }
+    index
+}
+
+/// parse a cell reference like `B3` or `$B$3` into (row number, column number), both 1 based.
+pub fn parse_cell_ref(cell_ref: &str) -> Result<(usize, usize), &'static str> {
+    let cell_ref = cell_ref.replace('$', "");
+    let split_at = cell_ref
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .ok_or("Invalid cell reference")?;
+    let (col_ref, row_ref) = cell_ref.split_at(split_at);
+    if col_ref.is_empty() || col_ref.len() > 3 || !row_ref.chars().all(|c| c.is_ascii_digit()) {
+        return Err("Invalid cell reference");
+    }
+    let row_number = row_ref
+        .parse::<usize>()
+        .map_err(|_| "Invalid cell reference")?;
+    let column_number = column_name_to_number(col_ref);
+    if row_number == 0 || row_number > MAX_ROWS || column_number > MAX_COLUMNS {
+        return Err("Invalid cell reference");
+    }
+    Ok((row_number, column_number))
+}
+
 impl Row {
     pub fn new(row_number: usize) -> Row {
         Row {
@@ -40,21 +122,41 @@ impl Row {
             cells: Vec::new(),
             column_number: 1,
             cell_reference_set: HashSet::new(),
+            format: RowFormat::default(),
         }
     }
 
-    pub fn add_string(&mut self, value: String) -> &mut Cell {
+    /// can fail if the string is longer than 32767 characters.
+    pub fn add_string(&mut self, value: String) -> Result<&mut Cell, &'static str> {
+        check_string_length(&value)?;
         let cell_reference = self.get_next_cell_ref();
         let cell = Cell::from_string(value, cell_reference, false);
         self.cells.push(cell);
-        self.cells.last_mut().unwrap()
+        Ok(self.cells.last_mut().unwrap())
     }
 
-    pub fn add_inline_string(&mut self, value: String) -> &mut Cell {
+    pub fn add_inline_string(&mut self, value: String) -> Result<&mut Cell, &'static str> {
+        check_string_length(&value)?;
         let cell_reference = self.get_next_cell_ref();
         let cell = Cell::from_string(value, cell_reference, true);
         self.cells.push(cell);
-        self.cells.last_mut().unwrap()
+        Ok(self.cells.last_mut().unwrap())
+    }
+
+    /// a string with differently formatted parts, eg. a bold label and a red value.
+    pub fn add_rich_string(&mut self, runs: Vec<Run>) -> Result<&mut Cell, &'static str> {
+        check_string_length(&rich_text::plain_text(&runs))?;
+        let cell_reference = self.get_next_cell_ref();
+        let cell = Cell::from_rich_string(runs, cell_reference);
+        self.cells.push(cell);
+        Ok(self.cells.last_mut().unwrap())
+    }
+
+    /// a string cell linking to `url`
+    pub fn add_url(&mut self, url: &str, text: String) -> Result<&mut Cell, &'static str> {
+        let cell = self.add_string(text)?;
+        cell.set_hyperlink(url, None);
+        Ok(cell)
     }
 
     pub fn add_number(&mut self, value: String) -> Result<&mut Cell, ParseFloatError> {
@@ -75,55 +177,51 @@ impl Row {
         if row_number != self.row_number.to_string() {
             return Err("Invalid row reference");
         }
+        cell.check_length()?;
 
         // update the set
         self.cell_reference_set.insert(cell_reference.clone());
         self.cells.push(cell);
-        // todo: update the coulumn_number;
-        self.column_number = self.column_ref_to_number(column_ref.as_str());
+        // the next added cell goes right of this one
+        self.column_number = self.column_ref_to_number(column_ref.as_str()) + 1;
         Ok(self.cells.last_mut().unwrap())
     }
 
+    pub fn get_row_number(&self) -> usize {
+        self.row_number
+    }
+
+    pub fn set_format(&mut self, format: RowFormat) -> Result<(), &'static str> {
+        format.validate()?;
+        self.format = format;
+        Ok(())
+    }
+
+    pub fn get_format(&self) -> &RowFormat {
+        &self.format
+    }
+
+    pub fn get_cells(&self) -> &Vec<Cell> {
+        &self.cells
+    }
+
     pub fn get_cells_mut(&mut self) -> &mut Vec<Cell> {
         &mut self.cells
     }
 
     fn get_next_cell_ref(&mut self) -> String {
-        println!("<{}>", self.column_number);
-
         if self.column_number == 0 {
             self.column_number += 1;
-            return format!("A{}", self.row_number);
+            format!("A{}", self.row_number)
         } else {
-            let mut result = Vec::with_capacity(3);
-            let mut idx = self.column_number; // Make a mutable copy of the index
-                                              // Process the index until it is fully converted
-            while idx > 0 {
-                let remainder = ((idx - 1) % 26) as usize; // Find the remainder when divided by 26
-                let ch = ALP.get(remainder).unwrap();
-                result.push(ch); // Append character to result
-                idx = idx / 26; // Update index for next iteration
-            }
+            let column_name = column_number_to_name(self.column_number);
             self.column_number += 1;
-            return format!(
-                "{}{}",
-                result.into_iter().rev().collect::<String>(),
-                self.row_number
-            );
+            format!("{}{}", column_name, self.row_number)
         }
     }
 
     fn column_ref_to_number(&self, s: &str) -> usize {
-        let mut index = 0;
-
-        for ch in s.chars() {
-            // Convert character to its 1-based value
-            let value = (ch as u8 - b'A' + 1) as usize;
-            // Calculate the index by considering base-26 positional values
-            index = index * 26 + value;
-        }
-
-        index
+        column_name_to_number(s)
     }
 }
 
@@ -131,6 +229,29 @@ impl XMLString for Row {
     fn to_xml(self, writer: &mut XmlWriter) {
         writer.start_element("row");
         writer.write_attribute("r", &self.row_number.to_string());
+        let format = self.format;
+        if let Some(style_index) = format.style_index {
+            writer.write_attribute("s", &style_index);
+            writer.write_attribute("customFormat", "1");
+        }
+        if let Some(height) = format.height {
+            writer.write_attribute("ht", &height);
+            if format.custom_height {
+                writer.write_attribute("customHeight", "1");
+            }
+        }
+        if format.hidden {
+            writer.write_attribute("hidden", "1");
+        }
+        if format.outline_level > 0 {
+            writer.write_attribute("outlineLevel", &format.outline_level);
+        }
+        if format.collapsed {
+            writer.write_attribute("collapsed", "1");
+        }
+        for (name, value) in format.kept_attributes.iter() {
+            writer.write_attribute(name, &escape_xml(value));
+        }
         for cell in self.cells {
             cell.to_xml(writer);
         }
@@ -157,6 +278,5 @@ impl<'a> ColMovement<'a> {
         for _ in 0..n {
             self.row.get_next_cell_ref();
         }
-        println!(" > {} ", self.row.column_number);
     }
 }
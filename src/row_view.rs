@@ -0,0 +1,700 @@
+// rows read one at a time from a sheet, with their values resolved, see
+// `WorkBook::stream_rows`.
+
+use crate::{
+    cell::{Cell, CellValue},
+    rich_text,
+    row::{parse_cell_ref, Row},
+    xml_templates::doc_props::{civil_from_days, days_from_civil},
+};
+
+/// the value of a read cell
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellData {
+    // a shared, inline or rich string as plain text, or the text result of a formula
+    Text(String),
+    Number(f64),
+    Bool(bool),
+    // ISO 8601, eg. `2024-01-31` or `2024-01-31T10:30:00`, from a number with a date format
+    Date(String),
+    Empty,
+}
+
+/// a cell of a read row
+#[derive(Debug, Clone)]
+pub struct CellView {
+    pub reference: String,
+    // 1 for column A
+    pub column: usize,
+    pub value: CellData,
+    pub formula: Option<String>,
+    // format code, None for General
+    pub number_format: Option<String>,
+}
+
+/// a row of a sheet read on its own, see `WorkBook::stream_rows`.
+#[derive(Debug, Clone)]
+pub struct RowView {
+    pub row_number: usize,
+    pub cells: Vec<CellView>,
+}
+
+impl RowView {
+    pub(crate) fn from_row(mut row: Row, date_1904: bool) -> Self {
+        let row_number = row.get_row_number();
+        let cells = row
+            .get_cells_mut()
+            .drain(..)
+            .map(|cell| CellView::from_cell(cell, date_1904))
+            .collect();
+        RowView { row_number, cells }
+    }
+
+    /// the cell in this column, 1 for column A
+    pub fn cell(&self, column: usize) -> Option<&CellView> {
+        self.cells.iter().find(|c| c.column == column)
+    }
+}
+
+impl CellView {
+    fn from_cell(mut cell: Cell, date_1904: bool) -> Self {
+        let reference = cell.get_attributes().reference.clone().unwrap_or_default();
+        let column = parse_cell_ref(&reference).map(|(_, c)| c).unwrap_or(0);
+        // a cell sharing the formula of an earlier one has no text of its own
+        let formula = cell
+            .get_formula()
+            .filter(|f| !f.is_empty())
+            .map(|f| f.to_string());
+        let number_format = cell
+            .get_style()
+            .as_ref()
+            .and_then(|s| s.number_format.clone());
+        let value = match std::mem::replace(&mut cell.value, CellValue::Empty) {
+            // an error shows as its text, eg. #DIV/0!
+            CellValue::CString(text)
+            | CellValue::CInString(text)
+            | CellValue::CFomula(text)
+            | CellValue::CError(text) => CellData::Text(text),
+            CellValue::CRichString(runs) => CellData::Text(rich_text::plain_text(&runs)),
+            CellValue::CBool(value) => CellData::Bool(value),
+            CellValue::CDate(date) => CellData::Date(date),
+            CellValue::CNumber(number) => match number.parse::<f64>() {
+                Ok(serial) if number_format.as_deref().is_some_and(is_date_format) => {
+                    serial_to_iso(serial, date_1904)
+                        .map(CellData::Date)
+                        .unwrap_or(CellData::Number(serial))
+                }
+                Ok(value) => CellData::Number(value),
+                Err(_) => CellData::Text(number),
+            },
+            CellValue::Empty => CellData::Empty,
+        };
+        CellView {
+            reference,
+            column,
+            value,
+            formula,
+            number_format,
+        }
+    }
+}
+
+/// whether a number format shows a date or a time, eg. `yyyy-mm-dd`, `h:mm` or `[h]:mm`.
+/// quoted text, escaped characters and colors like `[Red]` do not count.
+pub fn is_date_format(format_code: &str) -> bool {
+    let mut chars = format_code.chars();
+    let mut in_quotes = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            _ if in_quotes => {}
+            // the next character is shown as it is, or is a fill or a space
+            '\\' | '_' | '*' => {
+                chars.next();
+            }
+            '[' => {
+                let inner: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                // elapsed time, eg. `[h]` or `[mm]`
+                let elapsed = !inner.is_empty()
+                    && inner
+                        .chars()
+                        .all(|c| matches!(c.to_ascii_lowercase(), 'h' | 'm' | 's'));
+                if elapsed {
+                    return true;
+                }
+            }
+            _ if matches!(c.to_ascii_lowercase(), 'd' | 'm' | 'y' | 'h' | 's') => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// the date of a serial number as ISO 8601, `45000.5` is `2023-03-15T12:00:00`; a time
+/// without a day is `12:00:00`. None for a negative number.
+pub fn serial_to_iso(serial: f64, date_1904: bool) -> Option<String> {
+    let (date, seconds) = serial_to_parts(serial, date_1904)?;
+    let time = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    );
+    let Some((year, month, day)) = date else {
+        return Some(time);
+    };
+    let date = format!("{:04}-{:02}-{:02}", year, month, day);
+    match seconds {
+        0 => Some(date),
+        _ => Some(format!("{}T{}", date, time)),
+    }
+}
+
+// year, month and day
+type CivilDate = (i64, u32, u32);
+
+// the date of a serial number, None for a time without a day, and the seconds of its time.
+fn serial_to_parts(serial: f64, date_1904: bool) -> Option<(Option<CivilDate>, i64)> {
+    if !serial.is_finite() || serial < 0.0 {
+        return None;
+    }
+    let seconds = (serial * 86_400.0).round() as i64;
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+    if days == 0 {
+        return Some((None, seconds));
+    }
+    // days since 1970-01-01, excel counts a 1900-02-29 that never was
+    let unix_days = match (date_1904, days) {
+        (true, _) => days - 24_107,
+        (false, 61..) => days - 25_569,
+        (false, _) => days - 25_568,
+    };
+    Some((Some(civil_from_days(unix_days)), seconds))
+}
+
+/// the serial number of an ISO 8601 date, `2023-03-15` is `45000`, and whether it has a
+/// time (`2023-03-15T12:00:00` or `2023-03-15 12:00`). None for an other text or a date
+/// before 1900-01-01; 1900-01-01 to 1900-02-28 are 1 to 59, before the 1900-02-29 excel has.
+pub fn iso_to_serial(text: &str) -> Option<(f64, bool)> {
+    let (date, time) = match text.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+    let mut parts = date.splitn(3, '-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    let year: i64 = year.parse().ok()?;
+    let (month, day): (u32, u32) = (month.parse().ok()?, day.parse().ok()?);
+    let days = days_from_civil(year, month, day);
+    // 2023-02-30 comes back as an other day
+    if civil_from_days(days) != (year, month, day) || days < -25_567 {
+        return None;
+    }
+    // 1900-03-01 and later are one further for the 1900-02-29 that never was
+    let mut serial = match days < -25_508 {
+        true => days + 25_568,
+        false => days + 25_569,
+    } as f64;
+    if let Some(time) = time {
+        let mut parts = time.splitn(3, ':');
+        let (hours, minutes) = (parts.next()?, parts.next()?);
+        let seconds = parts.next().unwrap_or("00");
+        if [hours, minutes, seconds].iter().any(|p| p.len() != 2) {
+            return None;
+        }
+        let (hours, minutes, seconds): (u32, u32, u32) = (
+            hours.parse().ok()?,
+            minutes.parse().ok()?,
+            seconds.parse().ok()?,
+        );
+        if hours > 23 || minutes > 59 || seconds > 59 {
+            return None;
+        }
+        serial += (hours * 3600 + minutes * 60 + seconds) as f64 / 86_400.0;
+    }
+    Some((serial, time.is_some()))
+}
+
+static MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+static WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// a number shown the way a format code shows it, eg. `1234.5` with `#,##0.00` is
+/// `1,234.50` and `45000` with `dd/mm/yyyy` is `15/03/2023`. the section for negative
+/// numbers and zero is used, colors and conditions are left out.
+pub fn format_number(value: f64, format_code: &str, date_1904: bool) -> String {
+    let sections = split_sections(format_code);
+    let (section, value) = match sections.len() {
+        2.. if value < 0.0 => (sections[1], -value),
+        3.. if value == 0.0 => (sections[2], value),
+        _ => (sections[0], value),
+    };
+    if section.is_empty() || section.eq_ignore_ascii_case("general") {
+        return format_general(value);
+    }
+    if is_date_format(section) {
+        if let Some(text) = format_date(value, section, date_1904) {
+            return text;
+        }
+        return format_general(value);
+    }
+    format_decimal(value, section)
+}
+
+// the sections of a format code split on `;` outside quotes
+fn split_sections(format_code: &str) -> Vec<&str> {
+    let mut sections = Vec::new();
+    let (mut start, mut in_quotes, mut escaped) = (0, false, false);
+    for (i, c) in format_code.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                sections.push(&format_code[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    sections.push(&format_code[start..]);
+    sections
+}
+
+// like General, at most 15 significant digits
+fn format_general(value: f64) -> String {
+    let rounded: f64 = format!("{:.14e}", value).parse().unwrap_or(value);
+    rounded.to_string()
+}
+
+// the text of a format code and where the number goes in it
+enum Piece {
+    Text(String),
+    Number,
+}
+
+fn format_decimal(value: f64, section: &str) -> String {
+    let mut pieces = Vec::new();
+    // the digit placeholders, `,`, `.` and the exponent
+    let mut pattern = String::new();
+    let mut percents = 0;
+    let mut chars = section.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let text: String = chars.by_ref().take_while(|c| *c != '"').collect();
+                pieces.push(Piece::Text(text));
+            }
+            '\\' => pieces.extend(chars.next().map(|c| Piece::Text(c.to_string()))),
+            // the width of the next character, and a fill
+            '_' => {
+                chars.next();
+                pieces.push(Piece::Text(" ".to_string()));
+            }
+            '*' => {
+                chars.next();
+            }
+            // colors and conditions
+            '[' => {
+                chars.by_ref().take_while(|c| *c != ']').for_each(drop);
+            }
+            '0' | '#' | '?' | '.' | ',' => {
+                if pattern.is_empty() {
+                    pieces.push(Piece::Number);
+                }
+                pattern.push(c);
+            }
+            'E' | 'e' if !pattern.is_empty() && matches!(chars.peek(), Some('+' | '-')) => {
+                pattern.push('E');
+                pattern.extend(chars.next());
+                while let Some(c) = chars.next_if(|c| matches!(c, '0' | '#' | '?')) {
+                    pattern.push(c);
+                }
+            }
+            '%' => {
+                percents += 1;
+                pieces.push(Piece::Text("%".to_string()));
+            }
+            _ => pieces.push(Piece::Text(c.to_string())),
+        }
+    }
+    let value = value * 100f64.powi(percents);
+    let number = match pattern.is_empty() {
+        true => String::new(),
+        false => format_pattern(value, &pattern),
+    };
+    let mut text = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(t) => text.push_str(&t),
+            Piece::Number => text.push_str(&number),
+        }
+    }
+    text
+}
+
+// a number by its digit placeholders, eg. `#,##0.00` or `0.0E+00`
+fn format_pattern(value: f64, pattern: &str) -> String {
+    let (mantissa, exponent) = match pattern.split_once('E') {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (pattern, None),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let is_digit = |c: &char| matches!(c, '0' | '#' | '?');
+    let decimals = fraction.chars().filter(is_digit).count();
+    let min_decimals = fraction.chars().filter(|c| *c == '0').count();
+    let min_digits = integer.chars().filter(|c| *c == '0').count();
+    // commas after the last digit scale by a thousand, the others group the digits
+    let scaling = integer.len() - integer.trim_end_matches(',').len();
+    let grouping = integer.trim_end_matches(',').contains(',');
+
+    let mut value = value / 1000f64.powi(scaling as i32);
+    let mut exponent_text = String::new();
+    if let Some(exponent) = exponent {
+        let mut power = match value {
+            0.0 => 0,
+            _ => value.abs().log10().floor() as i32,
+        };
+        value /= 10f64.powi(power);
+        // 9.99 rounded to one decimal is 10.0
+        if format!("{:.*}", decimals, value.abs()).starts_with("10") {
+            value /= 10.0;
+            power += 1;
+        }
+        let sign = match (power < 0, exponent.starts_with('+')) {
+            (true, _) => "-",
+            (false, true) => "+",
+            (false, false) => "",
+        };
+        let width = exponent.chars().filter(|c| *c == '0').count();
+        exponent_text = format!("E{}{:0width$}", sign, power.abs(), width = width);
+    }
+
+    let rounded = format!("{:.*}", decimals, value.abs());
+    let (digits, fraction) = rounded.split_once('.').unwrap_or((&rounded, ""));
+    let fraction = fraction.trim_end_matches('0');
+    let fraction = format!("{:0<width$}", fraction, width = min_decimals);
+    let digits = digits.trim_start_matches('0');
+    let digits = format!("{:0>width$}", digits, width = min_digits);
+    let digits = match grouping {
+        true => group_thousands(&digits),
+        false => digits,
+    };
+
+    // a negative number in a format without a section for it keeps its sign
+    let mut text = String::new();
+    if value < 0.0 && (digits.chars().chain(fraction.chars())).any(|c| c != '0') {
+        text.push('-');
+    }
+    text.push_str(&digits);
+    if mantissa.contains('.') {
+        text.push('.');
+        text.push_str(&fraction);
+    }
+    text.push_str(&exponent_text);
+    text
+}
+
+// `1234567` as `1,234,567`
+fn group_thousands(digits: &str) -> String {
+    let mut text = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            text.push(',');
+        }
+        text.push(c);
+    }
+    text
+}
+
+// the parts of a date format, a month and a minute are both `m`
+enum DatePart {
+    Text(String),
+    Year(usize),
+    Month(usize),
+    Minute(usize),
+    Day(usize),
+    Hour(usize),
+    Second(usize),
+    // `[h]`, `[mm]` or `[ss]`
+    Elapsed(char, usize),
+    // digits of a second after `ss.`
+    SubSecond(usize),
+    // `AM/PM` or `A/P`
+    AmPm(bool),
+}
+
+fn format_date(value: f64, section: &str, date_1904: bool) -> Option<String> {
+    let parts = date_parts(section);
+    let twelve_hours = parts.iter().any(|p| matches!(p, DatePart::AmPm(_)));
+    // the time with the precision of a millisecond
+    let total = (value * 86_400_000.0).round() as i64;
+    let (days, millis) = (total / 86_400_000, total % 86_400_000);
+    let (date, _) = serial_to_parts(days as f64, date_1904)?;
+    // 0 is the day before 1900-01-01, 1904-01-01 in 1904 dates
+    let (year, month, day) = date.unwrap_or(match date_1904 {
+        true => (1904, 1, 1),
+        false => (1900, 1, 0),
+    });
+    let weekday = match date {
+        Some((year, month, day)) => (days_from_civil(year, month, day) + 4).rem_euclid(7),
+        None => 6,
+    } as usize;
+    let (seconds, millis) = (millis / 1000, millis % 1000);
+    let hour = seconds / 3600;
+
+    let mut text = String::new();
+    for part in parts {
+        match part {
+            DatePart::Text(t) => text.push_str(&t),
+            DatePart::Year(n) if n <= 2 => text.push_str(&format!("{:02}", year % 100)),
+            DatePart::Year(_) => text.push_str(&format!("{:04}", year)),
+            DatePart::Month(1) => text.push_str(&month.to_string()),
+            DatePart::Month(2) => text.push_str(&format!("{:02}", month)),
+            DatePart::Month(3) => text.push_str(&MONTHS[month as usize - 1][..3]),
+            DatePart::Month(4) => text.push_str(MONTHS[month as usize - 1]),
+            DatePart::Month(_) => text.push_str(&MONTHS[month as usize - 1][..1]),
+            DatePart::Day(1) => text.push_str(&day.to_string()),
+            DatePart::Day(2) => text.push_str(&format!("{:02}", day)),
+            DatePart::Day(3) => text.push_str(&WEEKDAYS[weekday][..3]),
+            DatePart::Day(_) => text.push_str(WEEKDAYS[weekday]),
+            DatePart::Hour(n) => {
+                let hour = match (twelve_hours, hour % 12) {
+                    (true, 0) => 12,
+                    (true, h) => h,
+                    (false, _) => hour,
+                };
+                text.push_str(&format!("{:0width$}", hour, width = n.min(2)));
+            }
+            DatePart::Minute(n) => {
+                let minute = (seconds % 3600) / 60;
+                text.push_str(&format!("{:0width$}", minute, width = n.min(2)));
+            }
+            DatePart::Second(n) => {
+                text.push_str(&format!("{:0width$}", seconds % 60, width = n.min(2)))
+            }
+            DatePart::SubSecond(n) => {
+                let digits = format!("{:03}", millis);
+                text.push_str(&digits[..n.min(3)]);
+            }
+            DatePart::Elapsed(unit, n) => {
+                let total = days * 86_400 + seconds;
+                let elapsed = match unit {
+                    'h' => total / 3600,
+                    'm' => total / 60,
+                    _ => total,
+                };
+                text.push_str(&format!("{:0width$}", elapsed, width = n));
+            }
+            DatePart::AmPm(long) => {
+                let am = hour < 12;
+                text.push_str(match (long, am) {
+                    (true, true) => "AM",
+                    (true, false) => "PM",
+                    (false, true) => "A",
+                    (false, false) => "P",
+                });
+            }
+        }
+    }
+    Some(text)
+}
+
+// the parts of a date format code, `m` next to an hour or a second is a minute
+fn date_parts(section: &str) -> Vec<DatePart> {
+    let mut parts = Vec::new();
+    let chars: Vec<char> = section.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let lower = c.to_ascii_lowercase();
+        // the length of a run of this letter
+        let run = chars[i..]
+            .iter()
+            .take_while(|x| x.to_ascii_lowercase() == lower)
+            .count();
+        let rest: String = chars[i..].iter().collect();
+        match lower {
+            '"' => {
+                let text: String = chars[i + 1..].iter().take_while(|c| **c != '"').collect();
+                i += text.chars().count() + 2;
+                parts.push(DatePart::Text(text));
+                continue;
+            }
+            '\\' => {
+                parts.extend(chars.get(i + 1).map(|c| DatePart::Text(c.to_string())));
+                i += 2;
+                continue;
+            }
+            '_' => {
+                parts.push(DatePart::Text(" ".to_string()));
+                i += 2;
+                continue;
+            }
+            '*' => {
+                i += 2;
+                continue;
+            }
+            '[' => {
+                let inner: String = chars[i + 1..].iter().take_while(|c| **c != ']').collect();
+                i += inner.chars().count() + 2;
+                let unit = inner.chars().next().map(|c| c.to_ascii_lowercase());
+                if let Some(unit @ ('h' | 'm' | 's')) = unit {
+                    if inner.chars().all(|c| c.to_ascii_lowercase() == unit) {
+                        parts.push(DatePart::Elapsed(unit, inner.len()));
+                    }
+                }
+                continue;
+            }
+            _ if rest.to_ascii_uppercase().starts_with("AM/PM") => {
+                parts.push(DatePart::AmPm(true));
+                i += 5;
+                continue;
+            }
+            _ if rest.to_ascii_uppercase().starts_with("A/P") => {
+                parts.push(DatePart::AmPm(false));
+                i += 3;
+                continue;
+            }
+            'y' => parts.push(DatePart::Year(run)),
+            'm' => parts.push(DatePart::Month(run)),
+            'd' => parts.push(DatePart::Day(run)),
+            'h' => parts.push(DatePart::Hour(run)),
+            's' => parts.push(DatePart::Second(run)),
+            '0' if matches!(parts.last(), Some(DatePart::Text(t)) if t == ".") => {
+                parts.push(DatePart::SubSecond(run))
+            }
+            _ => {
+                parts.push(DatePart::Text(c.to_string()));
+                i += 1;
+                continue;
+            }
+        }
+        i += run;
+    }
+
+    // `m` or `mm` after an hour or before a second is a minute
+    let time_before: Vec<bool> = parts
+        .iter()
+        .scan(false, |hour, part| {
+            let before = *hour;
+            match part {
+                DatePart::Hour(_) | DatePart::Elapsed('h', _) => *hour = true,
+                DatePart::Text(_) => {}
+                _ => *hour = false,
+            }
+            Some(before)
+        })
+        .collect();
+    let mut second_after = false;
+    let mut minutes = vec![false; parts.len()];
+    for (i, part) in parts.iter().enumerate().rev() {
+        match part {
+            DatePart::Month(n) if *n <= 2 => {
+                minutes[i] = time_before[i] || second_after;
+                second_after = false;
+            }
+            DatePart::Second(_) | DatePart::Elapsed('s', _) => second_after = true,
+            DatePart::Text(_) | DatePart::SubSecond(_) => {}
+            _ => second_after = false,
+        }
+    }
+    parts
+        .into_iter()
+        .zip(minutes)
+        .map(|(part, minute)| match (part, minute) {
+            (DatePart::Month(n), true) => DatePart::Minute(n),
+            (part, _) => part,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_formatted() {
+        assert_eq!(format_number(1234.5, "#,##0.00", false), "1,234.50");
+        assert_eq!(format_number(1234567.0, "#,##0", false), "1,234,567");
+        assert_eq!(format_number(0.125, "0.0%", false), "12.5%");
+        assert_eq!(format_number(12345.678, "0.00E+00", false), "1.23E+04");
+        assert_eq!(format_number(0.000123, "0.00E+00", false), "1.23E-04");
+        assert_eq!(format_number(2.5, "General", false), "2.5");
+    }
+
+    #[test]
+    fn negative_numbers_and_zero_use_their_sections() {
+        assert_eq!(format_number(-5.0, "0", false), "-5");
+        assert_eq!(
+            format_number(-1234.5, "#,##0.00;(#,##0.00)", false),
+            "(1,234.50)"
+        );
+        assert_eq!(
+            format_number(1234.5, "#,##0.00;(#,##0.00)", false),
+            "1,234.50"
+        );
+        assert_eq!(format_number(0.0, "0.00;-0.00;\"-\"", false), "-");
+        assert_eq!(format_number(-2.0, "0.00;[Red]-0.00;\"-\"", false), "-2.00");
+    }
+
+    #[test]
+    fn m_is_a_month_or_a_minute() {
+        // 2023-03-15 13:05:30
+        let serial = 45000.0 + (13.0 * 3600.0 + 5.0 * 60.0 + 30.0) / 86_400.0;
+        assert_eq!(format_number(serial, "m/d/yy", false), "3/15/23");
+        assert_eq!(
+            format_number(serial, "yyyy-mm-dd hh:mm", false),
+            "2023-03-15 13:05"
+        );
+        assert_eq!(format_number(serial, "h:mm", false), "13:05");
+        assert_eq!(format_number(serial, "mm:ss", false), "05:30");
+        assert_eq!(format_number(serial, "mmm d", false), "Mar 15");
+        assert_eq!(format_number(serial, "mmmm", false), "March");
+    }
+
+    #[test]
+    fn elapsed_hours_and_twelve_hour_clocks() {
+        assert_eq!(format_number(1.5, "[h]:mm", false), "36:00");
+        assert_eq!(format_number(0.25, "[h]:mm:ss", false), "6:00:00");
+        assert_eq!(format_number(45000.75, "h:mm AM/PM", false), "6:00 PM");
+        assert_eq!(format_number(45000.0, "h:mm AM/PM", false), "12:00 AM");
+        assert_eq!(
+            format_number(45000.5, "h:mm:ss AM/PM", false),
+            "12:00:00 PM"
+        );
+    }
+
+    #[test]
+    fn dates_count_from_1904() {
+        assert_eq!(format_number(0.0, "yyyy-mm-dd", true), "1904-01-01");
+        assert_eq!(format_number(43538.0, "yyyy-mm-dd", true), "2023-03-15");
+        assert_eq!(format_number(45000.0, "yyyy-mm-dd", false), "2023-03-15");
+        assert_eq!(
+            serial_to_iso(43538.5, true).as_deref(),
+            Some("2023-03-15T12:00:00")
+        );
+    }
+}
@@ -0,0 +1,77 @@
+// how the package is written: the compression of its entries, zip64 and their dates.
+
+use zip::{write::SimpleFileOptions, CompressionMethod, DateTime};
+
+/// the date of every entry, and of the document when it has none, with
+/// `deterministic_timestamps`; the earliest a zip file can hold.
+pub static FIXED_TIMESTAMP: &str = "1980-01-01T00:00:00Z";
+
+/// how the entries of the package are compressed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    /// not compressed, the fastest to write and the largest file
+    Stored,
+    /// deflate at a level from 1 (fastest) to 9 (smallest), 0 is 1 and higher levels are 9
+    Deflate(u8),
+}
+
+/// options of `WorkBook::save_with_options`
+#[derive(Debug, Clone, Copy)]
+pub struct SaveOptions {
+    pub compression: Compression,
+    /// zip64 entries, needed for a sheet over 4 GB
+    pub large_file: bool,
+    /// entries, threaded comments not given a time and the document when it has no dates
+    /// are dated `FIXED_TIMESTAMP`, so the same work book always gives the same bytes.
+    pub deterministic_timestamps: bool,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        SaveOptions {
+            compression: Compression::Deflate(6),
+            large_file: false,
+            deterministic_timestamps: false,
+        }
+    }
+}
+
+impl SaveOptions {
+    /// the options of every entry of the package
+    pub(crate) fn file_options(&self) -> SimpleFileOptions {
+        let options = match self.compression {
+            Compression::Stored => {
+                SimpleFileOptions::default().compression_method(CompressionMethod::Stored)
+            }
+            Compression::Deflate(level) => SimpleFileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .compression_level(Some(level.clamp(1, 9) as i64)),
+        };
+        let options = options.large_file(self.large_file);
+        match self.deterministic_timestamps {
+            true => options.last_modified_time(DateTime::default()),
+            false => options,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::*;
+
+    #[test]
+    fn every_deflate_level_can_be_written() {
+        for level in [0, 1, 9, 255] {
+            let options = SaveOptions {
+                compression: Compression::Deflate(level),
+                ..Default::default()
+            };
+            let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+            zip.start_file("part.xml", options.file_options()).unwrap();
+            zip.write_all(b"<part/>").unwrap();
+            zip.finish().unwrap();
+        }
+    }
+}
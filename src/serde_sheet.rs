@@ -0,0 +1,611 @@
+// work sheets from records that implement `serde::Serialize`, with the `serde` feature.
+
+use std::fmt::{self, Display};
+
+use serde::{
+    ser::{self, Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeTuple},
+    Serialize, Serializer,
+};
+
+use crate::{
+    cell::{Cell, CellValue},
+    row::{column_number_to_name, ColMovement, Row, MAX_COLUMNS},
+    work_sheet::WorkSheet,
+};
+
+impl WorkSheet {
+    /// a sheet named `name` with a header row of the field names of the records and a row
+    /// for every record below it, see `serialize_rows`.
+    pub fn from_serialize<T: Serialize>(
+        name: &str,
+        records: &[T],
+    ) -> Result<WorkSheet, &'static str> {
+        let mut work_sheet = WorkSheet::blank(name);
+        work_sheet.serialize_rows(records)?;
+        Ok(work_sheet)
+    }
+
+    /// add a row for every record, a struct, a map or a tuple. the field names, as renamed
+    /// with `#[serde(rename)]`, make a header row when the sheet has no rows yet, after
+    /// that the fields go in the column of their name in the first row. numbers and bools
+    /// are written as such, `None` and a field skipped with `skip_serializing_if` leave the
+    /// cell blank, a unit variant of an enum is its name.
+    pub fn serialize_rows<I>(&mut self, records: I) -> Result<(), &'static str>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        let mut headers: Option<Vec<String>> = self.rows.first().map(|row| {
+            let cells = row.get_cells().iter();
+            cells
+                .map(|cell| match &cell.value {
+                    CellValue::CString(text) | CellValue::CInString(text) => text.clone(),
+                    _ => String::new(),
+                })
+                .collect()
+        });
+        for record in records {
+            let mut fields = Fields::default();
+            record.serialize(&mut fields).map_err(|e| e.0)?;
+            let headers = match &headers {
+                Some(headers) => headers,
+                None if fields.named() => {
+                    let names: Vec<String> = fields.0.iter().flat_map(|f| f.0.clone()).collect();
+                    let row = self.add_blank_row();
+                    for name in names.iter() {
+                        row.add_string(name.clone())?;
+                    }
+                    headers.insert(names)
+                }
+                None => headers.insert(vec![]),
+            };
+
+            // the values in the order of their columns
+            let mut values = vec![];
+            for (index, (name, value)) in fields.0.into_iter().enumerate() {
+                let column = match name {
+                    Some(name) => headers
+                        .iter()
+                        .position(|h| *h == name)
+                        .ok_or("Field not in the header")?,
+                    None => index,
+                };
+                if column >= MAX_COLUMNS {
+                    return Err("Too many columns");
+                }
+                if values.len() <= column {
+                    values.resize_with(column + 1, || Value::Empty);
+                }
+                values[column] = value;
+            }
+            let row = self.add_blank_row();
+            for (column, value) in values.into_iter().enumerate() {
+                add_value(row, column + 1, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// add the value as the next cell of the row
+fn add_value(row: &mut Row, column: usize, value: Value) -> Result<(), &'static str> {
+    match value {
+        Value::Text(text) => {
+            row.add_string(text)?;
+        }
+        Value::Number(number) => {
+            row.add_number(number).map_err(|_| "Invalid number")?;
+        }
+        Value::Bool(value) => {
+            let reference = format!("{}{}", column_number_to_name(column), row.get_row_number());
+            row.add_cell(Cell::new(CellValue::CBool(value), reference))?;
+        }
+        Value::Empty => ColMovement::new(row).next(),
+    }
+    Ok(())
+}
+
+// fails with the text of the error, a message of serde is not kept
+#[derive(Debug)]
+struct Error(&'static str);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(_msg: T) -> Self {
+        Error("Could not serialize a record")
+    }
+}
+
+// the value of a field
+enum Value {
+    Text(String),
+    Number(String),
+    Bool(bool),
+    Empty,
+}
+
+// the fields of a record and their names, none for a tuple
+#[derive(Default)]
+struct Fields(Vec<(Option<String>, Value)>, Option<String>);
+
+impl Fields {
+    fn named(&self) -> bool {
+        self.0.iter().any(|f| f.0.is_some())
+    }
+}
+
+static NOT_A_RECORD: &str = "A record must be a struct, a map or a tuple";
+
+impl Serializer for &mut Fields {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<(), Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_i64(self, _v: i64) -> Result<(), Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_u64(self, _v: u64) -> Result<(), Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_f64(self, _v: f64) -> Result<(), Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+    fn serialize_f32(self, _v: f32) -> Result<(), Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_char(self, _v: char) -> Result<(), Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_str(self, _v: &str) -> Result<(), Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_none(self) -> Result<(), Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, Error> {
+        Ok(self)
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, Error> {
+        Ok(self)
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error(NOT_A_RECORD))
+    }
+}
+
+impl SerializeSeq for &mut Fields {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.0.push((None, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl SerializeTuple for &mut Fields {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut Fields {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl SerializeMap for &mut Fields {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        let key = match key.serialize(ValueSerializer)? {
+            Value::Text(key) | Value::Number(key) => key,
+            _ => return Err(Error("A key must be a string or a number")),
+        };
+        self.1 = Some(key);
+        Ok(())
+    }
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.1.take();
+        self.0.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl SerializeStruct for &mut Fields {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.0
+            .push((Some(key.to_string()), value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+    // keeps the column of a field left out with `skip_serializing_if`
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Error> {
+        self.0.push((Some(key.to_string()), Value::Empty));
+        Ok(())
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+// the value of one field, a nested struct, map or sequence does not fit in a cell
+struct ValueSerializer;
+
+static NESTED: &str = "A field must not be a struct, a map or a sequence";
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = Impossible<Value, Error>;
+    type SerializeTuple = Impossible<Value, Error>;
+    type SerializeTupleStruct = Impossible<Value, Error>;
+    type SerializeTupleVariant = Impossible<Value, Error>;
+    type SerializeMap = Impossible<Value, Error>;
+    type SerializeStruct = Impossible<Value, Error>;
+    type SerializeStructVariant = Impossible<Value, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(Value::Bool(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        Ok(Value::Number(v.to_string()))
+    }
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        Ok(Value::Number(v.to_string()))
+    }
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        Ok(Value::Number(v.to_string()))
+    }
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(Value::Number(v.to_string()))
+    }
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        Ok(Value::Number(v.to_string()))
+    }
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        Ok(Value::Number(v.to_string()))
+    }
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        Ok(Value::Number(v.to_string()))
+    }
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        Ok(Value::Number(v.to_string()))
+    }
+    // infinity and NaN have no cell, an f32 is written with its own digits, `0.1` and
+    // not `0.10000000149011612`
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        match v.is_finite() {
+            true => Ok(Value::Number(v.to_string())),
+            false => Err(Error("Invalid number")),
+        }
+    }
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        match v.is_finite() {
+            true => Ok(Value::Number(v.to_string())),
+            false => Err(Error("Invalid number")),
+        }
+    }
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        Ok(Value::Text(v.to_string()))
+    }
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::Text(v.to_string()))
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Value, Error> {
+        Err(Error(NESTED))
+    }
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::Empty)
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::Empty)
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+        Ok(Value::Empty)
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        Ok(Value::Text(variant.to_string()))
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Error(NESTED))
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(Error(NESTED))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Error(NESTED))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error(NESTED))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Error(NESTED))
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(Error(NESTED))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error(NESTED))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Record {
+        name: String,
+        #[serde(rename = "Amount")]
+        amount: f32,
+        note: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tag: Option<bool>,
+    }
+
+    // the text of every cell of a row by its reference
+    fn cells(row: &Row) -> Vec<(String, String)> {
+        let cells = row.get_cells().iter();
+        cells
+            .map(|cell| {
+                let text = match &cell.value {
+                    CellValue::CString(v) | CellValue::CNumber(v) => v.clone(),
+                    CellValue::CBool(v) => v.to_string(),
+                    _ => String::new(),
+                };
+                (cell.get_attributes().reference.clone().unwrap(), text)
+            })
+            .collect()
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        let pairs = pairs.iter();
+        pairs.map(|(r, v)| (r.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn field_names_make_the_header() {
+        let records = [
+            Record {
+                name: "a".to_string(),
+                amount: 0.1,
+                note: None,
+                tag: Some(true),
+            },
+            Record {
+                name: "b".to_string(),
+                amount: 2.5,
+                note: Some("n".to_string()),
+                tag: None,
+            },
+        ];
+        let work_sheet = WorkSheet::from_serialize("Records", &records).unwrap();
+        assert_eq!(work_sheet.rows.len(), 3);
+        assert_eq!(
+            cells(&work_sheet.rows[0]),
+            pairs(&[
+                ("A1", "name"),
+                ("B1", "Amount"),
+                ("C1", "note"),
+                ("D1", "tag")
+            ])
+        );
+        // None leaves the cell blank, an f32 keeps its own digits
+        assert_eq!(
+            cells(&work_sheet.rows[1]),
+            pairs(&[("A2", "a"), ("B2", "0.1"), ("D2", "true")])
+        );
+        // a skipped field keeps its column
+        assert_eq!(
+            cells(&work_sheet.rows[2]),
+            pairs(&[("A3", "b"), ("B3", "2.5"), ("C3", "n")])
+        );
+    }
+
+    #[test]
+    fn fields_go_in_the_column_of_their_name() {
+        let mut work_sheet = WorkSheet::blank("Maps");
+        let row = work_sheet.add_blank_row();
+        row.add_string("b".to_string()).unwrap();
+        row.add_string("a".to_string()).unwrap();
+        let record = BTreeMap::from([("a", 1), ("b", 2)]);
+        work_sheet.serialize_rows([&record]).unwrap();
+        assert_eq!(
+            cells(&work_sheet.rows[1]),
+            pairs(&[("A2", "2"), ("B2", "1")])
+        );
+
+        let record = BTreeMap::from([("c", 3)]);
+        assert_eq!(
+            work_sheet.serialize_rows([&record]),
+            Err("Field not in the header")
+        );
+    }
+
+    #[test]
+    fn tuples_have_no_header() {
+        let work_sheet = WorkSheet::from_serialize("Tuples", &[("x", 1), ("y", 2)]).unwrap();
+        assert_eq!(work_sheet.rows.len(), 2);
+        assert_eq!(
+            cells(&work_sheet.rows[0]),
+            pairs(&[("A1", "x"), ("B1", "1")])
+        );
+        assert_eq!(
+            WorkSheet::from_serialize("Numbers", &[1, 2]).err(),
+            Some(NOT_A_RECORD)
+        );
+        assert_eq!(
+            WorkSheet::from_serialize("Floats", &[(f32::NAN,)]).err(),
+            Some("Invalid number")
+        );
+    }
+}
@@ -0,0 +1,230 @@
+// sparklines, small charts in a cell. excel 2010 keeps them in the x14 extension list of the sheet.
+
+use xmlwriter::XmlWriter;
+
+use crate::{
+    defined_name::quote_sheet_name, escape::escape_xml, package::PreservedElement,
+    page_setup::absolute_range, row::parse_cell_ref,
+};
+
+static X14_XMLNS: &str = "http://schemas.microsoft.com/office/spreadsheetml/2009/9/main";
+static XM_XMLNS: &str = "http://schemas.microsoft.com/office/excel/2006/main";
+// the extension holding sparkline groups
+static SPARKLINE_EXT_URI: &str = "{05C60535-1F16-4fd2-B633-F4F36F0B64E0}";
+
+// colors of excel's first sparkline style
+static SERIES_COLOR: &str = "FF376092";
+static HIGHLIGHT_COLOR: &str = "FFD00000";
+static AXIS_COLOR: &str = "FF000000";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SparklineType {
+    #[default]
+    Line,
+    Column,
+    WinLoss,
+}
+
+/// how a sparkline looks, colors are ARGB hex, eg. `FF376092`
+#[derive(Debug, Clone, Default)]
+pub struct SparklineOptions {
+    pub kind: SparklineType,
+    pub markers: bool,
+    pub high_point: bool,
+    pub low_point: bool,
+    pub first_point: bool,
+    pub last_point: bool,
+    pub negative_points: bool,
+    pub series_color: Option<String>,
+    pub negative_color: Option<String>,
+    pub markers_color: Option<String>,
+    pub high_color: Option<String>,
+    pub low_color: Option<String>,
+}
+
+// eg. FF376092
+fn is_argb(color: &str) -> bool {
+    color.len() == 8 && color.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[derive(Debug, Clone)]
+pub struct Sparkline {
+    // the cell showing the sparkline
+    reference: String,
+    // range of the data, without a sheet it is on the sheet of the sparkline
+    data_range: String,
+    options: SparklineOptions,
+}
+
+impl Sparkline {
+    pub fn new(
+        cell_ref: &str,
+        data_range: &str,
+        options: SparklineOptions,
+    ) -> Result<Self, &'static str> {
+        parse_cell_ref(cell_ref)?;
+        let data_range = data_range.trim_start_matches('=');
+        let invalid = "Invalid sparkline data range";
+        let range = data_range
+            .rsplit_once('!')
+            .map_or(data_range, |(_, range)| range);
+        let absolute = absolute_range(range).map_err(|_| invalid)?;
+        // the values of one sparkline are a row or a column
+        let ends: Vec<(usize, usize)> = range
+            .split(':')
+            .map(parse_cell_ref)
+            .collect::<Result<_, _>>()?;
+        if let [(first_row, first_column), (last_row, last_column)] = ends[..] {
+            if first_row != last_row && first_column != last_column {
+                return Err("Sparkline data must be one row or one column");
+            }
+        }
+        let colors = [
+            &options.series_color,
+            &options.negative_color,
+            &options.markers_color,
+            &options.high_color,
+            &options.low_color,
+        ];
+        if !colors.into_iter().flatten().all(|color| is_argb(color)) {
+            return Err("Sparkline colors must be ARGB hex, eg. FF376092");
+        }
+        let data_range = match data_range.contains('!') {
+            true => data_range.to_string(),
+            false => absolute,
+        };
+        Ok(Sparkline {
+            reference: cell_ref.replace('$', ""),
+            data_range,
+            options,
+        })
+    }
+
+    fn to_xml(&self, writer: &mut XmlWriter, sheet_name: &str) {
+        let options = &self.options;
+        writer.start_element("x14:sparklineGroup");
+        match options.kind {
+            SparklineType::Line => {}
+            SparklineType::Column => writer.write_attribute("type", "column"),
+            SparklineType::WinLoss => writer.write_attribute("type", "stacked"),
+        }
+        writer.write_attribute("displayEmptyCellsAs", "gap");
+        for (name, on) in [
+            ("markers", options.markers),
+            ("high", options.high_point),
+            ("low", options.low_point),
+            ("first", options.first_point),
+            ("last", options.last_point),
+            ("negative", options.negative_points),
+        ] {
+            if on {
+                writer.write_attribute(name, "1");
+            }
+        }
+
+        let color = |color: &Option<String>, default: &'static str| {
+            color.clone().unwrap_or_else(|| default.to_string())
+        };
+        for (name, rgb) in [
+            (
+                "x14:colorSeries",
+                color(&options.series_color, SERIES_COLOR),
+            ),
+            (
+                "x14:colorNegative",
+                color(&options.negative_color, HIGHLIGHT_COLOR),
+            ),
+            ("x14:colorAxis", AXIS_COLOR.to_string()),
+            (
+                "x14:colorMarkers",
+                color(&options.markers_color, HIGHLIGHT_COLOR),
+            ),
+            ("x14:colorFirst", HIGHLIGHT_COLOR.to_string()),
+            ("x14:colorLast", HIGHLIGHT_COLOR.to_string()),
+            ("x14:colorHigh", color(&options.high_color, HIGHLIGHT_COLOR)),
+            ("x14:colorLow", color(&options.low_color, HIGHLIGHT_COLOR)),
+        ] {
+            writer.start_element(name);
+            writer.write_attribute("rgb", &rgb);
+            writer.end_element();
+        }
+
+        // the data range always carries its sheet
+        let formula = match self.data_range.rsplit_once('!') {
+            Some(_) => self.data_range.clone(),
+            None => format!("{}!{}", quote_sheet_name(sheet_name), self.data_range),
+        };
+        writer.start_element("x14:sparklines");
+        writer.start_element("x14:sparkline");
+        writer.start_element("xm:f");
+        writer.write_text(&escape_xml(&formula));
+        writer.end_element();
+        writer.start_element("xm:sqref");
+        writer.write_text(&self.reference);
+        writer.end_element();
+        writer.end_element();
+        writer.end_element();
+
+        writer.end_element();
+    }
+}
+
+/// writes the `<extLst>` of a sheet with its sparklines and the extensions kept from a read
+/// sheet, nothing when there are none. the sparklines replace the kept ones.
+pub fn write_sparklines(
+    writer: &mut XmlWriter,
+    sheet_name: &str,
+    sparklines: &[Sparkline],
+    ext_list: Option<&PreservedElement>,
+) {
+    let kept: Vec<&PreservedElement> = ext_list
+        .iter()
+        .flat_map(|ext_list| ext_list.elements())
+        .filter(|ext| sparklines.is_empty() || ext.attribute("uri") != Some(SPARKLINE_EXT_URI))
+        .collect();
+    if sparklines.is_empty() && kept.is_empty() {
+        return;
+    }
+    writer.start_element("extLst");
+    for ext in kept {
+        ext.write(writer);
+    }
+    if !sparklines.is_empty() {
+        writer.start_element("ext");
+        writer.write_attribute("uri", SPARKLINE_EXT_URI);
+        writer.write_attribute("xmlns:x14", X14_XMLNS);
+        writer.start_element("x14:sparklineGroups");
+        writer.write_attribute("xmlns:xm", XM_XMLNS);
+        for sparkline in sparklines {
+            sparkline.to_xml(writer, sheet_name);
+        }
+        writer.end_element();
+        writer.end_element();
+    }
+    writer.end_element();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_ranges_are_one_row_or_one_column() {
+        for range in ["A1:A10", "B2:H2", "'sheet 1'!C3", "Data!$A$1:$F$1"] {
+            assert!(Sparkline::new("J1", range, SparklineOptions::default()).is_ok());
+        }
+        assert!(Sparkline::new("J1", "A1:B10", SparklineOptions::default()).is_err());
+    }
+
+    #[test]
+    fn colors_are_argb() {
+        let options = |color: &str| SparklineOptions {
+            high_color: Some(color.to_string()),
+            ..Default::default()
+        };
+        assert!(Sparkline::new("J1", "A1:A10", options("FF376092")).is_ok());
+        for color in ["376092", "red", "FF37609G", ""] {
+            assert!(Sparkline::new("J1", "A1:A10", options(color)).is_err());
+        }
+    }
+}
@@ -0,0 +1,202 @@
+// a work sheet written row by row, for sheets too large to hold in memory.
+
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use xmlwriter::{Indent, Options, XmlWriter};
+
+use crate::{
+    row::{Row, MAX_ROWS},
+    traits::XMLString,
+    work_book::register_row,
+    work_sheet::WorkSheet,
+    xml_templates::{shared_string::SharedStrings, style::Style},
+};
+
+// numbers the temporary files of one process
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+/// the rows of a streamed sheet as `<row>` elements in a temporary file, removed with it.
+#[derive(Debug)]
+pub(crate) struct StreamedRows {
+    path: PathBuf,
+}
+
+impl StreamedRows {
+    fn create() -> Result<(Self, File), &'static str> {
+        let path = std::env::temp_dir().join(format!(
+            "xml1-{}-{}.rows",
+            std::process::id(),
+            NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = File::create(&path).map_err(|_| "Could not create a temporary file")?;
+        Ok((StreamedRows { path }, file))
+    }
+
+    /// write the sheet xml with these rows in its `<sheetData/>`, copied from the file.
+    pub(crate) fn write_sheet<W: Write>(
+        &self,
+        sheet_xml: &str,
+        out: &mut W,
+    ) -> Result<(), &'static str> {
+        // every text of the sheet is escaped, the first one is the sheet data
+        let (head, tail) = sheet_xml
+            .split_once("<sheetData/>")
+            .ok_or("Missing sheet data")?;
+        let mut rows = File::open(&self.path).map_err(|_| "Could not read a temporary file")?;
+        write_around(out, head, &mut rows, tail).map_err(|_| "Could not write the sheet")
+    }
+}
+
+fn write_around<W: Write>(out: &mut W, head: &str, rows: &mut File, tail: &str) -> io::Result<()> {
+    out.write_all(head.as_bytes())?;
+    out.write_all(b"<sheetData>")?;
+    io::copy(rows, out)?;
+    out.write_all(b"</sheetData>")?;
+    out.write_all(tail.as_bytes())
+}
+
+impl Drop for StreamedRows {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// a work sheet whose rows are written out as they are added, so memory stays the same for
+/// any number of rows. made with `WorkBook::add_streaming_sheet`; rows are added top to
+/// bottom like on a `WorkSheet`, and a row can not change once the next one is started.
+/// strings and styles go to the work book right away.
+pub struct StreamingWorkSheet<'a> {
+    work_sheet: &'a mut WorkSheet,
+    shared_string: &'a mut SharedStrings,
+    style: &'a mut Style,
+    file: BufWriter<File>,
+    // the row being filled, written when the next one starts
+    row: Option<Row>,
+    next_row: usize,
+}
+
+impl<'a> StreamingWorkSheet<'a> {
+    /// stream the rows of `work_sheet`, a sheet just added to the work book.
+    pub(crate) fn new(
+        work_sheet: &'a mut WorkSheet,
+        shared_string: &'a mut SharedStrings,
+        style: &'a mut Style,
+    ) -> Result<Self, &'static str> {
+        let (rows, file) = StreamedRows::create()?;
+        work_sheet.stream_rows(rows);
+        Ok(StreamingWorkSheet {
+            work_sheet,
+            shared_string,
+            style,
+            file: BufWriter::new(file),
+            row: None,
+            next_row: 1,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.work_sheet.name
+    }
+
+    /// write the row filled so far and start a new one below it.
+    pub fn add_blank_row(&mut self) -> Result<&mut Row, &'static str> {
+        self.write_row()?;
+        let row = self.row.insert(Row::new(self.next_row));
+        self.next_row += 1;
+        Ok(row)
+    }
+
+    /// a row with the given number (1 based), it must come after the rows added so far.
+    pub fn add_row(&mut self, row_number: usize) -> Result<&mut Row, &'static str> {
+        if row_number < self.next_row || row_number > MAX_ROWS {
+            return Err("Invalid row number");
+        }
+        self.next_row = row_number;
+        self.add_blank_row()
+    }
+
+    /// write the last row, the sheet is saved with the work book.
+    pub fn finish(mut self) -> Result<(), &'static str> {
+        self.write_row()?;
+        self.file
+            .flush()
+            .map_err(|_| "Could not write a temporary file")
+    }
+
+    fn write_row(&mut self) -> Result<(), &'static str> {
+        let Some(mut row) = self.row.take() else {
+            return Ok(());
+        };
+        // hyperlinks of the cells belong to the sheet.
+        let mut hyperlink_refs = HashSet::new();
+        for cell in row.get_cells_mut().iter_mut() {
+            if let Some(hyperlink) = cell.take_hyperlink() {
+                hyperlink_refs.insert(hyperlink.reference.clone());
+                self.work_sheet.push_hyperlink(hyperlink);
+            }
+        }
+        register_row(&mut row, &hyperlink_refs, self.shared_string, self.style);
+
+        let mut writer = XmlWriter::new(Options {
+            indent: Indent::None,
+            ..Default::default()
+        });
+        row.to_xml(&mut writer);
+        self.file
+            .write_all(writer.end_document().as_bytes())
+            .map_err(|_| "Could not write a temporary file")
+    }
+}
+
+impl Drop for StreamingWorkSheet<'_> {
+    // a sheet dropped without `finish` keeps its last row, errors are left out.
+    fn drop(&mut self) {
+        let _ = self.write_row();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::work_book::WorkBook;
+
+    #[test]
+    fn streamed_rows_are_spliced_in_to_the_sheet_data() {
+        let mut work_book = WorkBook::new();
+        let mut sheet = work_book.add_streaming_sheet("Stream").unwrap();
+        for i in 1..=3 {
+            let row = sheet.add_blank_row().unwrap();
+            row.add_number(i.to_string()).unwrap();
+        }
+        let row = sheet.add_row(5).unwrap();
+        row.add_string("last".to_string()).unwrap();
+        sheet.finish().unwrap();
+
+        let mut work_sheet = work_book.work_sheets.pop().unwrap();
+        let rows = work_sheet.take_streamed_rows().unwrap();
+        let mut out = vec![];
+        rows.write_sheet(&work_sheet.to_xml(), &mut out).unwrap();
+        let xml = String::from_utf8(out).unwrap();
+        let sheet_data = xml
+            .split_once("<sheetData>")
+            .and_then(|(_, rest)| rest.split_once("</sheetData>"))
+            .map(|(data, _)| data)
+            .unwrap();
+        assert_eq!(
+            sheet_data,
+            concat!(
+                r#"<row r="1"><c r="A1" t="n"><v>1</v></c></row>"#,
+                r#"<row r="2"><c r="A2" t="n"><v>2</v></c></row>"#,
+                r#"<row r="3"><c r="A3" t="n"><v>3</v></c></row>"#,
+                r#"<row r="5"><c r="A5" t="s"><v>0</v></c></row>"#,
+            )
+        );
+        assert!(!xml.contains("<sheetData/>"));
+        assert!(xml.ends_with("</worksheet>"));
+    }
+}
@@ -1,29 +1,114 @@
 use crate::cell::*;
+use crate::chart::Chart;
+use crate::chart_sheet::ChartSheet;
+use crate::comment::persons_to_xml;
+use crate::defined_name::{BuiltInName, DefinedName};
+use crate::drawing::DrawingNumbers;
+use crate::package::{
+    PreservedElement, PreservedElements, PreservedParts, PreservedRelation, PreservedSheet,
+};
+use crate::pivot_table::{CacheSource, CacheValue, PivotParts, PivotTable};
+use crate::reader::{open_row_stream, read_work_book, ReadSheet, RowStream};
+use crate::rich_text;
+use crate::save_options::{SaveOptions, FIXED_TIMESTAMP};
+use crate::row::{parse_cell_ref, Row};
+use crate::escape::escape_xml;
+use crate::streaming_work_sheet::{StreamedRows, StreamingWorkSheet};
+use crate::traits::XMLString;
 use crate::{
     work_sheet::WorkSheet,
     xml_templates::{
-        content_type::ContentType, relation_ship::RelationShip, shared_string::SharedStrings,
+        content_type::ContentType,
+        doc_props::{CustomProperties, CustomPropertyValue, DocProperties},
+        relation_ship::RelationShip,
+        shared_string::{SharedStrings, StringStrategy},
         style::Style,
     },
 };
-use std::{collections::HashSet, fs::File, io::Write};
-use xmlwriter::{Options, XmlWriter};
-use zip::write::SimpleFileOptions;
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, Cursor, Read, Seek, Write},
+    mem,
+    sync::{mpsc, Mutex},
+    thread,
+};
+use xmlwriter::{Indent, Options, XmlWriter};
+use zip::{
+    result::{ZipError, ZipResult},
+    write::SimpleFileOptions,
+    ZipArchive,
+};
+
+// a tab of the work book, the index in to work_sheets, chart_sheets or preserved_sheets.
+enum SheetKind {
+    WorkSheet(usize),
+    ChartSheet(usize),
+    Preserved(usize),
+}
+
+// the children of <workbook> in the order the schema asks for
+static WORK_BOOK_ELEMENTS: [&str; 19] = [
+    "fileVersion",
+    "fileSharing",
+    "workbookPr",
+    "workbookProtection",
+    "bookViews",
+    "sheets",
+    "functionGroups",
+    "externalReferences",
+    "definedNames",
+    "calcPr",
+    "oleSize",
+    "customWorkbookViews",
+    "pivotCaches",
+    "smartTagPr",
+    "smartTagTypes",
+    "webPublishing",
+    "fileRecoveryPr",
+    "webPublishObjects",
+    "extLst",
+];
 
 pub struct WorkBook {
     pub work_sheets: Vec<WorkSheet>,
+    pub chart_sheets: Vec<ChartSheet>,
+    // the tabs in order, work sheets and chart sheets are numbered apart
+    sheet_order: Vec<SheetKind>,
     pub work_sheet_names: HashSet<String>,
     shared_string: SharedStrings,
     style: Style,
     content_type: ContentType,
     root_relation_ship: RelationShip,
     work_book_relation_ship: RelationShip,
+    doc_properties: DocProperties,
+    custom_properties: CustomProperties,
+    defined_names: Vec<DefinedName>,
+    // authors of threaded comments in every sheet
+    persons: Vec<String>,
+    drawing_numbers: DrawingNumbers,
+    // numbered from 1, a pivot table and its cache share the number
+    pivot_tables: Vec<PivotParts>,
+    // of a read work book, what this crate does not model
+    preserved_sheets: Vec<PreservedSheet>,
+    preserved_parts: PreservedParts,
+    preserved_root_relations: Vec<PreservedRelation>,
+    preserved_work_book_relations: Vec<PreservedRelation>,
+    preserved_elements: PreservedElements,
+}
+
+impl Default for WorkBook {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl WorkBook {
     pub fn new() -> Self {
         WorkBook {
             work_sheets: vec![],
+            chart_sheets: vec![],
+            sheet_order: vec![],
             work_sheet_names: HashSet::new(),
 
             // other xmls
@@ -32,63 +117,464 @@ impl WorkBook {
             content_type: ContentType::new(),
             root_relation_ship: RelationShip::new(),
             work_book_relation_ship: RelationShip::new(),
+            doc_properties: DocProperties::new(),
+            custom_properties: CustomProperties::new(),
+            defined_names: vec![],
+            persons: vec![],
+            drawing_numbers: DrawingNumbers::default(),
+            pivot_tables: vec![],
+            preserved_sheets: vec![],
+            preserved_parts: PreservedParts::new(),
+            preserved_root_relations: vec![],
+            preserved_work_book_relations: vec![],
+            preserved_elements: PreservedElements::default(),
+        }
+    }
+
+    /// load a work book from an xlsx file, see `from_reader`.
+    pub fn open(path: &str) -> Result<Self, &'static str> {
+        let file = File::open(path).map_err(|_| "Could not open the file")?;
+        Self::from_reader(file)
+    }
+
+    /// the rows of a sheet of an xlsx file one at a time, without loading the work book.
+    /// strings and styles are read first, then each row is parsed from the sheet as the
+    /// iterator is advanced; numbers with a date format come back as dates.
+    pub fn stream_rows(path: &str, sheet: &str) -> Result<RowStream, &'static str> {
+        open_row_stream(path, sheet)
+    }
+
+    /// load a work book written by excel or an other tool. the cells come back with their
+    /// text, fonts and number formats; chart sheets, drawings, vba projects and the other parts
+    /// this crate cannot hold are kept as they were and written back on save.
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Self, &'static str> {
+        let read = read_work_book(reader)?;
+        let mut work_book = Self::new();
+        for sheet in read.sheets {
+            match sheet {
+                ReadSheet::WorkSheet(work_sheet) => work_book.add_sheet(*work_sheet),
+                ReadSheet::Preserved(sheet) => {
+                    work_book.work_sheet_names.insert(sheet.name.clone());
+                    work_book
+                        .sheet_order
+                        .push(SheetKind::Preserved(work_book.preserved_sheets.len()));
+                    work_book.preserved_sheets.push(sheet);
+                }
+            }
+        }
+        work_book.defined_names = read.defined_names;
+        work_book
+            .content_type
+            .set_work_book_type(&read.work_book_content_type);
+        work_book.style.preserve(read.styles);
+        work_book.preserved_parts = read.parts;
+        work_book.preserved_root_relations = read.root_relations;
+        work_book.preserved_work_book_relations = read.work_book_relations;
+        work_book.preserved_elements = read.work_book_elements;
+        Ok(work_book)
+    }
+
+    /// define a work book scoped name, eg. `define_name("TaxRate", "=Settings!$B$2")`
+    pub fn define_name(&mut self, name: &str, formula: &str) -> Result<(), &'static str> {
+        let defined_name = DefinedName::new(name, formula, None)?;
+        self.add_defined_name(defined_name)
+    }
+
+    /// define a name only visible from the given sheet, the sheet must be added already.
+    pub fn define_sheet_name(
+        &mut self,
+        sheet_name: &str,
+        name: &str,
+        formula: &str,
+    ) -> Result<(), &'static str> {
+        let sheet_index = self.sheet_index(sheet_name)?;
+        let defined_name = DefinedName::new(name, formula, Some(sheet_index))?;
+        self.add_defined_name(defined_name)
+    }
+
+    /// set one of the built in names (print area, print titles, filter database) of a sheet,
+    /// an earlier value of the same built in name is replaced.
+    pub fn define_built_in_name(
+        &mut self,
+        sheet_name: &str,
+        name: BuiltInName,
+        formula: &str,
+    ) -> Result<(), &'static str> {
+        let sheet_index = self.sheet_index(sheet_name)?;
+        let defined_name = DefinedName::built_in(name, formula, sheet_index);
+        self.defined_names
+            .retain(|d| !d.clashes_with(&defined_name));
+        self.defined_names.push(defined_name);
+        Ok(())
+    }
+
+    fn add_defined_name(&mut self, defined_name: DefinedName) -> Result<(), &'static str> {
+        if self
+            .defined_names
+            .iter()
+            .any(|d| d.clashes_with(&defined_name))
+        {
+            return Err("Defined name already exists");
         }
+        self.defined_names.push(defined_name);
+        Ok(())
+    }
+
+    fn sheet_name(&self, kind: &SheetKind) -> &str {
+        match kind {
+            SheetKind::WorkSheet(i) => &self.work_sheets[*i].name,
+            SheetKind::ChartSheet(i) => &self.chart_sheets[*i].name,
+            SheetKind::Preserved(i) => &self.preserved_sheets[*i].name,
+        }
+    }
+
+    // position of the sheet among the tabs
+    fn sheet_index(&self, sheet_name: &str) -> Result<usize, &'static str> {
+        self.sheet_order
+            .iter()
+            .position(|kind| self.sheet_name(kind) == sheet_name)
+            .ok_or("Sheet not found")
+    }
+
+    /// set the document properties (title, author, ...) written to docProps/core.xml
+    /// and docProps/app.xml
+    pub fn set_properties(&mut self, properties: DocProperties) -> Result<(), &'static str> {
+        properties.validate()?;
+        self.doc_properties = properties;
+        Ok(())
+    }
+
+    /// add a typed custom property written to docProps/custom.xml
+    pub fn add_custom_property(
+        &mut self,
+        name: &str,
+        value: CustomPropertyValue,
+    ) -> Result<(), &'static str> {
+        self.custom_properties.add(name, value)
     }
 
     pub fn add_sheet(&mut self, mut work_sheet: WorkSheet) {
         if self.work_sheet_names.contains(&work_sheet.name) {
             work_sheet.name = format!("Sheet{}", self.work_sheet_names.len() + 1)
         }
+        // print area and print titles are stored as built in names of the sheet.
+        let sheet_index = self.sheet_order.len();
+        let sheet_number = self.work_sheets.len() + 1;
+        if let Some(page_setup) = work_sheet.get_page_setup() {
+            let built_in_names = [
+                (
+                    BuiltInName::PrintArea,
+                    page_setup.print_area_formula(&work_sheet.name),
+                ),
+                (
+                    BuiltInName::PrintTitles,
+                    page_setup.print_titles_formula(&work_sheet.name),
+                ),
+            ];
+            for (name, formula) in built_in_names {
+                if let Some(formula) = formula {
+                    let defined_name = DefinedName::built_in(name, &formula, sheet_index);
+                    self.defined_names
+                        .retain(|d| !d.clashes_with(&defined_name));
+                    self.defined_names.push(defined_name);
+                }
+            }
+        }
+
         // register this sheet to content type.
         self.content_type.add_sheet();
-        // update the share string.
-        let row_itr = work_sheet.rows.iter_mut();
-        for row in row_itr {
-            let cell_itr = row.get_cells_mut().iter_mut();
-            for cell in cell_itr {
-                match &mut cell.value {
-                    CellValue::CString(v) => {
-                        self.shared_string.add_string(v);
+        work_sheet.link_parts(sheet_number, &mut self.drawing_numbers);
+        if !work_sheet.get_drawing().is_empty() {
+            self.content_type
+                .add_drawing(work_sheet.get_drawing().number().unwrap());
+            for (extension, content_type) in work_sheet.get_drawing().media_types() {
+                self.content_type.add_default(extension, content_type);
+            }
+            for chart_number in work_sheet.get_drawing().chart_numbers() {
+                self.content_type.add_chart(chart_number);
+            }
+        }
+        if !work_sheet.get_notes().is_empty() {
+            self.content_type.add_comments(sheet_number);
+        }
+        if !work_sheet.get_threaded_comments().is_empty() {
+            self.content_type.add_threaded_comments(sheet_number);
+            if self.persons.is_empty() {
+                self.content_type.add_persons();
+            }
+            for thread in work_sheet.get_threaded_comments().iter() {
+                for author in thread.authors() {
+                    if !self.persons.iter().any(|p| p == author) {
+                        self.persons.push(author.to_string());
                     }
-                    _ => {}
-                }
-
-                // cells have style
-                if let Some(i) = cell.get_style().as_ref() {
-                    let sid = self.style.add_cell_xf(Some(&i.font_style));
-                    cell.set_style_index(sid);
                 }
             }
         }
-
         // append this work sheet
+        self.sheet_order
+            .push(SheetKind::WorkSheet(self.work_sheets.len()));
         self.work_sheets.push(work_sheet);
         let last = self.work_sheets.last().unwrap();
 
         self.work_sheet_names.insert(last.name.clone());
     }
 
+    // strings go to the shared strings and styles to the style sheet when the work book is
+    // saved, until then the cells keep their text and can be changed.
+    fn register_cells(&mut self) {
+        for work_sheet in self.work_sheets.iter_mut() {
+            // hyperlinks of the cells belong to the sheet.
+            let mut cell_hyperlinks = vec![];
+            for row in work_sheet.rows.iter_mut() {
+                for cell in row.get_cells_mut().iter_mut() {
+                    if let Some(hyperlink) = cell.take_hyperlink() {
+                        cell_hyperlinks.push(hyperlink);
+                    }
+                }
+            }
+            for hyperlink in cell_hyperlinks {
+                work_sheet.push_hyperlink(hyperlink);
+            }
+            let hyperlink_refs = work_sheet.hyperlink_references();
+
+            for row in work_sheet.rows.iter_mut() {
+                register_row(row, &hyperlink_refs, &mut self.shared_string, &mut self.style);
+            }
+        }
+    }
+
+    /// how string cells are stored, shared by default. set it before adding streaming
+    /// sheets, their strings are stored as they are written.
+    pub fn set_string_strategy(&mut self, strategy: StringStrategy) -> Result<(), &'static str> {
+        self.shared_string.set_strategy(strategy)
+    }
+
+    /// add a sheet whose rows are written to a temporary file as they are added, for
+    /// exports too large to hold in memory. the rows are copied in to the package on save.
+    pub fn add_streaming_sheet(
+        &mut self,
+        name: &str,
+    ) -> Result<StreamingWorkSheet<'_>, &'static str> {
+        self.add_sheet(WorkSheet::blank(name));
+        let work_sheet = self.work_sheets.last_mut().unwrap();
+        StreamingWorkSheet::new(work_sheet, &mut self.shared_string, &mut self.style)
+    }
+
+    /// add a sheet that holds only the chart, after the sheets added so far.
+    pub fn add_chartsheet(&mut self, name: &str, chart: Chart) -> Result<(), &'static str> {
+        if self.work_sheet_names.contains(name) {
+            return Err("Sheet name already exists");
+        }
+        let mut chart_sheet = ChartSheet::new(name, chart)?;
+
+        // register this sheet, its drawing and chart to content type.
+        self.content_type.add_chart_sheet();
+        chart_sheet.link_parts(&mut self.drawing_numbers);
+        let drawing = chart_sheet.get_drawing();
+        self.content_type.add_drawing(drawing.number().unwrap());
+        for chart_number in drawing.chart_numbers() {
+            self.content_type.add_chart(chart_number);
+        }
+
+        self.sheet_order
+            .push(SheetKind::ChartSheet(self.chart_sheets.len()));
+        self.chart_sheets.push(chart_sheet);
+        self.work_sheet_names.insert(name.to_string());
+        Ok(())
+    }
+
+    /// add a pivot table to an added work sheet, its top left corner at `cell_ref`.
+    /// the source range must be on an added work sheet too, its data fills the pivot cache.
+    pub fn add_pivot_table(
+        &mut self,
+        sheet_name: &str,
+        cell_ref: &str,
+        pivot_table: PivotTable,
+    ) -> Result<(), &'static str> {
+        let (row, column) = parse_cell_ref(cell_ref)?;
+        let target = self
+            .work_sheets
+            .iter()
+            .position(|w| w.name == sheet_name)
+            .ok_or("Sheet not found")?;
+        if self
+            .pivot_tables
+            .iter()
+            .any(|p| p.get_name() == pivot_table.get_name())
+        {
+            return Err("Pivot table name already exists");
+        }
+
+        let (source, source_sheet, range) = self.pivot_source(pivot_table.get_source())?;
+        let values = self.range_values(&source_sheet, &range)?;
+        let number = self.pivot_tables.len() + 1;
+        let cache_id = self.next_pivot_cache_id();
+        let parts = PivotParts::new(&pivot_table, cache_id, row, column, &source, &values)?;
+
+        self.work_sheets[target].link_pivot_table(number);
+        self.content_type.add_pivot_table(number);
+        self.pivot_tables.push(parts);
+        Ok(())
+    }
+
+    // the cache ids of a read work book are kept, the added caches come after them.
+    fn next_pivot_cache_id(&self) -> usize {
+        let kept = self
+            .preserved_elements
+            .get("pivotCaches")
+            .into_iter()
+            .flat_map(|caches| caches.elements())
+            .filter_map(|cache| cache.attribute("cacheId")?.parse::<usize>().ok());
+        let added = self.pivot_tables.iter().map(|p| p.get_cache_id());
+        kept.chain(added).max().unwrap_or(0) + 1
+    }
+
+    // the cache source and the (sheet, range) it covers, from `Sheet!A1:D9` or a defined name.
+    fn pivot_source(&self, source: &str) -> Result<(CacheSource, String, String), &'static str> {
+        let (cache_source, formula) = match source.rsplit_once('!') {
+            Some(_) => (None, source.to_string()),
+            None => {
+                let defined_name = self
+                    .defined_names
+                    .iter()
+                    .find(|d| d.local_sheet_id.is_none() && d.name == source)
+                    .ok_or("Pivot source is not a range or a defined name")?;
+                (
+                    Some(CacheSource::Name(source.to_string())),
+                    defined_name.formula.clone(),
+                )
+            }
+        };
+        let (sheet, range) = formula
+            .rsplit_once('!')
+            .ok_or("Pivot source is not a range")?;
+        // 'My sheet' -> My sheet
+        let sheet = match sheet.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+            Some(quoted) => quoted.replace("''", "'"),
+            None => sheet.to_string(),
+        };
+        let range = range.replace('$', "");
+        let cache_source =
+            cache_source.unwrap_or_else(|| CacheSource::Range(sheet.clone(), range.clone()));
+        Ok((cache_source, sheet, range))
+    }
+
+    // the values of a range of an added work sheet, row by row.
+    fn range_values(
+        &self,
+        sheet_name: &str,
+        range: &str,
+    ) -> Result<Vec<Vec<CacheValue>>, &'static str> {
+        let work_sheet = self
+            .work_sheets
+            .iter()
+            .find(|w| w.name == sheet_name)
+            .ok_or("Pivot source sheet not found")?;
+        let (first, last) = range.split_once(':').unwrap_or((range, range));
+        let (first_row, first_column) = parse_cell_ref(first)?;
+        let (last_row, last_column) = parse_cell_ref(last)?;
+        if last_row < first_row || last_column < first_column {
+            return Err("Invalid pivot source range");
+        }
+
+        let mut values =
+            vec![vec![CacheValue::Blank; last_column - first_column + 1]; last_row - first_row + 1];
+        for row in work_sheet.rows.iter() {
+            for cell in row.get_cells() {
+                let Some(reference) = cell.get_attributes().reference.as_ref() else {
+                    continue;
+                };
+                let (r, c) = parse_cell_ref(reference)?;
+                let in_range = (first_row..=last_row).contains(&r)
+                    && (first_column..=last_column).contains(&c);
+                if !in_range {
+                    continue;
+                }
+                // formulas have no value yet
+                let value = match &cell.value {
+                    CellValue::CString(text) | CellValue::CInString(text) => {
+                        CacheValue::Text(text.clone())
+                    }
+                    CellValue::CRichString(runs) => CacheValue::Text(rich_text::plain_text(runs)),
+                    CellValue::CNumber(number) | CellValue::CDate(number) => number
+                        .parse::<f64>()
+                        .map(CacheValue::Number)
+                        .unwrap_or(CacheValue::Blank),
+                    CellValue::CBool(b) => {
+                        CacheValue::Text(if *b { "TRUE" } else { "FALSE" }.to_string())
+                    }
+                    CellValue::CFomula(_) | CellValue::CError(_) | CellValue::Empty => {
+                        CacheValue::Blank
+                    }
+                };
+                values[r - first_row][c - first_column] = value;
+            }
+        }
+        Ok(values)
+    }
+
     fn create_sheets(&self, writer: &mut XmlWriter) {
         writer.start_element("sheets");
-        let mut r_id: u8 = 1;
-        // for each worksheet print xml
-        for (inx, work_sheet) in self.work_sheets.iter().enumerate() {
+        // for each sheet print xml, in the relationships the work sheets come first
+        // then the chart sheets.
+        for (inx, kind) in self.sheet_order.iter().enumerate() {
             writer.start_element("sheet");
-            writer.write_attribute("name", work_sheet.name.as_str());
-            writer.write_attribute("state", "visiable");
-            let current_rid = format!("rId{}", r_id.to_string());
-            // todo: add sheet to content type and get back the id, which will be set in here.
+            writer.write_attribute("name", &escape_xml(self.sheet_name(kind)));
+            let state = match kind {
+                SheetKind::Preserved(i) => self.preserved_sheets[*i].state.as_deref(),
+                _ => None,
+            };
+            writer.write_attribute("state", &escape_xml(state.unwrap_or("visible")));
+            let current_rid = match kind {
+                SheetKind::WorkSheet(i) => format!("rId{}", i + 1),
+                SheetKind::ChartSheet(i) => format!("rId{}", self.work_sheets.len() + i + 1),
+                SheetKind::Preserved(i) => self.preserved_sheets[*i].r_id.clone(),
+            };
             writer.write_attribute("r:id", current_rid.as_str());
             writer.write_attribute("sheetId", (inx + 1).to_string().as_str());
             writer.end_element();
-            r_id += 1;
+        }
+        writer.end_element();
+    }
+
+    fn create_defined_names(&self, writer: &mut XmlWriter) {
+        if self.defined_names.is_empty() {
+            return;
+        }
+        writer.start_element("definedNames");
+        for defined_name in self.defined_names.iter() {
+            defined_name.clone().to_xml(writer);
+        }
+        writer.end_element();
+    }
+
+    // the caches of a read work book, then the added ones
+    fn create_pivot_caches(&self, writer: &mut XmlWriter, kept: Option<PreservedElement>) {
+        if self.pivot_tables.is_empty() {
+            return;
+        }
+        // in the relationships the pivot caches come right after the sheets.
+        let first_rid = self.work_sheets.len() + self.chart_sheets.len() + 1;
+        writer.start_element("pivotCaches");
+        for cache in kept.iter().flat_map(|kept| kept.elements()) {
+            cache.write(writer);
+        }
+        for (i, pivot_table) in self.pivot_tables.iter().enumerate() {
+            writer.start_element("pivotCache");
+            writer.write_attribute("cacheId", &pivot_table.get_cache_id().to_string());
+            writer.write_attribute("r:id", &format!("rId{}", first_rid + i));
+            writer.end_element();
         }
         writer.end_element();
     }
 
     // todo: we need to cover every thing here, be the following code cosume everthing.
-    fn to_xml(&mut self) -> String {
-        let mut writer = XmlWriter::new(Options::default());
+    fn to_xml(&self) -> String {
+        // defined name formulas are text, they must not be indented.
+        let mut writer = XmlWriter::new(Options {
+            indent: Indent::None,
+            ..Default::default()
+        });
 
         writer.start_element("workbook");
         writer.write_attribute(
@@ -99,91 +585,333 @@ impl WorkBook {
             "xmlns:r",
             "http://schemas.openxmlformats.org/officeDocument/2006/relationships",
         );
+        // book views, calculation properties, .. of a read work book
+        let mut preserved = self.preserved_elements.clone();
+        preserved.write_root_attributes(&mut writer);
+        let kept_caches = match self.pivot_tables.is_empty() {
+            true => None,
+            false => preserved.take("pivotCaches"),
+        };
 
         // self.create_file_version(&mut writer);
         // self.create_book_views(&mut writer);
+        preserved.write_before(&mut writer, &WORK_BOOK_ELEMENTS, "sheets");
         self.create_sheets(&mut writer);
+        preserved.write_before(&mut writer, &WORK_BOOK_ELEMENTS, "definedNames");
+        self.create_defined_names(&mut writer);
+        preserved.write_before(&mut writer, &WORK_BOOK_ELEMENTS, "pivotCaches");
+        self.create_pivot_caches(&mut writer, kept_caches);
+        preserved.write_before(&mut writer, &WORK_BOOK_ELEMENTS, "");
 
         writer.end_element();
         writer.end_document()
     }
 
-    pub fn save(mut self) {
+    /// save to an xlsx file with the default options, see `write_to`.
+    pub fn save(self, path: &str) -> Result<(), &'static str> {
+        self.save_with_options(path, SaveOptions::default())
+    }
+
+    /// save to an xlsx file with the given compression, zip64 and time stamps.
+    pub fn save_with_options(
+        self,
+        path: &str,
+        save_options: SaveOptions,
+    ) -> Result<(), &'static str> {
+        let file = File::create(path).map_err(|_| "Could not create the file")?;
+        self.write_to(file, save_options)
+    }
+
+    /// write the work book as an xlsx package to any writer, eg. a file or a `Cursor<Vec<u8>>`.
+    pub fn write_to<W: Write + Seek>(
+        self,
+        writer: W,
+        save_options: SaveOptions,
+    ) -> Result<(), &'static str> {
+        self.write_package(writer, save_options)
+            .map_err(|_| "Could not write the work book")
+    }
+
+    fn write_package<W: Write + Seek>(
+        mut self,
+        writer: W,
+        save_options: SaveOptions,
+    ) -> ZipResult<()> {
+        let options = save_options.file_options();
+        if save_options.deterministic_timestamps {
+            self.doc_properties
+                .created
+                .get_or_insert_with(|| FIXED_TIMESTAMP.to_string());
+            for work_sheet in self.work_sheets.iter_mut() {
+                work_sheet.date_untimed_comments(FIXED_TIMESTAMP);
+            }
+        }
+        self.register_cells();
         let work_book_xml = self.to_xml();
 
         let ss_xml = self.shared_string.to_xml();
 
         let style_xml = self.style.to_xml();
 
-        println!("{}", style_xml);
+        let sheet_names: Vec<&str> = self.work_sheets.iter().map(|w| w.name.as_str()).collect();
+        let chart_sheet_names: Vec<&str> =
+            self.chart_sheets.iter().map(|c| c.name.as_str()).collect();
+        let core_xml = self.doc_properties.to_core_xml();
+        let app_xml = self
+            .doc_properties
+            .to_app_xml(&sheet_names, &chart_sheet_names);
+
+        let has_custom_properties = !self.custom_properties.is_empty();
+        if has_custom_properties {
+            self.content_type.add_custom_properties();
+        }
+        let custom_xml = self.custom_properties.to_xml();
+
+        // parts kept from a read work book, the ones written from the work book replace them.
+        self.preserved_parts.register(&mut self.content_type);
+        for relation in self.preserved_root_relations.iter() {
+            if !(has_custom_properties && relation.is("custom-properties")) {
+                relation.add_to(&mut self.root_relation_ship);
+            }
+        }
+        for relation in self.preserved_work_book_relations.iter() {
+            if self.persons.is_empty() || !relation.is("person") {
+                relation.add_to(&mut self.work_book_relation_ship);
+            }
+        }
 
         let content_type_xml = self.content_type.to_xml();
 
-        let root_rs_xml = self.root_relation_ship.to_root_xml();
+        let root_rs_xml = self.root_relation_ship.to_root_xml(has_custom_properties);
 
-        let work_book_rs_xml = self
-            .work_book_relation_ship
-            .to_work_book_rel_xml(0, self.work_sheets.len());
+        let work_book_rs_xml = self.work_book_relation_ship.to_work_book_rel_xml(
+            0,
+            self.work_sheets.len(),
+            self.chart_sheets.len(),
+            self.pivot_tables.len(),
+            !self.persons.is_empty(),
+        );
 
-        let cursor = std::io::Cursor::new(Vec::new());
-        let mut zip = zip::ZipWriter::new(cursor);
+        // written straight to the writer, a streamed sheet is never held in memory
+        let mut zip = zip::ZipWriter::new(writer);
 
         // content type root
-        zip.start_file("[Content_Types].xml", SimpleFileOptions::default())
-            .unwrap();
-        zip.write_all(content_type_xml.as_bytes()).unwrap();
+        zip.start_file("[Content_Types].xml", options)?;
+        zip.write_all(content_type_xml.as_bytes())?;
 
         //_rels root
-        zip.add_directory("_rels/", SimpleFileOptions::default())
-            .unwrap();
-        zip.start_file("_rels/.rels", SimpleFileOptions::default())
-            .unwrap();
-        zip.write_all(root_rs_xml.as_bytes()).unwrap();
+        zip.add_directory("_rels/", options)?;
+        zip.start_file("_rels/.rels", options)?;
+        zip.write_all(root_rs_xml.as_bytes())?;
+
+        // document properties
+        zip.add_directory("docProps/", options)?;
+        zip.start_file("docProps/core.xml", options)?;
+        zip.write_all(core_xml.as_bytes())?;
+        zip.start_file("docProps/app.xml", options)?;
+        zip.write_all(app_xml.as_bytes())?;
+        if has_custom_properties {
+            zip.start_file("docProps/custom.xml", options)?;
+            zip.write_all(custom_xml.as_bytes())?;
+        }
 
         // folder for x1
-        zip.add_directory("xl/", SimpleFileOptions::default())
-            .unwrap();
-        zip.add_directory("xl/_rels/", SimpleFileOptions::default())
-            .unwrap();
-        zip.add_directory("xl/worksheets/", SimpleFileOptions::default())
-            .unwrap();
+        zip.add_directory("xl/", options)?;
+        zip.add_directory("xl/_rels/", options)?;
+        zip.add_directory("xl/worksheets/", options)?;
+        zip.add_directory("xl/worksheets/_rels/", options)?;
 
         // add relation ship for workbook
-        zip.start_file("xl/_rels/workbook.xml.rels", SimpleFileOptions::default())
-            .unwrap();
-        zip.write_all(work_book_rs_xml.as_bytes()).unwrap();
+        zip.start_file("xl/_rels/workbook.xml.rels", options)?;
+        zip.write_all(work_book_rs_xml.as_bytes())?;
 
         // add sheets
-        for (i, work_sheet) in self.work_sheets.into_iter().enumerate() {
-            let sheet_xml = work_sheet.to_xml();
-            println!("{}", sheet_xml);
-            let sheet_name = format!("xl/worksheets/sheet{}.xml", i + 1);
-            zip.start_file(sheet_name, SimpleFileOptions::default())
-                .unwrap();
-            zip.write_all(sheet_xml.as_bytes()).unwrap();
+        // serialized and compressed side by side, then copied in the order of the sheets
+        pack_sheets(self.work_sheets, options, |packed| {
+            let mut entries = ZipArchive::new(Cursor::new(packed.entries))?;
+            for i in 0..entries.len() {
+                zip.raw_copy_file(entries.by_index(i)?)?;
+            }
+            if let Some((sheet_xml, rows)) = packed.streamed {
+                let sheet_name = format!("xl/worksheets/sheet{}.xml", packed.number);
+                zip.start_file(sheet_name, options)?;
+                rows.write_sheet(&sheet_xml, &mut zip)
+                    .map_err(|e| ZipError::Io(io::Error::other(e)))?;
+            }
+            Ok(())
+        })?;
+
+        // add chart sheets
+        for (i, chart_sheet) in self.chart_sheets.iter().enumerate() {
+            for (part_name, data) in chart_sheet.parts(i + 1) {
+                zip.start_file(part_name, options)?;
+                zip.write_all(&data)?;
+            }
+            let sheet_name = format!("xl/chartsheets/sheet{}.xml", i + 1);
+            zip.start_file(sheet_name, options)?;
+            zip.write_all(chart_sheet.to_xml().as_bytes())?;
+        }
+
+        // add pivot tables and their caches
+        for (i, pivot_table) in self.pivot_tables.iter().enumerate() {
+            for (part_name, data) in pivot_table.parts(i + 1) {
+                zip.start_file(part_name, options)?;
+                zip.write_all(&data)?;
+            }
+        }
+
+        // charts, drawings, vba, .. of a read work book
+        for (part_name, data) in self.preserved_parts.parts() {
+            zip.start_file(part_name, options)?;
+            zip.write_all(&data)?;
+        }
+
+        // authors of threaded comments
+        if !self.persons.is_empty() {
+            zip.start_file("xl/persons/person.xml", options)?;
+            zip.write_all(persons_to_xml(&self.persons).as_bytes())?;
         }
 
         // add shared strings
-        zip.start_file("xl/sharedStrings.xml", SimpleFileOptions::default())
-            .unwrap();
-        zip.write_all(ss_xml.as_bytes()).unwrap();
+        zip.start_file("xl/sharedStrings.xml", options)?;
+        zip.write_all(ss_xml.as_bytes())?;
 
         // add styles
-        zip.start_file("xl/styles.xml", SimpleFileOptions::default())
-            .unwrap();
-        zip.write_all(style_xml.as_bytes()).unwrap();
+        zip.start_file("xl/styles.xml", options)?;
+        zip.write_all(style_xml.as_bytes())?;
 
         // add workbook
-        zip.start_file("xl/workbook.xml", SimpleFileOptions::default())
-            .unwrap();
-        zip.write_all(work_book_xml.as_bytes()).unwrap();
+        zip.start_file("xl/workbook.xml", options)?;
+        zip.write_all(work_book_xml.as_bytes())?;
 
-        let cursor = zip.finish().unwrap();
-        let data = cursor.into_inner();
-        let mut file = File::create("test.xlsx").unwrap();
-        file.write_all(&data).unwrap();
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+// a work sheet and its parts as a small package of their own, made apart from the work book
+// so sheets can be serialized on many threads. its entries are copied as they are.
+struct PackedSheet {
+    number: usize,
+    entries: Vec<u8>,
+    // the xml of a streamed sheet, its rows are copied from the temporary file on save
+    streamed: Option<(String, StreamedRows)>,
+}
 
-        let mut zip_file = File::create("test.zip").unwrap();
-        zip_file.write_all(&data).unwrap();
+fn pack_sheet(
+    number: usize,
+    mut work_sheet: WorkSheet,
+    options: SimpleFileOptions,
+) -> ZipResult<PackedSheet> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (part_name, data) in work_sheet.parts(number) {
+        zip.start_file(part_name, options)?;
+        zip.write_all(&data)?;
+    }
+    let streamed_rows = work_sheet.take_streamed_rows();
+    let sheet_xml = work_sheet.to_xml();
+    let streamed = match streamed_rows {
+        Some(rows) => Some((sheet_xml, rows)),
+        None => {
+            let sheet_name = format!("xl/worksheets/sheet{}.xml", number);
+            zip.start_file(sheet_name, options)?;
+            zip.write_all(sheet_xml.as_bytes())?;
+            None
+        }
+    };
+    Ok(PackedSheet {
+        number,
+        entries: zip.finish()?.into_inner(),
+        streamed,
+    })
+}
+
+// pack every sheet on a pool of threads, one per core, and hand them to `write` in order,
+// each one as soon as it and the sheets before it are packed.
+fn pack_sheets(
+    work_sheets: Vec<WorkSheet>,
+    options: SimpleFileOptions,
+    mut write: impl FnMut(PackedSheet) -> ZipResult<()>,
+) -> ZipResult<()> {
+    let count = work_sheets.len();
+    let threads = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(count);
+    let queue = Mutex::new(work_sheets.into_iter().enumerate());
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let queue = &queue;
+            scope.spawn(move || loop {
+                let next = queue.lock().unwrap().next();
+                let Some((i, work_sheet)) = next else {
+                    break;
+                };
+                // the receiver is gone once writing failed
+                if sender.send((i, pack_sheet(i + 1, work_sheet, options))).is_err() {
+                    break;
+                }
+            });
+        }
+        // the channel ends with the last worker
+        drop(sender);
+
+        // sheets packed before the ones in front of them wait here
+        let mut packed: Vec<Option<PackedSheet>> = (0..count).map(|_| None).collect();
+        let mut next = 0;
+        for (i, sheet) in receiver {
+            packed[i] = Some(sheet?);
+            while let Some(sheet) = packed.get_mut(next).and_then(Option::take) {
+                write(sheet)?;
+                next += 1;
+            }
+        }
+        Ok(())
+    })
+}
+
+// the strings of a row go to the shared strings and the styles of its cells to the style
+// sheet, a linked cell without a style looks like a link.
+pub(crate) fn register_row(
+    row: &mut Row,
+    hyperlink_refs: &HashSet<String>,
+    shared_string: &mut SharedStrings,
+    style: &mut Style,
+) {
+    for cell in row.get_cells_mut().iter_mut() {
+        match &mut cell.value {
+            CellValue::CString(v) => {
+                let shared = shared_string.share_string(v);
+                if !shared {
+                    cell.value = CellValue::CInString(mem::take(v));
+                }
+            }
+            // a rich string not shared is written in the cell as it is
+            CellValue::CRichString(runs) => {
+                if let Some(index) = shared_string.share_rich_string(runs) {
+                    cell.value = CellValue::CString(index.to_string());
+                }
+            }
+            _ => {}
+        }
+
+        // cells have style
+        if let Some(cell_style) = cell.get_style().as_ref() {
+            let sid = style.add_cell_xf(
+                Some(&cell_style.font_style),
+                cell_style.number_format.as_deref(),
+                cell_style.base_xf,
+            );
+            cell.set_style_index(sid);
+        } else if cell
+            .get_attributes()
+            .reference
+            .as_ref()
+            .is_some_and(|r| hyperlink_refs.contains(r))
+        {
+            let sid = style.add_hyperlink_xf();
+            cell.set_style_index(sid);
+        }
     }
 }
@@ -1,13 +1,93 @@
-use xmlwriter::{Options, XmlWriter};
+use std::collections::HashSet;
 
-use crate::{row::Row, traits::XMLString};
+use xmlwriter::{Indent, Options, XmlWriter};
+
+use crate::{
+    chart::{Chart, ChartOptions},
+    comment::{CommentThread, CommentThreads, Note, Notes},
+    drawing::{Drawing, DrawingNumbers},
+    header_footer::{HeaderFooter, HeaderFooters},
+    hyperlink::{Hyperlink, HyperlinkTarget},
+    image::{Image, ImageOptions, ImageSource},
+    package::{PreservedElements, PreservedRelation},
+    page_setup::PageSetup,
+    row::{parse_cell_ref, Row, MAX_COLUMNS, MAX_ROWS},
+    sparkline::{write_sparklines, Sparkline, SparklineOptions},
+    streaming_work_sheet::StreamedRows,
+    traits::XMLString,
+    xml_templates::relation_ship::{
+        RelationShip, RS_COMMENTS, RS_DRAWING, RS_HYPERLINK, RS_PIVOT_TABLE, RS_THREADED_COMMENT,
+        RS_VML_DRAWING,
+    },
+};
 
 pub struct WorkSheet {
     pub name: String,
     pub rows: Vec<Row>,
     next_row: usize,
+    page_setup: Option<PageSetup>,
+    row_breaks: Vec<usize>,
+    column_breaks: Vec<usize>,
+    header_footers: HeaderFooters,
+    hyperlinks: Vec<Hyperlink>,
+    relation_ship: RelationShip,
+    notes: Notes,
+    threads: CommentThreads,
+    // relationship id of the vml drawing, set once the sheet is added to a work book.
+    legacy_drawing_r_id: Option<String>,
+    drawing: Drawing,
+    drawing_r_id: Option<String>,
+    sparklines: Vec<Sparkline>,
+    // of a read sheet, what this crate does not model
+    preserved_elements: PreservedElements,
+    preserved_relations: Vec<PreservedRelation>,
+    // the rows of a streaming sheet, written apart from `rows`
+    streamed_rows: Option<StreamedRows>,
 }
 
+// the children of <worksheet> in the order the schema asks for
+static SHEET_ELEMENTS: [&str; 39] = [
+    "sheetPr",
+    "dimension",
+    "sheetViews",
+    "sheetFormatPr",
+    "cols",
+    "sheetData",
+    "sheetCalcPr",
+    "sheetProtection",
+    "protectedRanges",
+    "scenarios",
+    "autoFilter",
+    "sortState",
+    "dataConsolidate",
+    "customSheetViews",
+    "mergeCells",
+    "phoneticPr",
+    "conditionalFormatting",
+    "dataValidations",
+    "hyperlinks",
+    "printOptions",
+    "pageMargins",
+    "pageSetup",
+    "headerFooter",
+    "rowBreaks",
+    "colBreaks",
+    "customProperties",
+    "cellWatches",
+    "ignoredErrors",
+    "smartTags",
+    "drawing",
+    "legacyDrawing",
+    "legacyDrawingHF",
+    "drawingHF",
+    "picture",
+    "oleObjects",
+    "controls",
+    "webPublishItems",
+    "tableParts",
+    "extLst",
+];
+
 impl WorkSheet {
     // create a new
     pub fn blank(name: &str) -> Self {
@@ -15,7 +95,320 @@ impl WorkSheet {
             name: name.to_string(),
             rows: vec![],
             next_row: 1,
+            page_setup: None,
+            row_breaks: vec![],
+            column_breaks: vec![],
+            header_footers: HeaderFooters::default(),
+            hyperlinks: vec![],
+            relation_ship: RelationShip::new(),
+            notes: Notes::new(),
+            threads: CommentThreads::new(),
+            legacy_drawing_r_id: None,
+            drawing: Drawing::new(),
+            drawing_r_id: None,
+            sparklines: vec![],
+            preserved_elements: PreservedElements::default(),
+            preserved_relations: vec![],
+            streamed_rows: None,
+        }
+    }
+
+    /// keep what a read sheet holds beyond the types of this crate, written back on save.
+    pub(crate) fn preserve(
+        &mut self,
+        elements: PreservedElements,
+        relations: Vec<PreservedRelation>,
+    ) {
+        self.preserved_elements = elements;
+        self.preserved_relations = relations;
+    }
+
+    pub(crate) fn stream_rows(&mut self, rows: StreamedRows) {
+        self.streamed_rows = Some(rows);
+    }
+
+    pub(crate) fn has_streamed_rows(&self) -> bool {
+        self.streamed_rows.is_some()
+    }
+
+    pub(crate) fn take_streamed_rows(&mut self) -> Option<StreamedRows> {
+        self.streamed_rows.take()
+    }
+
+    /// add a note (legacy comment) to a cell, the returned note can be sized,
+    /// made visible or get more formatted text.
+    pub fn add_note(
+        &mut self,
+        cell_ref: &str,
+        author: &str,
+        text: &str,
+    ) -> Result<&mut Note, &'static str> {
+        let note = Note::new(cell_ref, author, text)?;
+        Ok(self.notes.add(note))
+    }
+
+    pub fn get_notes(&self) -> &Notes {
+        &self.notes
+    }
+
+    /// start a threaded comment conversation on a cell, replies and the resolved
+    /// state are set on the returned thread.
+    pub fn add_threaded_comment(
+        &mut self,
+        cell_ref: &str,
+        author: &str,
+        text: &str,
+    ) -> Result<&mut CommentThread, &'static str> {
+        let thread = CommentThread::new(cell_ref, author, text)?;
+        Ok(self.threads.add(thread))
+    }
+
+    pub fn get_threaded_comments(&self) -> &CommentThreads {
+        &self.threads
+    }
+
+    pub(crate) fn date_untimed_comments(&mut self, w3cdtf: &str) {
+        self.threads.date_untimed(w3cdtf);
+    }
+
+    /// insert a png, jpeg, gif or bmp image (a path or the file bytes) at a cell.
+    pub fn insert_image<S: Into<ImageSource>>(
+        &mut self,
+        cell_ref: &str,
+        source: S,
+        options: ImageOptions,
+    ) -> Result<(), &'static str> {
+        let (row, column) = parse_cell_ref(cell_ref)?;
+        options.validate()?;
+        let image = Image::new(source.into())?;
+        self.drawing.add_image(row - 1, column - 1, image, options);
+        Ok(())
+    }
+
+    /// insert a chart with its top left corner at a cell.
+    pub fn insert_chart(
+        &mut self,
+        cell_ref: &str,
+        chart: Chart,
+        options: ChartOptions,
+    ) -> Result<(), &'static str> {
+        let (row, column) = parse_cell_ref(cell_ref)?;
+        chart.validate()?;
+        self.drawing.add_chart(row - 1, column - 1, chart, options);
+        Ok(())
+    }
+
+    /// show the data of a range as a small chart in a cell, eg. `add_sparkline("F2", "B2:E2", ..)`
+    pub fn add_sparkline(
+        &mut self,
+        cell_ref: &str,
+        data_range: &str,
+        options: SparklineOptions,
+    ) -> Result<(), &'static str> {
+        let sparkline = Sparkline::new(cell_ref, data_range, options)?;
+        self.sparklines.push(sparkline);
+        Ok(())
+    }
+
+    pub fn get_drawing(&self) -> &Drawing {
+        &self.drawing
+    }
+
+    /// register the parts of this sheet (notes, ...) in its relationships,
+    /// called when the sheet is added to a work book as the `sheet_number`th sheet.
+    pub(crate) fn link_parts(&mut self, sheet_number: usize, drawing_numbers: &mut DrawingNumbers) {
+        if !self.drawing.is_empty() {
+            self.drawing.link(drawing_numbers);
+            let r_id = self.relation_ship.add_relation(
+                RS_DRAWING,
+                &format!("../drawings/drawing{}.xml", self.drawing.number().unwrap()),
+                false,
+            );
+            self.drawing_r_id = Some(r_id);
+        }
+        if !self.threads.is_empty() {
+            self.threads.link(sheet_number, &mut self.notes);
+            self.relation_ship.add_relation(
+                RS_THREADED_COMMENT,
+                &format!("../threadedComments/threadedComment{}.xml", sheet_number),
+                false,
+            );
+        }
+        if !self.notes.is_empty() {
+            self.notes.link(drawing_numbers);
+            self.relation_ship.add_relation(
+                RS_COMMENTS,
+                &format!("../comments{}.xml", sheet_number),
+                false,
+            );
+            let r_id = self.relation_ship.add_relation(
+                RS_VML_DRAWING,
+                &format!("../drawings/vmlDrawing{}.vml", sheet_number),
+                false,
+            );
+            self.legacy_drawing_r_id = Some(r_id);
+        }
+
+        // a drawing or notes added to a read sheet take the place of the kept ones.
+        let mut replaced = vec![];
+        if self.drawing_r_id.is_some() {
+            replaced.push("drawing");
+        }
+        if self.legacy_drawing_r_id.is_some() {
+            replaced.push("legacyDrawing");
+        }
+        let replaced_ids = self.preserved_elements.remove(&replaced);
+        for relation in self.preserved_relations.iter() {
+            let replaced = replaced_ids.contains(&relation.id)
+                || (!self.notes.is_empty()
+                    && (relation.is("comments") || relation.is("threadedComment")));
+            if !replaced {
+                relation.add_to(&mut self.relation_ship);
+            }
+        }
+    }
+
+    /// register a pivot table placed on this sheet, its parts are kept by the work book.
+    pub(crate) fn link_pivot_table(&mut self, number: usize) {
+        self.relation_ship.add_relation(
+            RS_PIVOT_TABLE,
+            &format!("../pivotTables/pivotTable{}.xml", number),
+            false,
+        );
+    }
+
+    /// every part of the package this sheet owns other than the sheet xml itself,
+    /// as (path in the package, content).
+    pub fn parts(&self, sheet_number: usize) -> Vec<(String, Vec<u8>)> {
+        let mut parts = vec![];
+        if !self.relation_ship.is_empty() {
+            parts.push((
+                format!("xl/worksheets/_rels/sheet{}.xml.rels", sheet_number),
+                self.relation_ship.to_xml().into_bytes(),
+            ));
+        }
+        if !self.drawing.is_empty() {
+            parts.extend(self.drawing.parts());
+        }
+        if !self.notes.is_empty() {
+            parts.push((
+                format!("xl/comments{}.xml", sheet_number),
+                self.notes.to_comments_xml().into_bytes(),
+            ));
+            parts.push((
+                format!("xl/drawings/vmlDrawing{}.vml", sheet_number),
+                self.notes.to_vml_xml().into_bytes(),
+            ));
+        }
+        if !self.threads.is_empty() {
+            parts.push((
+                format!("xl/threadedComments/threadedComment{}.xml", sheet_number),
+                self.threads.to_xml().into_bytes(),
+            ));
+        }
+        parts
+    }
+
+    /// link a cell to a url, email, file or a location in the work book (`Sheet2!A1`)
+    pub fn add_hyperlink(
+        &mut self,
+        cell_ref: &str,
+        target: &str,
+        tooltip: Option<&str>,
+    ) -> Result<(), &'static str> {
+        parse_cell_ref(cell_ref)?;
+        self.push_hyperlink(Hyperlink::new(cell_ref, target, tooltip));
+        Ok(())
+    }
+
+    pub(crate) fn push_hyperlink(&mut self, mut hyperlink: Hyperlink) {
+        // a cell has only one link, the target of a replaced one goes with it
+        if let Some(at) = self
+            .hyperlinks
+            .iter()
+            .position(|h| h.reference == hyperlink.reference)
+        {
+            if let Some(r_id) = self.hyperlinks.remove(at).r_id {
+                self.relation_ship.remove(&r_id);
+            }
+        }
+        if let HyperlinkTarget::External(target) = &hyperlink.target {
+            let r_id = self.relation_ship.add_relation(RS_HYPERLINK, target, true);
+            hyperlink.r_id = Some(r_id);
+        }
+        self.hyperlinks.push(hyperlink);
+    }
+
+    pub fn hyperlink_references(&self) -> HashSet<String> {
+        self.hyperlinks
+            .iter()
+            .map(|h| h.reference.clone())
+            .collect()
+    }
+
+    /// header of every page, or of the odd pages when an even page header is set.
+    pub fn set_header(&mut self, header: HeaderFooter) -> Result<(), &'static str> {
+        self.header_footers.odd_header = Some(header.to_code()?);
+        Ok(())
+    }
+
+    /// footer of every page, or of the odd pages when an even page footer is set.
+    pub fn set_footer(&mut self, footer: HeaderFooter) -> Result<(), &'static str> {
+        self.header_footers.odd_footer = Some(footer.to_code()?);
+        Ok(())
+    }
+
+    pub fn set_even_page_header(&mut self, header: HeaderFooter) -> Result<(), &'static str> {
+        self.header_footers.even_header = Some(header.to_code()?);
+        Ok(())
+    }
+
+    pub fn set_even_page_footer(&mut self, footer: HeaderFooter) -> Result<(), &'static str> {
+        self.header_footers.even_footer = Some(footer.to_code()?);
+        Ok(())
+    }
+
+    pub fn set_first_page_header(&mut self, header: HeaderFooter) -> Result<(), &'static str> {
+        self.header_footers.first_header = Some(header.to_code()?);
+        Ok(())
+    }
+
+    pub fn set_first_page_footer(&mut self, footer: HeaderFooter) -> Result<(), &'static str> {
+        self.header_footers.first_footer = Some(footer.to_code()?);
+        Ok(())
+    }
+
+    /// set how this sheet is printed.
+    pub fn page_setup(&mut self, page_setup: PageSetup) -> Result<(), &'static str> {
+        page_setup.validate()?;
+        self.page_setup = Some(page_setup);
+        Ok(())
+    }
+
+    pub fn get_page_setup(&self) -> Option<&PageSetup> {
+        self.page_setup.as_ref()
+    }
+
+    /// start a new page after the given row (1 based)
+    pub fn add_row_break(&mut self, row_number: usize) -> Result<(), &'static str> {
+        if row_number == 0 || row_number >= MAX_ROWS {
+            return Err("Invalid row break");
+        }
+        if !self.row_breaks.contains(&row_number) {
+            self.row_breaks.push(row_number);
+        }
+        Ok(())
+    }
+
+    /// start a new page after the given column (1 based)
+    pub fn add_column_break(&mut self, column_number: usize) -> Result<(), &'static str> {
+        if column_number == 0 || column_number >= MAX_COLUMNS {
+            return Err("Invalid column break");
+        }
+        if !self.column_breaks.contains(&column_number) {
+            self.column_breaks.push(column_number);
         }
+        Ok(())
     }
 
     // return the newly created blank row mut.
@@ -26,8 +419,38 @@ impl WorkSheet {
         self.rows.last_mut().unwrap()
     }
 
+    /// a row with the given number (1 based), rows are added top to bottom
+    /// so it must come after the rows added so far.
+    pub fn add_row(&mut self, row_number: usize) -> Result<&mut Row, &'static str> {
+        if row_number < self.next_row || row_number > MAX_ROWS {
+            return Err("Invalid row number");
+        }
+        self.next_row = row_number;
+        Ok(self.add_blank_row())
+    }
+
+    /// the number the next blank row gets
+    pub fn next_row_number(&self) -> usize {
+        self.next_row
+    }
+
+    /// add a row made apart, like `add_row` it must come after the rows added so far.
+    pub fn push_row(&mut self, row: Row) -> Result<&mut Row, &'static str> {
+        let row_number = row.get_row_number();
+        if row_number < self.next_row || row_number > MAX_ROWS {
+            return Err("Invalid row number");
+        }
+        self.next_row = row_number + 1;
+        self.rows.push(row);
+        Ok(self.rows.last_mut().unwrap())
+    }
+
     pub fn to_xml(self) -> String {
-        let mut writer = XmlWriter::new(Options::default());
+        // header and footer codes are text, they must not be indented.
+        let mut writer = XmlWriter::new(Options {
+            indent: Indent::None,
+            ..Default::default()
+        });
         writer.start_element("worksheet");
         writer.write_attribute(
             "xmlns",
@@ -37,14 +460,101 @@ impl WorkSheet {
             "xmlns:r",
             "http://schemas.openxmlformats.org/officeDocument/2006/relationships",
         );
+        let mut preserved = self.preserved_elements;
+        preserved.write_root_attributes(&mut writer);
+
+        // what is set on the sheet replaces the kept elements
+        if let Some(page_setup) = &self.page_setup {
+            preserved.remove(&["printOptions", "pageMargins", "pageSetup"]);
+            if page_setup.fit_to_page() {
+                preserved.remove(&["sheetPr"]);
+            }
+        }
+        if !self.header_footers.is_empty() {
+            preserved.remove(&["headerFooter"]);
+        }
+        if !self.row_breaks.is_empty() {
+            preserved.remove(&["rowBreaks"]);
+        }
+        if !self.column_breaks.is_empty() {
+            preserved.remove(&["colBreaks"]);
+        }
+        let ext_list = preserved.take("extLst");
+
+        if self.page_setup.as_ref().is_some_and(|p| p.fit_to_page()) {
+            writer.start_element("sheetPr");
+            writer.start_element("pageSetUpPr");
+            writer.write_attribute("fitToPage", "1");
+            writer.end_element();
+            writer.end_element();
+        }
 
+        preserved.write_before(&mut writer, &SHEET_ELEMENTS, "sheetData");
         writer.start_element("sheetData");
         for row in self.rows {
             row.to_xml(&mut writer);
         }
         writer.end_element();
 
+        preserved.write_before(&mut writer, &SHEET_ELEMENTS, "hyperlinks");
+        if !self.hyperlinks.is_empty() {
+            writer.start_element("hyperlinks");
+            for hyperlink in self.hyperlinks {
+                hyperlink.to_xml(&mut writer);
+            }
+            writer.end_element();
+        }
+
+        preserved.write_before(&mut writer, &SHEET_ELEMENTS, "printOptions");
+        if let Some(page_setup) = self.page_setup {
+            page_setup.to_xml(&mut writer);
+        }
+
+        preserved.write_before(&mut writer, &SHEET_ELEMENTS, "headerFooter");
+        self.header_footers.to_xml(&mut writer);
+
+        preserved.write_before(&mut writer, &SHEET_ELEMENTS, "rowBreaks");
+        write_breaks(&mut writer, "rowBreaks", self.row_breaks, MAX_COLUMNS - 1);
+        preserved.write_before(&mut writer, &SHEET_ELEMENTS, "colBreaks");
+        write_breaks(&mut writer, "colBreaks", self.column_breaks, MAX_ROWS - 1);
+
+        preserved.write_before(&mut writer, &SHEET_ELEMENTS, "drawing");
+        if let Some(r_id) = &self.drawing_r_id {
+            writer.start_element("drawing");
+            writer.write_attribute("r:id", r_id);
+            writer.end_element();
+        }
+
+        preserved.write_before(&mut writer, &SHEET_ELEMENTS, "legacyDrawing");
+        if let Some(r_id) = &self.legacy_drawing_r_id {
+            writer.start_element("legacyDrawing");
+            writer.write_attribute("r:id", r_id);
+            writer.end_element();
+        }
+
+        preserved.write_before(&mut writer, &SHEET_ELEMENTS, "");
+        write_sparklines(&mut writer, &self.name, &self.sparklines, ext_list.as_ref());
+
         writer.end_element();
         writer.end_document()
     }
 }
+
+// a break is placed after `id`, `max` is the last index along the other direction.
+fn write_breaks(writer: &mut XmlWriter, name: &str, mut breaks: Vec<usize>, max: usize) {
+    if breaks.is_empty() {
+        return;
+    }
+    breaks.sort();
+    writer.start_element(name);
+    writer.write_attribute("count", &breaks.len().to_string());
+    writer.write_attribute("manualBreakCount", &breaks.len().to_string());
+    for id in breaks {
+        writer.start_element("brk");
+        writer.write_attribute("id", &id.to_string());
+        writer.write_attribute("max", &max.to_string());
+        writer.write_attribute("man", "1");
+        writer.end_element();
+    }
+    writer.end_element();
+}
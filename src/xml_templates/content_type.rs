@@ -4,7 +4,10 @@ use xmlwriter::{Options, XmlWriter};
 
 pub struct ContentType {
     overrides: Vec<Override>,
+    // (extension, content type), other than rels and xml
+    defaults: Vec<(String, String)>,
     next_sheet_number: i32,
+    next_chart_sheet_number: i32,
 }
 
 struct Override {
@@ -16,6 +19,8 @@ static WORK_BOOK_CONTENT_TYPE: &str =
     "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml";
 static WORK_SHEET_CONTENT_TYPE: &str =
     "application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml";
+static CHART_SHEET_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.chartsheet+xml";
 static SS_CONTENT_TYPE: &str =
     "application/vnd.openxmlformats-officedocument.spreadsheetml.sharedStrings+xml";
 static RS_CONTENT_TYPE: &str = "application/vnd.openxmlformats-package.relationships+xml";
@@ -23,10 +28,39 @@ static RS_CONTENT_TYPE: &str = "application/vnd.openxmlformats-package.relations
 static STYLES_CONTENT_TYPE: &str =
     "application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml";
 
+static CORE_PROPS_CONTENT_TYPE: &str = "application/vnd.openxmlformats-package.core-properties+xml";
+static APP_PROPS_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.extended-properties+xml";
+static CUSTOM_PROPS_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.custom-properties+xml";
+
+static COMMENTS_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.comments+xml";
+static VML_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.vmlDrawing";
+static DRAWING_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.drawing+xml";
+static CHART_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.drawingml.chart+xml";
+static PIVOT_TABLE_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.pivotTable+xml";
+static PIVOT_CACHE_DEFINITION_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.pivotCacheDefinition+xml";
+static PIVOT_CACHE_RECORDS_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.pivotCacheRecords+xml";
+static THREADED_COMMENTS_CONTENT_TYPE: &str = "application/vnd.ms-excel.threadedcomments+xml";
+static PERSON_CONTENT_TYPE: &str = "application/vnd.ms-excel.person+xml";
+
+impl Default for ContentType {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ContentType {
     pub fn new() -> Self {
         ContentType {
             next_sheet_number: 1,
+            next_chart_sheet_number: 1,
+            defaults: vec![],
             overrides: vec![
                 // work book
                 Override {
@@ -51,10 +85,28 @@ impl ContentType {
                     content_type: STYLES_CONTENT_TYPE.to_string(),
                     part_name: String::from("/xl/styles.xml"),
                 },
+                // document properties
+                Override {
+                    content_type: CORE_PROPS_CONTENT_TYPE.to_string(),
+                    part_name: String::from("/docProps/core.xml"),
+                },
+                Override {
+                    content_type: APP_PROPS_CONTENT_TYPE.to_string(),
+                    part_name: String::from("/docProps/app.xml"),
+                },
             ],
         }
     }
 
+    // a read work book keeps its type, eg. macro enabled.
+    pub fn set_work_book_type(&mut self, content_type: &str) {
+        for ov in self.overrides.iter_mut() {
+            if ov.part_name == "/xl/workbook.xml" {
+                ov.content_type = content_type.to_string();
+            }
+        }
+    }
+
     // add a new sheet information with the work book
     pub fn add_sheet(&mut self) {
         self.overrides.push(Override {
@@ -66,6 +118,88 @@ impl ContentType {
         self.next_sheet_number += 1;
     }
 
+    // chart sheets are numbered apart from the work sheets.
+    pub fn add_chart_sheet(&mut self) {
+        self.overrides.push(Override {
+            content_type: CHART_SHEET_CONTENT_TYPE.to_string(),
+            part_name: format!("/xl/chartsheets/sheet{}.xml", self.next_chart_sheet_number),
+        });
+        self.next_chart_sheet_number += 1;
+    }
+
+    // custom properties are optional, only register when there is some.
+    pub fn add_custom_properties(&mut self) {
+        self.overrides.push(Override {
+            content_type: CUSTOM_PROPS_CONTENT_TYPE.to_string(),
+            part_name: String::from("/docProps/custom.xml"),
+        });
+    }
+
+    // register a content type for every part with this extension, once.
+    pub fn add_default(&mut self, extension: &str, content_type: &str) {
+        if !self.defaults.iter().any(|(e, _)| e == extension) {
+            self.defaults
+                .push((extension.to_string(), content_type.to_string()));
+        }
+    }
+
+    pub fn add_override(&mut self, part_name: &str, content_type: &str) {
+        self.overrides.push(Override {
+            content_type: content_type.to_string(),
+            part_name: part_name.to_string(),
+        });
+    }
+
+    // notes of a sheet, xl/commentsN.xml and its vml shapes.
+    pub fn add_comments(&mut self, sheet_number: usize) {
+        self.add_override(
+            &format!("/xl/comments{}.xml", sheet_number),
+            COMMENTS_CONTENT_TYPE,
+        );
+        self.add_default("vml", VML_CONTENT_TYPE);
+    }
+
+    pub fn add_drawing(&mut self, drawing_number: usize) {
+        self.add_override(
+            &format!("/xl/drawings/drawing{}.xml", drawing_number),
+            DRAWING_CONTENT_TYPE,
+        );
+    }
+
+    pub fn add_chart(&mut self, chart_number: usize) {
+        self.add_override(
+            &format!("/xl/charts/chart{}.xml", chart_number),
+            CHART_CONTENT_TYPE,
+        );
+    }
+
+    // a pivot table with its cache, they share the number.
+    pub fn add_pivot_table(&mut self, number: usize) {
+        self.add_override(
+            &format!("/xl/pivotTables/pivotTable{}.xml", number),
+            PIVOT_TABLE_CONTENT_TYPE,
+        );
+        self.add_override(
+            &format!("/xl/pivotCache/pivotCacheDefinition{}.xml", number),
+            PIVOT_CACHE_DEFINITION_CONTENT_TYPE,
+        );
+        self.add_override(
+            &format!("/xl/pivotCache/pivotCacheRecords{}.xml", number),
+            PIVOT_CACHE_RECORDS_CONTENT_TYPE,
+        );
+    }
+
+    pub fn add_threaded_comments(&mut self, sheet_number: usize) {
+        self.add_override(
+            &format!("/xl/threadedComments/threadedComment{}.xml", sheet_number),
+            THREADED_COMMENTS_CONTENT_TYPE,
+        );
+    }
+
+    pub fn add_persons(&mut self) {
+        self.add_override("/xl/persons/person.xml", PERSON_CONTENT_TYPE);
+    }
+
     // retunr the complete content type.
     pub fn to_xml(self) -> String {
         let mut writer = XmlWriter::new(Options {
@@ -94,6 +228,13 @@ impl ContentType {
         writer.write_attribute("ContentType", "application/xml");
         writer.end_element();
 
+        for (extension, content_type) in self.defaults {
+            writer.start_element("Default");
+            writer.write_attribute("Extension", &extension);
+            writer.write_attribute("ContentType", &content_type);
+            writer.end_element();
+        }
+
         // loop over each ovrrides
         for ov in self.overrides {
             writer.start_element("Override");
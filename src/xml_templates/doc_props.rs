@@ -0,0 +1,425 @@
+// in this file create the docProps/core.xml, docProps/app.xml and docProps/custom.xml
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use xmlwriter::{Indent, Options, XmlWriter};
+
+use crate::escape::escape_xml;
+
+// core
+static CP_XMLNS: &str = "http://schemas.openxmlformats.org/package/2006/metadata/core-properties";
+static DC_XMLNS: &str = "http://purl.org/dc/elements/1.1/";
+static DCTERMS_XMLNS: &str = "http://purl.org/dc/terms/";
+static DCMITYPE_XMLNS: &str = "http://purl.org/dc/dcmitype/";
+static XSI_XMLNS: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
+// app and custom
+static EP_XMLNS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/extended-properties";
+static CUSTOM_XMLNS: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/custom-properties";
+static VT_XMLNS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes";
+
+// values are read as is, so the text must not be indented.
+fn writer_options() -> Options {
+    Options {
+        indent: Indent::None,
+        ..Default::default()
+    }
+}
+
+// every custom property share this format id, pid start from 2.
+static CUSTOM_FMTID: &str = "{D5CDD505-2E9C-101B-9397-08002B2CF9AE}";
+
+/// Document level properties, shown by excel under File > Info.
+/// `created` and `modified` are W3CDTF date times (eg. `2024-01-31T10:00:00Z`),
+/// when not given the time of saving is used.
+#[derive(Debug, Default, Clone)]
+pub struct DocProperties {
+    pub title: Option<String>,
+    pub subject: Option<String>,
+    pub author: Option<String>,
+    pub manager: Option<String>,
+    pub company: Option<String>,
+    pub category: Option<String>,
+    pub keywords: Option<String>,
+    pub comments: Option<String>,
+    pub status: Option<String>,
+    pub created: Option<String>,
+    pub modified: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum CustomPropertyValue {
+    Text(String),
+    Number(f64),
+    // W3CDTF date time, eg. 2024-01-31T10:00:00Z
+    Date(String),
+    Bool(bool),
+}
+
+#[derive(Debug)]
+struct CustomProperty {
+    name: String,
+    value: CustomPropertyValue,
+}
+
+#[derive(Debug, Default)]
+pub struct CustomProperties {
+    properties: Vec<CustomProperty>,
+}
+
+impl CustomProperties {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add a custom property, a property with the same name is replaced.
+    pub fn add(&mut self, name: &str, value: CustomPropertyValue) -> Result<(), &'static str> {
+        match &value {
+            CustomPropertyValue::Number(v) if !v.is_finite() => {
+                return Err("Custom property number must be finite");
+            }
+            CustomPropertyValue::Date(v) if !is_w3cdtf(v) => {
+                return Err("Custom property date must be a W3CDTF date time");
+            }
+            _ => {}
+        }
+        if let Some(property) = self.properties.iter_mut().find(|p| p.name == name) {
+            property.value = value;
+        } else {
+            self.properties.push(CustomProperty {
+                name: name.to_string(),
+                value,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.properties.is_empty()
+    }
+
+    pub fn to_xml(self) -> String {
+        let mut writer = XmlWriter::new(writer_options());
+        writer.write_declaration();
+        writer.start_element("Properties");
+        writer.write_attribute("xmlns", CUSTOM_XMLNS);
+        writer.write_attribute("xmlns:vt", VT_XMLNS);
+
+        for (i, property) in self.properties.into_iter().enumerate() {
+            writer.start_element("property");
+            writer.write_attribute("fmtid", CUSTOM_FMTID);
+            writer.write_attribute("pid", &(i + 2).to_string());
+            writer.write_attribute("name", &escape_xml(&property.name));
+            match property.value {
+                CustomPropertyValue::Text(v) => {
+                    writer.start_element("vt:lpwstr");
+                    writer.write_text(&escape_xml(&v));
+                }
+                CustomPropertyValue::Number(v) => {
+                    writer.start_element("vt:r8");
+                    writer.write_text(&v.to_string());
+                }
+                CustomPropertyValue::Date(v) => {
+                    writer.start_element("vt:filetime");
+                    writer.write_text(&escape_xml(&v));
+                }
+                CustomPropertyValue::Bool(v) => {
+                    writer.start_element("vt:bool");
+                    writer.write_text(&v.to_string());
+                }
+            }
+            writer.end_element();
+            writer.end_element();
+        }
+
+        writer.end_document()
+    }
+}
+
+impl DocProperties {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let dates = [&self.created, &self.modified];
+        if dates.into_iter().flatten().any(|date| !is_w3cdtf(date)) {
+            return Err("Created and modified must be W3CDTF date times");
+        }
+        Ok(())
+    }
+
+    pub fn to_core_xml(&self) -> String {
+        let mut writer = XmlWriter::new(writer_options());
+        writer.write_declaration();
+        writer.start_element("cp:coreProperties");
+        writer.write_attribute("xmlns:cp", CP_XMLNS);
+        writer.write_attribute("xmlns:dc", DC_XMLNS);
+        writer.write_attribute("xmlns:dcterms", DCTERMS_XMLNS);
+        writer.write_attribute("xmlns:dcmitype", DCMITYPE_XMLNS);
+        writer.write_attribute("xmlns:xsi", XSI_XMLNS);
+
+        write_optional(&mut writer, "dc:title", &self.title);
+        write_optional(&mut writer, "dc:subject", &self.subject);
+        write_optional(&mut writer, "dc:creator", &self.author);
+        write_optional(&mut writer, "cp:keywords", &self.keywords);
+        write_optional(&mut writer, "dc:description", &self.comments);
+        write_optional(&mut writer, "cp:lastModifiedBy", &self.author);
+
+        let created = self.created.clone().unwrap_or_else(now_w3cdtf);
+        let modified = self.modified.clone().unwrap_or_else(|| created.clone());
+        writer.start_element("dcterms:created");
+        writer.write_attribute("xsi:type", "dcterms:W3CDTF");
+        writer.write_text(&created);
+        writer.end_element();
+        writer.start_element("dcterms:modified");
+        writer.write_attribute("xsi:type", "dcterms:W3CDTF");
+        writer.write_text(&modified);
+        writer.end_element();
+
+        write_optional(&mut writer, "cp:category", &self.category);
+        write_optional(&mut writer, "cp:contentStatus", &self.status);
+
+        writer.end_document()
+    }
+
+    // app.xml also carry the sheet names as the titles of parts.
+    pub fn to_app_xml(&self, sheet_names: &[&str], chart_sheet_names: &[&str]) -> String {
+        let mut writer = XmlWriter::new(writer_options());
+        writer.write_declaration();
+        writer.start_element("Properties");
+        writer.write_attribute("xmlns", EP_XMLNS);
+        writer.write_attribute("xmlns:vt", VT_XMLNS);
+
+        writer.start_element("Application");
+        writer.write_text("Microsoft Excel");
+        writer.end_element();
+        writer.start_element("DocSecurity");
+        writer.write_text("0");
+        writer.end_element();
+        writer.start_element("ScaleCrop");
+        writer.write_text("false");
+        writer.end_element();
+
+        // heading pairs, one pair for the worksheets and one for the chart sheets
+        let mut headings = vec![("Worksheets", sheet_names.len())];
+        if !chart_sheet_names.is_empty() {
+            headings.push(("Charts", chart_sheet_names.len()));
+        }
+        writer.start_element("HeadingPairs");
+        writer.start_element("vt:vector");
+        writer.write_attribute("size", &(headings.len() * 2).to_string());
+        writer.write_attribute("baseType", "variant");
+        for (heading, count) in headings {
+            writer.start_element("vt:variant");
+            writer.start_element("vt:lpstr");
+            writer.write_text(heading);
+            writer.end_element();
+            writer.end_element();
+            writer.start_element("vt:variant");
+            writer.start_element("vt:i4");
+            writer.write_text(&count.to_string());
+            writer.end_element();
+            writer.end_element();
+        }
+        writer.end_element();
+        writer.end_element();
+
+        // titles of parts, in the order of the heading pairs
+        let titles = sheet_names.len() + chart_sheet_names.len();
+        writer.start_element("TitlesOfParts");
+        writer.start_element("vt:vector");
+        writer.write_attribute("size", &titles.to_string());
+        writer.write_attribute("baseType", "lpstr");
+        for name in sheet_names.iter().chain(chart_sheet_names) {
+            writer.start_element("vt:lpstr");
+            writer.write_text(&escape_xml(name));
+            writer.end_element();
+        }
+        writer.end_element();
+        writer.end_element();
+
+        write_optional(&mut writer, "Manager", &self.manager);
+        write_optional(&mut writer, "Company", &self.company);
+
+        writer.start_element("LinksUpToDate");
+        writer.write_text("false");
+        writer.end_element();
+        writer.start_element("SharedDoc");
+        writer.write_text("false");
+        writer.end_element();
+        writer.start_element("HyperlinksChanged");
+        writer.write_text("false");
+        writer.end_element();
+        writer.start_element("AppVersion");
+        writer.write_text("12.0000");
+        writer.end_element();
+
+        writer.end_document()
+    }
+}
+
+fn write_optional(writer: &mut XmlWriter, name: &str, value: &Option<String>) {
+    if let Some(v) = value {
+        writer.start_element(name);
+        writer.write_text(&escape_xml(v));
+        writer.end_element();
+    }
+}
+
+/// current utc time in W3CDTF, eg. 2024-01-31T10:00:00Z
+pub fn now_w3cdtf() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    unix_to_w3cdtf(secs)
+}
+
+pub fn unix_to_w3cdtf(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+/// whether `text` is a W3CDTF date or date time: `2024`, `2024-01`, `2024-01-31`,
+/// `2024-01-31T10:00Z`, `2024-01-31T10:00:00+01:00` or `2024-01-31T10:00:00.5Z`.
+pub fn is_w3cdtf(text: &str) -> bool {
+    let (date, time) = match text.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+    let parts: Vec<&str> = date.split('-').collect();
+    if parts.len() > 3
+        || !parts
+            .iter()
+            .zip([4, 2, 2])
+            .all(|(p, len)| is_digits(p, len))
+    {
+        return false;
+    }
+    let number = |i: usize| parts.get(i).map_or(1, |p| p.parse::<u32>().unwrap_or(0));
+    let (year, month, day) = (number(0) as i64, number(1), number(2));
+    // 2023-02-30 comes back as an other day
+    if !(1..=12).contains(&month)
+        || civil_from_days(days_from_civil(year, month, day)) != (year, month, day)
+    {
+        return false;
+    }
+    let Some(time) = time else {
+        return true;
+    };
+    // a time needs a full date and a zone, `Z`, `+hh:mm` or `-hh:mm`
+    let (clock, zone) = match time.strip_suffix('Z') {
+        Some(clock) => (clock, None),
+        None => match time.len().checked_sub(6).map(|at| time.split_at(at)) {
+            Some((clock, zone)) if zone.starts_with(['+', '-']) => (clock, Some(&zone[1..])),
+            _ => return false,
+        },
+    };
+    if parts.len() != 3 || !zone.is_none_or(|zone| is_clock(zone, false)) {
+        return false;
+    }
+    is_clock(clock, true)
+}
+
+fn is_digits(text: &str, len: usize) -> bool {
+    text.len() == len && text.bytes().all(|b| b.is_ascii_digit())
+}
+
+// hh:mm, with seconds `hh:mm:ss` or `hh:mm:ss.s` when `seconds` is set
+fn is_clock(text: &str, seconds: bool) -> bool {
+    let parts: Vec<&str> = text.split(':').collect();
+    let in_range = |part: &str, max: u32| is_digits(part, 2) && part.parse::<u32>().unwrap() <= max;
+    let seconds_ok = match parts.get(2) {
+        None => true,
+        Some(_) if !seconds => false,
+        Some(part) => {
+            let (whole, fraction) = part.split_once('.').unwrap_or((part, "0"));
+            in_range(whole, 59)
+                && !fraction.is_empty()
+                && fraction.bytes().all(|b| b.is_ascii_digit())
+        }
+    };
+    (2..=3).contains(&parts.len()) && in_range(parts[0], 23) && in_range(parts[1], 59) && seconds_ok
+}
+
+// days since 1970-01-01 to (year, month, day), Howard Hinnant's algorithm.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// (year, month, day) to days since 1970-01-01, the inverse of `civil_from_days`.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn w3cdtf_dates_are_checked() {
+        for text in [
+            "2024",
+            "2024-01",
+            "2024-02-29",
+            "2024-01-31T10:00Z",
+            "2024-01-31T10:00:00Z",
+            "2024-01-31T10:00:00.25+01:00",
+        ] {
+            assert!(is_w3cdtf(text), "{}", text);
+        }
+        for text in [
+            "",
+            "24-01-31",
+            "2023-02-29",
+            "2024-13-01",
+            "2024-01-31T10:00:00",
+            "2024-01-31T24:00:00Z",
+            "2024-01-31T10:00:00+1:00",
+            "2024-01T10:00Z",
+            "31/01/2024",
+        ] {
+            assert!(!is_w3cdtf(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn custom_properties_are_checked() {
+        let mut properties = CustomProperties::new();
+        assert!(properties
+            .add("Rate", CustomPropertyValue::Number(f64::NAN))
+            .is_err());
+        assert!(properties
+            .add("Due", CustomPropertyValue::Date("tomorrow".to_string()))
+            .is_err());
+        assert!(properties.is_empty());
+        let date = CustomPropertyValue::Date("2024-01-31T10:00:00Z".to_string());
+        assert!(properties.add("Due", date).is_ok());
+    }
+}
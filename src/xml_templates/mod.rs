@@ -1,4 +1,5 @@
 pub mod content_type;
+pub mod doc_props;
 pub mod shared_string;
 pub mod relation_ship;
 pub mod style;
\ No newline at end of file
@@ -1,30 +1,139 @@
 use xmlwriter::{Options, XmlWriter};
 
+use crate::escape::escape_xml;
+
+#[derive(Debug)]
 pub struct RelationShip {
     next_seq_number: u32,
+    relations: Vec<Relation>,
+}
+
+#[derive(Debug)]
+// a relationship of a part (eg. a worksheet) to an other part or an external target.
+struct Relation {
+    id: String,
+    rel_type: String,
+    target: String,
+    external: bool,
 }
 
 //root level
 static RSS_XMLNS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
 static RS_OFFICE_DOCUMENT: &str =
     "http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument";
+static RS_CORE_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties";
+static RS_EXTENDED_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties";
+static RS_CUSTOM_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/custom-properties";
 static RS_OFFICE_DOCUMENT_THEME: &str =
     "http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument/theme";
 
 // package level
 static RS_OFFICE_DOCUMENT_WS_PAK: &str =
     "http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet";
+static RS_OFFICE_DOCUMENT_CS_PAK: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/chartsheet";
 static RS_OFFICE_DOCUMENT_SS_PAK: &str =
     "http://schemas.openxmlformats.org/officeDocument/2006/relationships/sharedStrings";
 static RS_OFFICE_DOCUMENT_STYLE: &str =
     "http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles";
+
+// part level
+pub static RS_HYPERLINK: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink";
+pub static RS_COMMENTS: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/comments";
+pub static RS_VML_DRAWING: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/vmlDrawing";
+pub static RS_DRAWING: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/drawing";
+pub static RS_IMAGE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image";
+pub static RS_CHART: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/chart";
+pub static RS_PIVOT_TABLE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/pivotTable";
+pub static RS_PIVOT_CACHE_DEFINITION: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/pivotCacheDefinition";
+pub static RS_PIVOT_CACHE_RECORDS: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/pivotCacheRecords";
+pub static RS_THREADED_COMMENT: &str =
+    "http://schemas.microsoft.com/office/2017/10/relationships/threadedComment";
+static RS_PERSON: &str = "http://schemas.microsoft.com/office/2017/10/relationships/person";
+
+impl Default for RelationShip {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RelationShip {
     pub fn new() -> Self {
-        RelationShip { next_seq_number: 1 }
+        RelationShip {
+            next_seq_number: 1,
+            relations: vec![],
+        }
+    }
+
+    /// add a relationship to this part and return its id.
+    pub fn add_relation(&mut self, rel_type: &'static str, target: &str, external: bool) -> String {
+        let id = self.next_id();
+        self.relations.push(Relation {
+            id: id.clone(),
+            rel_type: rel_type.to_string(),
+            target: target.to_string(),
+            external,
+        });
+        id
+    }
+
+    /// add a relationship kept from a read package, its id is left as it is.
+    pub fn add_preserved(&mut self, id: &str, rel_type: &str, target: &str, external: bool) {
+        self.relations.push(Relation {
+            id: id.to_string(),
+            rel_type: rel_type.to_string(),
+            target: target.to_string(),
+            external,
+        });
+    }
+
+    /// drop a relationship, its id is not given out again.
+    pub fn remove(&mut self, id: &str) {
+        self.relations.retain(|r| r.id != id);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relations.is_empty()
+    }
+
+    /// the .rels of a part, with every relationship added by `add_relation`
+    pub fn to_xml(&self) -> String {
+        let mut writer = XmlWriter::new(Options::default());
+        writer.write_declaration();
+
+        writer.start_element("Relationships");
+        writer.write_attribute("xmlns", RSS_XMLNS);
+        self.write_relations(&mut writer);
+        writer.end_document()
+    }
+
+    fn write_relations(&self, writer: &mut XmlWriter) {
+        for relation in self.relations.iter() {
+            writer.start_element("Relationship");
+            writer.write_attribute("Id", &relation.id);
+            writer.write_attribute("Type", &relation.rel_type);
+            writer.write_attribute("Target", &escape_xml(&relation.target));
+            if relation.external {
+                writer.write_attribute("TargetMode", "External");
+            }
+            writer.end_element();
+        }
     }
 
     #[inline]
-    pub fn to_root_xml(mut self) -> String {
+    pub fn to_root_xml(mut self, has_custom_properties: bool) -> String {
         // xl/workbook.xml
         let mut writer = XmlWriter::new(Options::default());
 
@@ -37,12 +146,44 @@ impl RelationShip {
         writer.write_attribute("Id", self.next_id().as_str());
         writer.write_attribute("Type", RS_OFFICE_DOCUMENT);
         writer.write_attribute("Target", "xl/workbook.xml");
-        // TODO: add more.
+        writer.end_element();
+
+        // document properties
+        writer.start_element("Relationship");
+        writer.write_attribute("Id", self.next_id().as_str());
+        writer.write_attribute("Type", RS_CORE_PROPERTIES);
+        writer.write_attribute("Target", "docProps/core.xml");
+        writer.end_element();
+
+        writer.start_element("Relationship");
+        writer.write_attribute("Id", self.next_id().as_str());
+        writer.write_attribute("Type", RS_EXTENDED_PROPERTIES);
+        writer.write_attribute("Target", "docProps/app.xml");
+        writer.end_element();
+
+        if has_custom_properties {
+            writer.start_element("Relationship");
+            writer.write_attribute("Id", self.next_id().as_str());
+            writer.write_attribute("Type", RS_CUSTOM_PROPERTIES);
+            writer.write_attribute("Target", "docProps/custom.xml");
+            writer.end_element();
+        }
+
+        // kept from a read package, eg. a thumbnail
+        self.write_relations(&mut writer);
+
         writer.end_document()
     }
 
     #[inline]
-    pub fn to_work_book_rel_xml(mut self, no_of_themes: u32, no_of_sheets: usize) -> String {
+    pub fn to_work_book_rel_xml(
+        mut self,
+        no_of_themes: u32,
+        no_of_sheets: usize,
+        no_of_chart_sheets: usize,
+        no_of_pivot_caches: usize,
+        has_persons: bool,
+    ) -> String {
         let mut writer = XmlWriter::new(Options::default());
 
         writer.start_element("Relationships");
@@ -56,6 +197,27 @@ impl RelationShip {
             writer.end_element();
         }
 
+        // chart sheets come right after the work sheets, see WorkBook::create_sheets
+        for i in 1..=no_of_chart_sheets {
+            writer.start_element("Relationship");
+            writer.write_attribute("Id", self.next_id().as_str());
+            writer.write_attribute("Type", RS_OFFICE_DOCUMENT_CS_PAK);
+            writer.write_attribute("Target", format!("chartsheets/sheet{}.xml", i).as_str());
+            writer.end_element();
+        }
+
+        // pivot caches follow the sheets, see WorkBook::create_pivot_caches
+        for i in 1..=no_of_pivot_caches {
+            writer.start_element("Relationship");
+            writer.write_attribute("Id", self.next_id().as_str());
+            writer.write_attribute("Type", RS_PIVOT_CACHE_DEFINITION);
+            writer.write_attribute(
+                "Target",
+                format!("pivotCache/pivotCacheDefinition{}.xml", i).as_str(),
+            );
+            writer.end_element();
+        }
+
         // styles
         writer.start_element("Relationship");
         writer.write_attribute("Id", self.next_id().as_str());
@@ -81,6 +243,18 @@ impl RelationShip {
         writer.write_attribute("Target", "sharedStrings.xml");
         writer.end_element();
 
+        // authors of threaded comments
+        if has_persons {
+            writer.start_element("Relationship");
+            writer.write_attribute("Id", self.next_id().as_str());
+            writer.write_attribute("Type", RS_PERSON);
+            writer.write_attribute("Target", "persons/person.xml");
+            writer.end_element();
+        }
+
+        // kept from a read package, eg. chart sheets, a vba project or custom xml
+        self.write_relations(&mut writer);
+
         writer.end_document()
     }
 
@@ -1,27 +1,118 @@
 use std::{collections::HashMap, mem};
 
-use xmlwriter::Options;
+use xmlwriter::{Indent, Options};
+
+use crate::{
+    escape::write_string,
+    rich_text::{self, Run},
+};
+
+#[derive(Debug)]
+enum SharedString {
+    Plain(String),
+    Rich(Vec<Run>),
+}
+
+/// how the text of string cells is stored, see `WorkBook::set_string_strategy`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StringStrategy {
+    /// once in the shared strings of the work book, the cells refer to it; the smaller
+    /// file when strings repeat.
+    #[default]
+    Shared,
+    /// in the cells themselves, no table of the strings is held while writing.
+    Inline,
+    /// shared until the part of unique strings among the ones added goes over `threshold`
+    /// (0.0 to 1.0), after that a string not shared yet is inline.
+    Auto { threshold: f64 },
+}
+
+// strings added before `Auto` looks at how many are unique
+static AUTO_SAMPLE: u32 = 1000;
 
 #[derive(Debug)]
 pub struct SharedStrings {
     // first one is the index, second one is the total counter
     s_map: HashMap<String, u32>,
-    s_vec: Vec<String>,
+    // rich strings keyed on the text and the font of every run
+    rich_map: HashMap<String, u32>,
+    s_vec: Vec<SharedString>,
     pub next_index: u32,
     pub total_counter: u32,
+    strategy: StringStrategy,
+    // strings offered so far, and whether `Auto` went over its threshold
+    offered: u32,
+    inlining: bool,
 }
 
 static SST_XMLNS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
 
+impl Default for SharedStrings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SharedStrings {
     pub fn new() -> Self {
         SharedStrings {
             next_index: 0,
             s_map: HashMap::new(),
+            rich_map: HashMap::new(),
             s_vec: Vec::new(),
             total_counter: 0,
+            strategy: StringStrategy::Shared,
+            offered: 0,
+            inlining: false,
         }
     }
+
+    pub fn set_strategy(&mut self, strategy: StringStrategy) -> Result<(), &'static str> {
+        if let StringStrategy::Auto { threshold } = strategy {
+            if !(0.0..=1.0).contains(&threshold) {
+                return Err("String threshold must be between 0.0 and 1.0");
+            }
+        }
+        self.strategy = strategy;
+        Ok(())
+    }
+
+    // whether a string goes to the table, `known` when it is there already.
+    fn shares(&mut self, known: bool) -> bool {
+        self.offered += 1;
+        match self.strategy {
+            StringStrategy::Shared => true,
+            StringStrategy::Inline => false,
+            StringStrategy::Auto { threshold } => {
+                if !self.inlining && self.offered >= AUTO_SAMPLE {
+                    let unique = self.s_vec.len() as f64 / self.offered as f64;
+                    self.inlining = unique > threshold;
+                }
+                known || !self.inlining
+            }
+        }
+    }
+
+    /// add the string when the strategy shares it, like `add_string`.
+    /// returns false for a string to be written in the cell.
+    pub fn share_string(&mut self, st: &mut String) -> bool {
+        let known = self.s_map.contains_key(st);
+        if !self.shares(known) {
+            return false;
+        }
+        self.add_string(st);
+        true
+    }
+
+    /// add the rich string when the strategy shares it and return its index, the runs
+    /// are left as they were for a string to be written in the cell.
+    pub fn share_rich_string(&mut self, runs: &mut Vec<Run>) -> Option<u32> {
+        let known = self.rich_map.contains_key(&rich_key(runs));
+        if !self.shares(known) {
+            return None;
+        }
+        Some(self.add_rich_string(mem::take(runs)))
+    }
     pub fn add_string(&mut self, st: &mut String) -> u32 {
         // increase the counter by 1 in any case.
         self.total_counter += 1;
@@ -32,7 +123,7 @@ impl SharedStrings {
         } else {
             // insert and increament the next_counter by 1
             let key = mem::replace(st, self.next_index.to_string());
-            self.s_vec.push(key.clone());
+            self.s_vec.push(SharedString::Plain(key.clone()));
             self.s_map.insert(key, self.next_index);
             {
                 let temp = self.next_index;
@@ -41,21 +132,66 @@ impl SharedStrings {
             }
         }
     }
+    /// add a string made of formatted runs, returns its index.
+    /// the same text with the same formatting shares one entry.
+    pub fn add_rich_string(&mut self, runs: Vec<Run>) -> u32 {
+        self.total_counter += 1;
+        let key = rich_key(&runs);
+        if let Some(ind) = self.rich_map.get(&key) {
+            return *ind;
+        }
+        let index = self.next_index;
+        self.s_vec.push(SharedString::Rich(runs));
+        self.rich_map.insert(key, index);
+        self.next_index += 1;
+        index
+    }
     pub fn to_xml(self) -> String {
-        let mut writer = xmlwriter::XmlWriter::new(Options::default());
+        // no indent, it would end up in the text kept with xml:space="preserve"
+        let mut writer = xmlwriter::XmlWriter::new(Options {
+            indent: Indent::None,
+            ..Default::default()
+        });
         // todo: write declarion manually.
         writer.start_element("sst");
         writer.write_attribute("xmlns", SST_XMLNS);
         writer.write_attribute("count", &self.total_counter.to_string());
-        writer.write_attribute("uniqueCount", &self.s_map.len());
+        writer.write_attribute("uniqueCount", &self.s_vec.len());
         // order the hashmap by value
         for elem in self.s_vec.iter() {
             writer.start_element("si");
-            writer.start_element("t");
-            writer.write_text(&elem);
-            writer.end_element();
+            match elem {
+                SharedString::Plain(text) => write_string(&mut writer, text),
+                SharedString::Rich(runs) => rich_text::write_runs(&mut writer, runs),
+            }
             writer.end_element();
         }
         writer.end_document()
     }
 }
+
+// rich strings are keyed on the text and the font of every run
+fn rich_key(runs: &[Run]) -> String {
+    runs.iter()
+        .map(|r| r.unique_id())
+        .collect::<Vec<String>>()
+        .join("\u{0}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_thresholds_are_a_part_of_one() {
+        let mut shared_strings = SharedStrings::new();
+        for threshold in [0.0, 0.5, 1.0] {
+            let strategy = StringStrategy::Auto { threshold };
+            assert!(shared_strings.set_strategy(strategy).is_ok());
+        }
+        for threshold in [-0.1, 1.5, f64::NAN, f64::INFINITY] {
+            let strategy = StringStrategy::Auto { threshold };
+            assert!(shared_strings.set_strategy(strategy).is_err());
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use core::fmt;
 use std::{collections::HashMap, fmt::Debug};
 
-use crate::traits::XMLString;
+use crate::{
+    escape::escape_xml,
+    package::{PreservedElement, PreservedElements, PreservedNode},
+    traits::XMLString,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FontStyle {
     name: String,
     size: u8,
@@ -11,6 +15,8 @@ pub struct FontStyle {
     italic: bool,
     strike: bool,
     undeline: Option<UnderLine>,
+    // ARGB, eg. FFFF0000 for red
+    color: Option<String>,
 }
 
 impl FontStyle {
@@ -47,14 +53,60 @@ impl FontStyle {
         self
     }
 
-    fn unqiue_id(&self) -> String {
+    /// font color as ARGB hex, eg. `FFFF0000`
+    pub fn color(mut self, argb: &str) -> Self {
+        self.color = Some(argb.to_ascii_uppercase());
+        self
+    }
+
+    /// the font as run properties (`<rPr>`), used by rich text in comments and strings.
+    pub fn to_run_properties_xml(&self, writer: &mut xmlwriter::XmlWriter) {
+        writer.start_element("rPr");
+        if self.bold {
+            writer.start_element("b");
+            writer.end_element();
+        }
+        if self.italic {
+            writer.start_element("i");
+            writer.end_element();
+        }
+        if self.strike {
+            writer.start_element("strike");
+            writer.end_element();
+        }
+        if let Some(v) = &self.undeline {
+            writer.start_element("u");
+            match v {
+                UnderLine::Single => writer.write_attribute("val", "single"),
+                UnderLine::Double => writer.write_attribute("val", "double"),
+                UnderLine::SingleAcccounting => writer.write_attribute("val", "singleAccounting"),
+                UnderLine::DoubleAccouting => writer.write_attribute("val", "doubleAccounting"),
+            }
+            writer.end_element();
+        }
+        writer.start_element("sz");
+        writer.write_attribute("val", &self.size.to_string());
+        writer.end_element();
+        if let Some(color) = &self.color {
+            writer.start_element("color");
+            writer.write_attribute("rgb", color);
+            writer.end_element();
+        }
+        writer.start_element("rFont");
+        writer.write_attribute("val", &self.name);
+        writer.end_element();
+        writer.end_element();
+    }
+
+    pub(crate) fn unqiue_id(&self) -> String {
         let und: String = self
             .undeline
             .as_ref()
             .map_or_else(|| "u_none".to_string(), |v| format!("{:?}", v));
+        let color = self.color.as_deref().unwrap_or("c_none");
         format!(
-            "{}-{}-{}-{}-{}-{}",
-            self.size, self.name, self.bold, self.italic, self.strike, und
+            "{}-{}-{}-{}-{}-{}-{}",
+            self.size, self.name, self.bold, self.italic, self.strike, und, color
         )
     }
 }
@@ -73,6 +125,13 @@ impl XMLString for FontStyle {
         writer.write_attribute("val", &self.name);
         writer.end_element();
 
+        // color
+        if let Some(color) = &self.color {
+            writer.start_element("color");
+            writer.write_attribute("rgb", color);
+            writer.end_element();
+        }
+
         // bold
         if self.bold {
             writer.start_element("b");
@@ -137,16 +196,22 @@ impl Default for FontStyle {
             italic: false,
             strike: false,
             undeline: None,
+            color: None,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 struct CellXf {
     font_id: usize,
     fill_id: usize,
     border_id: usize,
     num_fmt_id: usize,
+    // index in to cellStyleXfs, the named cell style this xf is based on.
+    xf_id: usize,
+    // the xf of a read style sheet this one is made from, its alignment, protection
+    // and other attributes are written with it.
+    base: Option<usize>,
 }
 impl Default for CellXf {
     fn default() -> Self {
@@ -155,6 +220,8 @@ impl Default for CellXf {
             fill_id: 0,
             num_fmt_id: 164,
             border_id: 0,
+            xf_id: 0,
+            base: None,
         }
     }
 }
@@ -168,17 +235,90 @@ impl CellXf {
         }
     }
 
+    // the xf at `index` of a read style sheet
+    fn read(index: usize, element: &PreservedElement) -> Self {
+        let id = |name: &str| {
+            element
+                .attribute(name)
+                .and_then(|id| id.parse().ok())
+                .unwrap_or(0)
+        };
+        Self {
+            font_id: id("fontId"),
+            fill_id: id("fillId"),
+            border_id: id("borderId"),
+            num_fmt_id: id("numFmtId"),
+            xf_id: id("xfId"),
+            base: Some(index),
+        }
+    }
+
     fn unique_id(&self) -> String {
-        format!("{}{}{}", self.font_id, self.fill_id, self.border_id)
+        format!(
+            "{}-{}-{}-{}-{}-{:?}",
+            self.font_id, self.fill_id, self.border_id, self.num_fmt_id, self.xf_id, self.base
+        )
     }
+
+    // an xf made from a read one, a changed font or number format is applied over
+    // the named style.
+    fn write_read(self, writer: &mut xmlwriter::XmlWriter, element: &PreservedElement) {
+        let ids = [
+            ("numFmtId", self.num_fmt_id),
+            ("fontId", self.font_id),
+            ("fillId", self.fill_id),
+            ("borderId", self.border_id),
+            ("xfId", self.xf_id),
+        ];
+        let changed = |name: &str, id: usize| element.attribute(name) != Some(&id.to_string());
+        let font_changed = changed("fontId", self.font_id);
+        let num_fmt_changed = changed("numFmtId", self.num_fmt_id);
+        writer.start_element("xf");
+        for (name, id) in ids {
+            writer.write_attribute(name, &id.to_string());
+        }
+        for (name, value) in element.attributes.iter() {
+            match name.as_str() {
+                "numFmtId" | "fontId" | "fillId" | "borderId" | "xfId" => {}
+                "applyFont" if font_changed => {}
+                "applyNumberFormat" if num_fmt_changed => {}
+                _ => writer.write_attribute(name, &escape_xml(value)),
+            }
+        }
+        if font_changed {
+            writer.write_attribute("applyFont", "1");
+        }
+        if num_fmt_changed {
+            writer.write_attribute("applyNumberFormat", "1");
+        }
+        // alignment and protection
+        for child in element.elements() {
+            child.write(writer);
+        }
+        writer.end_element();
+    }
+}
+
+/// the fonts, number formats and cell xfs of a read style sheet. they keep their ids,
+/// the cells, named styles and differential formats refer to them.
+#[derive(Debug, Default)]
+pub struct ReadStyles {
+    // a font as it was and as this crate holds it
+    pub fonts: Vec<(PreservedElement, FontStyle)>,
+    pub num_fmts: Vec<(usize, String)>,
+    pub cell_xfs: Vec<PreservedElement>,
+    // fills, borders, named styles, differential formats, ..
+    pub elements: PreservedElements,
 }
 
 impl XMLString for CellXf {
     fn to_xml(self, writer: &mut xmlwriter::XmlWriter) {
-        println!("{:?}", self);
         writer.start_element("xf");
         writer.write_attribute("fontId", &self.font_id.to_string());
         writer.write_attribute("numFmtId", &self.num_fmt_id.to_string());
+        if self.xf_id != 0 {
+            writer.write_attribute("xfId", &self.xf_id.to_string());
+        }
 
         writer.write_attribute("applyFont", "true");
         writer.write_attribute("applyBorder", "false");
@@ -193,9 +333,17 @@ impl XMLString for CellXf {
 pub struct Style {
     next_unique_font_count: usize,
     fonts_map: HashMap<String, (usize, FontStyle)>,
-    next_unique_xf_count: usize,
-    cell_xfs_map: HashMap<String, (usize, CellXf)>,
+    cell_xfs: Vec<CellXf>,
+    cell_xfs_map: HashMap<String, usize>,
     num_fmts: Vec<NumFmt>,
+    // fonts and cell xfs of a read style sheet, written first as they were. a font
+    // keeps the unique id of its `FontStyle`.
+    read_fonts: Vec<(PreservedElement, String)>,
+    read_cell_xfs: Vec<PreservedElement>,
+    // font of the built in "Hyperlink" cell style, once a hyperlink is used.
+    hyperlink_font_id: Option<usize>,
+    // differential formats, table styles, .. of a read style sheet
+    preserved: PreservedElements,
 }
 
 impl Default for Style {
@@ -212,38 +360,111 @@ impl Default for Style {
 
         let default_cell_xf = CellXf::new(0, 164);
         let mut cell_xfs_map = HashMap::new();
-        cell_xfs_map.insert(default_cell_xf.unique_id(), (0, default_cell_xf));
+        cell_xfs_map.insert(default_cell_xf.unique_id(), 0);
 
         Self {
             next_unique_font_count: fonts_map.len(),
-            next_unique_xf_count: cell_xfs_map.len(),
             fonts_map,
+            cell_xfs: vec![default_cell_xf],
             cell_xfs_map,
             num_fmts: vec![NumFmt::new(164, "General")],
+            read_fonts: vec![],
+            read_cell_xfs: vec![],
+            hyperlink_font_id: None,
+            preserved: PreservedElements::default(),
         }
     }
 }
 
 static SS_XMLNS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
 
+// number formats excel knows by id, they are not written to the style sheet.
+static BUILT_IN_NUM_FMTS: [(usize, &str); 28] = [
+    (0, "General"),
+    (1, "0"),
+    (2, "0.00"),
+    (3, "#,##0"),
+    (4, "#,##0.00"),
+    (9, "0%"),
+    (10, "0.00%"),
+    (11, "0.00E+00"),
+    (12, "# ?/?"),
+    (13, "# ??/??"),
+    (14, "mm-dd-yy"),
+    (15, "d-mmm-yy"),
+    (16, "d-mmm"),
+    (17, "mmm-yy"),
+    (18, "h:mm AM/PM"),
+    (19, "h:mm:ss AM/PM"),
+    (20, "h:mm"),
+    (21, "h:mm:ss"),
+    (22, "m/d/yy h:mm"),
+    (37, "#,##0 ;(#,##0)"),
+    (38, "#,##0 ;[Red](#,##0)"),
+    (39, "#,##0.00;(#,##0.00)"),
+    (40, "#,##0.00;[Red](#,##0.00)"),
+    (45, "mm:ss"),
+    (46, "[h]:mm:ss"),
+    (47, "mmss.0"),
+    (48, "##0.0E+0"),
+    (49, "@"),
+];
+
+/// the format code of a built in number format id
+pub fn built_in_number_format(id: usize) -> Option<&'static str> {
+    BUILT_IN_NUM_FMTS
+        .iter()
+        .find(|(i, _)| *i == id)
+        .map(|(_, code)| *code)
+}
+
 impl Style {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// start from a read style sheet: its fonts, number formats and cell xfs keep their
+    /// ids and the ones added come after them. fills, borders, named styles, differential
+    /// formats and table styles are written back as they were.
+    pub(crate) fn preserve(&mut self, read: ReadStyles) {
+        self.preserved = read.elements;
+        if read.cell_xfs.is_empty() {
+            return;
+        }
+        self.num_fmts = read
+            .num_fmts
+            .iter()
+            .map(|(id, code)| NumFmt::new(*id, code))
+            .collect();
+        self.fonts_map.clear();
+        for (index, (element, font)) in read.fonts.into_iter().enumerate() {
+            let unique_id = font.unqiue_id();
+            self.fonts_map
+                .entry(unique_id.clone())
+                .or_insert((index, font));
+            self.read_fonts.push((element, unique_id));
+        }
+        self.next_unique_font_count = self.read_fonts.len();
+        self.cell_xfs.clear();
+        self.cell_xfs_map.clear();
+        for (index, element) in read.cell_xfs.iter().enumerate() {
+            let cell_xf = CellXf::read(index, element);
+            self.cell_xfs_map
+                .entry(cell_xf.unique_id())
+                .or_insert(index);
+            self.cell_xfs.push(cell_xf);
+        }
+        self.read_cell_xfs = read.cell_xfs;
+    }
+
     fn add_font(&mut self, font: &FontStyle) -> usize {
         // first check check if the font is already in the fonts_map
         // if no add the font to the fonts_map and fonts and return the index.
 
         if let Some(&index) = self.fonts_map.get(&font.unqiue_id()).as_ref() {
-            return index.0;
+            index.0
         } else {
-            let _fonst = FontStyle::new()
-                .size(font.size)
-                .name(&font.name)
-                .bold(font.bold)
-                .italic(font.italic)
-                .strike(font.strike)
-                .underline(font.undeline.clone());
+            let _fonst = font.clone();
 
             let index = self.next_unique_font_count;
             self.fonts_map
@@ -252,34 +473,137 @@ impl Style {
             index
         }
     }
-    // after call to add_font now we have font  and other ids
-    pub fn add_cell_xf(&mut self, font: Option<&FontStyle>) -> usize {
-        let font_id = match font {
-            Some(font) => self.add_font(font),
-            None => 0,
-        };
+    // formats of the style sheet keep their id, then built in formats keep theirs and
+    // others are numbered after the last one, "General" (164) in a new style sheet.
+    fn add_num_fmt(&mut self, format_code: &str) -> usize {
+        if let Some(num_fmt) = self.num_fmts.iter().find(|n| n.format_code == format_code) {
+            return num_fmt.format_id;
+        }
+        if let Some(id) = BUILT_IN_NUM_FMTS
+            .iter()
+            .find(|(_, code)| *code == format_code)
+            .map(|(id, _)| *id)
+        {
+            return id;
+        }
+        let id = self
+            .num_fmts
+            .iter()
+            .map(|n| n.format_id + 1)
+            .max()
+            .unwrap_or(164)
+            .max(164);
+        self.num_fmts.push(NumFmt::new(id, format_code));
+        id
+    }
 
-        let cell_xf = CellXf::new(font_id, 164);
-        if let Some(&index) = self.cell_xfs_map.get(&cell_xf.unique_id()).as_ref() {
-            index.0
-        } else {
-            let index = self.next_unique_xf_count;
+    // the format code a number format id shows as, None for General or an id not known.
+    fn format_code(&self, id: usize) -> Option<&str> {
+        self.num_fmts
+            .iter()
+            .find(|n| n.format_id == id)
+            .map(|n| n.format_code.as_str())
+            .or_else(|| built_in_number_format(id))
+            .filter(|code| *code != "General")
+    }
 
-            let cell_xf = CellXf::new(font_id, 164);
+    /// the cell xf of a font and number format. `base_xf` is the xf of a read style sheet
+    /// the cell had, its fill, border, alignment and protection are kept.
+    pub fn add_cell_xf(
+        &mut self,
+        font: Option<&FontStyle>,
+        number_format: Option<&str>,
+        base_xf: Option<usize>,
+    ) -> usize {
+        let base = base_xf
+            .and_then(|index| self.cell_xfs.get(index))
+            .filter(|cell_xf| cell_xf.base.is_some())
+            .copied();
+        let font_id = match (font, base) {
+            // the font the read xf has, even when an other font of the style sheet
+            // reads the same
+            (Some(font), Some(base))
+                if self
+                    .read_fonts
+                    .get(base.font_id)
+                    .is_some_and(|(_, unique_id)| *unique_id == font.unqiue_id()) =>
+            {
+                base.font_id
+            }
+            (Some(font), _) => self.add_font(font),
+            (None, _) => 0,
+        };
+        let num_fmt_id = match (number_format, base) {
+            (format_code, Some(base)) if self.format_code(base.num_fmt_id) == format_code => {
+                base.num_fmt_id
+            }
+            (Some(format_code), _) => self.add_num_fmt(format_code),
+            (None, _) => self.add_num_fmt("General"),
+        };
 
-            self.cell_xfs_map
-                .insert(cell_xf.unique_id(), (self.next_unique_xf_count, cell_xf));
-            self.next_unique_xf_count += 1;
-            index
+        let cell_xf = match base {
+            Some(base) => CellXf {
+                font_id,
+                num_fmt_id,
+                ..base
+            },
+            None => CellXf::new(font_id, num_fmt_id),
+        };
+        self.push_cell_xf(cell_xf)
+    }
+
+    fn push_cell_xf(&mut self, cell_xf: CellXf) -> usize {
+        if let Some(&index) = self.cell_xfs_map.get(&cell_xf.unique_id()) {
+            return index;
         }
+        let index = self.cell_xfs.len();
+        self.cell_xfs_map.insert(cell_xf.unique_id(), index);
+        self.cell_xfs.push(cell_xf);
+        index
     }
 
-    pub fn to_xml(self) -> String {
-        println!("{:?}", self.fonts_map);
+    // the named style xf of the built in "Hyperlink" style of a read style sheet
+    fn read_hyperlink_style(&self) -> Option<usize> {
+        self.preserved
+            .get("cellStyles")?
+            .elements()
+            .find(|cell_style| cell_style.attribute("builtinId") == Some("8"))?
+            .attribute("xfId")?
+            .parse()
+            .ok()
+    }
 
+    /// cell xf of the built in "Hyperlink" cell style (blue, underlined).
+    pub fn add_hyperlink_xf(&mut self) -> usize {
+        let font = FontStyle::new()
+            .color("FF0563C1")
+            .underline(Some(UnderLine::Single));
+        let font_id = self.add_font(&font);
+        // a read style sheet may have the style, else it comes after its named styles
+        let xf_id = match self.read_hyperlink_style() {
+            Some(xf_id) => xf_id,
+            None => {
+                self.hyperlink_font_id = Some(font_id);
+                self.preserved
+                    .get("cellStyleXfs")
+                    .map_or(1, |xfs| xfs.elements().count())
+            }
+        };
+
+        let num_fmt_id = self.add_num_fmt("General");
+        let cell_xf = CellXf {
+            xf_id,
+            ..CellXf::new(font_id, num_fmt_id)
+        };
+        self.push_cell_xf(cell_xf)
+    }
+
+    pub fn to_xml(self) -> String {
         let mut writer = xmlwriter::XmlWriter::new(xmlwriter::Options::default());
         writer.start_element("styleSheet");
         writer.write_attribute("xmlns", SS_XMLNS);
+        let mut preserved = self.preserved;
+        preserved.write_root_attributes(&mut writer);
 
         // write numFmts
         writer.start_element("numFmts");
@@ -289,70 +613,136 @@ impl Style {
         }
         writer.end_element();
 
-        // write fonts
+        // write fonts, the read ones as they were
         writer.start_element("fonts");
-        writer.write_attribute("count", &self.fonts_map.len().to_string());
+        writer.write_attribute("count", &self.next_unique_font_count.to_string());
         let mut fonts_vec: Vec<Option<FontStyle>> = Vec::with_capacity(self.fonts_map.len());
         // fill the fonts_vec with None
-        for _ in 0..self.fonts_map.len() {
+        for _ in 0..self.next_unique_font_count {
             fonts_vec.push(None);
         }
         // iterate over the fonts_map and write the fonts
         for font_style in self.fonts_map.into_values() {
             fonts_vec[font_style.0] = Some(font_style.1);
         }
-        // now that fonts are ordered
-        for font in fonts_vec.into_iter() {
-            if let Some(font) = font {
-                font.to_xml(&mut writer);
+        for (index, font) in fonts_vec.into_iter().enumerate() {
+            match (self.read_fonts.get(index), font) {
+                (Some((element, _)), _) => element.write(&mut writer),
+                (None, Some(font)) => font.to_xml(&mut writer),
+                (None, None) => {}
             }
         }
         writer.end_element();
 
-        // fills
-        writer.start_element("fills");
-        writer.write_attribute("count", "1");
-        writer.start_element("fill");
-        writer.start_element("patternFill");
-        writer.write_attribute("patternType", "none");
-        writer.end_element();
-        writer.end_element();
-        writer.end_element();
+        // fills and borders of a read style sheet, else the default ones
+        let fills = preserved.take("fills");
+        let borders = preserved.take("borders");
+        if let Some(fills) = fills {
+            fills.write(&mut writer);
+        } else {
+            writer.start_element("fills");
+            writer.write_attribute("count", "1");
+            writer.start_element("fill");
+            writer.start_element("patternFill");
+            writer.write_attribute("patternType", "none");
+            writer.end_element();
+            writer.end_element();
+            writer.end_element();
+        }
+        if let Some(borders) = borders {
+            borders.write(&mut writer);
+        } else {
+            writer.start_element("borders");
+            writer.write_attribute("count", "1");
+            writer.start_element("border");
+            writer.start_element("left");
+            writer.end_element();
+            writer.start_element("right");
+            writer.end_element();
+            writer.start_element("top");
+            writer.end_element();
+            writer.start_element("bottom");
+            writer.end_element();
+            writer.start_element("diagonal");
+            writer.end_element();
+            writer.end_element();
+            writer.end_element();
+        }
 
-        // borders
-        writer.start_element("borders");
-        writer.write_attribute("count", "1");
-        writer.start_element("border");
-        writer.start_element("left");
-        writer.end_element();
-        writer.start_element("right");
-        writer.end_element();
-        writer.start_element("top");
-        writer.end_element();
-        writer.start_element("bottom");
-        writer.end_element();
-        writer.start_element("diagonal");
-        writer.end_element();
-        writer.end_element();
-        writer.end_element();
+        // named cell styles of a read style sheet, the built in hyperlink style is
+        // added after them when it is used and not there.
+        let mut cell_style_xfs = preserved.take("cellStyleXfs");
+        let mut cell_styles = preserved.take("cellStyles");
+        if let (Some(font_id), Some(xfs)) = (self.hyperlink_font_id, cell_style_xfs.as_mut()) {
+            let xf_id = xfs.elements().count().to_string();
+            let font_id = font_id.to_string();
+            let attributes = [
+                ("numFmtId", "0"),
+                ("fontId", font_id.as_str()),
+                ("fillId", "0"),
+                ("borderId", "0"),
+            ];
+            push_element(xfs, "xf", &attributes);
+            let cell_styles = cell_styles.get_or_insert_with(|| PreservedElement {
+                name: "cellStyles".to_string(),
+                attributes: vec![],
+                children: vec![],
+            });
+            let attributes = [
+                ("name", "Hyperlink"),
+                ("xfId", xf_id.as_str()),
+                ("builtinId", "8"),
+            ];
+            push_element(cell_styles, "cellStyle", &attributes);
+        }
+        if let Some(xfs) = cell_style_xfs {
+            xfs.write(&mut writer);
+        } else if let Some(font_id) = self.hyperlink_font_id {
+            writer.start_element("cellStyleXfs");
+            writer.write_attribute("count", "2");
+            for font_id in [0, font_id] {
+                writer.start_element("xf");
+                writer.write_attribute("numFmtId", "164");
+                writer.write_attribute("fontId", &font_id.to_string());
+                writer.write_attribute("fillId", "0");
+                writer.write_attribute("borderId", "0");
+                writer.end_element();
+            }
+            writer.end_element();
+        }
 
         // write cellXfs
         writer.start_element("cellXfs");
-        writer.write_attribute("count", &self.cell_xfs_map.len().to_string());
-        let mut cell_xfs_vec: Vec<Option<CellXf>> = Vec::with_capacity(self.cell_xfs_map.len());
-        for _ in 0..self.cell_xfs_map.len() {
-            cell_xfs_vec.push(None);
-        }
-        for cell_xf in self.cell_xfs_map.into_values() {
-            cell_xfs_vec[cell_xf.0] = Some(cell_xf.1);
-        }
-        for cell_xf in cell_xfs_vec {
-            if let Some(cell_xf) = cell_xf {
-                cell_xf.to_xml(&mut writer);
+        writer.write_attribute("count", &self.cell_xfs.len().to_string());
+        for cell_xf in self.cell_xfs {
+            match cell_xf.base.and_then(|base| self.read_cell_xfs.get(base)) {
+                Some(element) => cell_xf.write_read(&mut writer, element),
+                None => cell_xf.to_xml(&mut writer),
             }
         }
         writer.end_element();
 
+        if let Some(cell_styles) = cell_styles {
+            cell_styles.write(&mut writer);
+        } else if self.hyperlink_font_id.is_some() {
+            writer.start_element("cellStyles");
+            writer.write_attribute("count", "2");
+            writer.start_element("cellStyle");
+            writer.write_attribute("name", "Normal");
+            writer.write_attribute("xfId", "0");
+            writer.write_attribute("builtinId", "0");
+            writer.end_element();
+            writer.start_element("cellStyle");
+            writer.write_attribute("name", "Hyperlink");
+            writer.write_attribute("xfId", "1");
+            writer.write_attribute("builtinId", "8");
+            writer.end_element();
+            writer.end_element();
+        }
+
+        // dxfs, tableStyles, colors and extLst come last
+        preserved.write_before(&mut writer, &[], "");
+
         writer.end_document()
     }
 }
@@ -376,7 +766,24 @@ impl XMLString for NumFmt {
     fn to_xml(self, writer: &mut xmlwriter::XmlWriter) {
         writer.start_element("numFmt");
         writer.write_attribute("numFmtId", &self.format_id.to_string());
-        writer.write_attribute("formatCode", &self.format_code);
+        writer.write_attribute("formatCode", &escape_xml(&self.format_code));
         writer.end_element();
     }
 }
+
+// add a child element to a read list and count it
+fn push_element(list: &mut PreservedElement, name: &str, attributes: &[(&str, &str)]) {
+    list.children.push(PreservedNode::Element(PreservedElement {
+        name: name.to_string(),
+        attributes: attributes
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        children: vec![],
+    }));
+    let count = list.elements().count().to_string();
+    match list.attributes.iter_mut().find(|(name, _)| name == "count") {
+        Some((_, value)) => *value = count,
+        None => list.attributes.push(("count".to_string(), count)),
+    }
+}